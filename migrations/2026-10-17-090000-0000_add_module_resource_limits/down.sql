@@ -0,0 +1,4 @@
+ALTER TABLE modules
+    DROP COLUMN max_memory_bytes,
+    DROP COLUMN fuel_limit,
+    DROP COLUMN timeout_ms;
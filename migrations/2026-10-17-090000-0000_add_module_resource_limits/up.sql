@@ -0,0 +1,4 @@
+ALTER TABLE modules
+    ADD COLUMN max_memory_bytes BIGINT NOT NULL DEFAULT 67108864,
+    ADD COLUMN fuel_limit BIGINT NOT NULL DEFAULT 1000000000,
+    ADD COLUMN timeout_ms INTEGER NOT NULL DEFAULT 5000;
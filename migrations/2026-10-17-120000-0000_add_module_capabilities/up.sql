@@ -0,0 +1 @@
+ALTER TABLE modules ADD COLUMN capabilities JSONB NOT NULL DEFAULT '{}';
@@ -0,0 +1 @@
+DROP TABLE interceptor_bindings;
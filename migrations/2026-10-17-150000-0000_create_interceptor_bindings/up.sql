@@ -0,0 +1,8 @@
+CREATE TABLE interceptor_bindings (
+    module_id VARCHAR(64) PRIMARY KEY NOT NULL REFERENCES modules(id) ON DELETE CASCADE,
+    priority INTEGER NOT NULL DEFAULT 0,
+    definition_ids TEXT[] NOT NULL DEFAULT '{}',
+    definition_types TEXT[] NOT NULL DEFAULT '{}'
+);
+
+CREATE INDEX idx_interceptor_bindings_priority ON interceptor_bindings(priority);
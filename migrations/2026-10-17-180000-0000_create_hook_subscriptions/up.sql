@@ -0,0 +1,6 @@
+CREATE TABLE hook_subscriptions (
+    module_id VARCHAR(64) PRIMARY KEY NOT NULL REFERENCES modules(id) ON DELETE CASCADE,
+    events TEXT[] NOT NULL DEFAULT '{}'
+);
+
+CREATE INDEX idx_hook_subscriptions_events ON hook_subscriptions USING GIN (events);
@@ -0,0 +1,7 @@
+CREATE TABLE proxy_bindings (
+    definition_id VARCHAR(64) PRIMARY KEY NOT NULL REFERENCES definitions(id) ON DELETE CASCADE,
+    module_id VARCHAR(64) NOT NULL REFERENCES modules(id) ON DELETE CASCADE,
+    upstream_url TEXT NOT NULL
+);
+
+CREATE INDEX idx_proxy_bindings_module_id ON proxy_bindings(module_id);
@@ -0,0 +1 @@
+DROP TABLE language_bindings;
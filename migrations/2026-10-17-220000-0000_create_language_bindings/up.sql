@@ -0,0 +1,6 @@
+CREATE TABLE language_bindings (
+    definition_type VARCHAR(64) PRIMARY KEY NOT NULL,
+    module_id VARCHAR(64) NOT NULL REFERENCES modules(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_language_bindings_module_id ON language_bindings(module_id);
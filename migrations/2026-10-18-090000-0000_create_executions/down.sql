@@ -0,0 +1,2 @@
+DROP TABLE executions;
+DROP TYPE execution_status;
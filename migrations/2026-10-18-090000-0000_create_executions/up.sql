@@ -0,0 +1,17 @@
+CREATE TYPE execution_status AS ENUM ('running', 'completed', 'failed');
+
+CREATE TABLE executions (
+    id UUID PRIMARY KEY NOT NULL,
+    module_id VARCHAR(64) NOT NULL REFERENCES modules(id) ON DELETE CASCADE,
+    status execution_status NOT NULL DEFAULT 'running',
+    exit_code INTEGER,
+    stdout TEXT NOT NULL DEFAULT '',
+    stderr TEXT NOT NULL DEFAULT '',
+    error TEXT,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMPTZ,
+    duration_ms BIGINT
+);
+
+CREATE INDEX idx_executions_module_id_started_at ON executions(module_id, started_at DESC);
+CREATE INDEX idx_executions_status ON executions(status);
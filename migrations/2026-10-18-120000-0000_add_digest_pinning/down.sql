@@ -0,0 +1,2 @@
+ALTER TABLE modules DROP COLUMN is_pinned;
+ALTER TABLE definitions DROP COLUMN is_pinned;
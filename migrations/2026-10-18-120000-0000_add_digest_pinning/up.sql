@@ -0,0 +1,2 @@
+ALTER TABLE definitions ADD COLUMN is_pinned BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE modules ADD COLUMN is_pinned BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1 @@
+ALTER TABLE executions ADD COLUMN heartbeat_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
@@ -0,0 +1 @@
+ALTER TABLE hook_subscriptions DROP COLUMN approvals;
@@ -0,0 +1 @@
+ALTER TABLE hook_subscriptions ADD COLUMN approvals TEXT[] NOT NULL DEFAULT '{}';
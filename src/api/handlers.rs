@@ -1,18 +1,45 @@
 use crate::{
+    db,
     errors::AppError,
-    models::{Definition, Module, UpdateDefinitionRequest, UpdateModuleRequest},
+    events::LifecycleEvent,
+    mcp::{self, jsonrpc, sessions},
+    models::{
+        Capabilities, Definition, Execution, HookSubscription, HookSubscriptionRequest,
+        InterceptorBinding, InterceptorBindingRequest, LanguageBinding, LanguageBindingRequest,
+        Module, ModuleType, ProxyBinding, ProxyBindingRequest, UpdateDefinitionRequest,
+        UpdateModuleRequest, UpgradeOptions,
+    },
+    runtime::CommandInput,
+    s3,
     services::{
+        configuration_services,
         definitions_services::{self, DefinitionFilter, DefinitionPayload},
+        executions_services::{self, ExecutionFilter},
+        hooks_services, http_definitions_services, interceptors_services, invocation_services,
+        languages_services,
         modules_services::{self, ModuleFilter, ModulePayload},
+        proxies_services,
+        secrets_services::{self, RotationReport, Secrets},
     },
+    utils::regex_utils,
     AppState,
 };
 use axum::{
+    body::Bytes,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
     Json,
 };
+use futures::{stream, StreamExt};
 use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, time::Duration};
+use tokio::sync::broadcast;
+use uuid::Uuid;
 use validator::Validate;
 
 #[derive(Debug, Deserialize, Validate)]
@@ -25,6 +52,37 @@ pub struct InstallDefinitionRequest {
 pub struct InstallModuleRequest {
     #[validate(url)]
     pub source: String,
+    /// Capabilities the operator approves for the module.
+    #[serde(default)]
+    pub capabilities: Capabilities,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InvokeDefinitionRequest {
+    #[serde(default = "empty_arguments")]
+    pub arguments: Value,
+}
+
+fn empty_arguments() -> Value {
+    json!({})
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateExecutionRequest {
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Code or input for the sandbox, passed on its stdin.
+    #[serde(default)]
+    pub stdin: String,
+    /// How long to wait for the run to finish before answering with the
+    /// still-running execution record.
+    #[serde(default = "default_wait_ms")]
+    #[validate(range(max = 60000))]
+    pub wait_ms: u64,
+}
+
+fn default_wait_ms() -> u64 {
+    10_000
 }
 
 pub async fn list_definitions(
@@ -53,10 +111,16 @@ pub async fn create_definition(
         &mut db_pool.get()?,
         &http_client,
         &s3_client,
+        &state.upload_options,
         &payload,
+        |event| hooks_services::approve(&state, event),
     )
     .await?;
 
+    state.events.publish(LifecycleEvent::DefinitionCreated {
+        definition: definition.clone(),
+    });
+
     Ok((StatusCode::CREATED, Json(definition)))
 }
 
@@ -73,26 +137,36 @@ pub async fn get_definition(
     Ok(Json(definition))
 }
 
+/// Answers a delete whose row is gone: `204` when every stored object went
+/// with it, otherwise the report listing the objects left behind.
+fn deletion_response(report: s3::DeletionReport) -> Response {
+    if report.failed.is_empty() {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        (StatusCode::OK, Json(report)).into_response()
+    }
+}
+
 pub async fn delete_definition(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<StatusCode, AppError> {
+) -> Result<Response, AppError> {
     let mut conn = state.db_pool.get()?;
     let id_for_thread = id.clone();
 
-    let rows_deleted = tokio::task::spawn_blocking(move || {
+    let definition = tokio::task::spawn_blocking(move || {
         definitions_services::delete_definition(&mut conn, &id_for_thread)
     })
-    .await??;
+    .await??
+    .ok_or_else(|| AppError::not_found(format!("Definition with id '{}' not found", id)))?;
 
-    if rows_deleted == 0 {
-        return Err(AppError::not_found(format!(
-            "Definition with id '{}' not found",
-            id
-        )));
-    }
+    state
+        .events
+        .publish(LifecycleEvent::DefinitionDeleted { id });
 
-    Ok(StatusCode::NO_CONTENT)
+    let report = definitions_services::delete_objects(&state.s3_client, &definition).await;
+
+    Ok(deletion_response(report))
 }
 
 pub async fn update_definition(
@@ -105,10 +179,39 @@ pub async fn update_definition(
     let update = request.into_changeset();
     let mut conn = state.db_pool.get()?;
 
-    let definition = tokio::task::spawn_blocking(move || {
-        definitions_services::update_definition(&mut conn, &id, &update)
-    })
-    .await??;
+    let (previous, definition) = db::transaction_checked(
+        &mut conn,
+        |conn| definitions_services::update_definition(conn, &id, &update).map_err(AppError::from),
+        |(previous, definition)| {
+            let enabled = (!previous.is_enabled && definition.is_enabled).then(|| {
+                LifecycleEvent::DefinitionEnabled {
+                    definition: definition.clone(),
+                }
+            });
+            async {
+                match enabled {
+                    Some(event) => hooks_services::approve(&state, event).await,
+                    None => Ok(()),
+                }
+            }
+        },
+    )
+    .await?;
+
+    state.events.publish(LifecycleEvent::DefinitionUpdated {
+        definition: definition.clone(),
+    });
+    if previous.is_enabled != definition.is_enabled {
+        state.events.publish(if definition.is_enabled {
+            LifecycleEvent::DefinitionEnabled {
+                definition: definition.clone(),
+            }
+        } else {
+            LifecycleEvent::DefinitionDisabled {
+                definition: definition.clone(),
+            }
+        });
+    }
 
     Ok(Json(definition))
 }
@@ -127,32 +230,291 @@ pub async fn install_definition(
         &mut db_pool.get()?,
         &http_client,
         &s3_client,
+        &state.upload_options,
         &request.source,
+        |event| hooks_services::approve(&state, event),
     )
     .await?;
 
+    state.events.publish(LifecycleEvent::DefinitionCreated {
+        definition: definition.clone(),
+    });
+
     Ok((StatusCode::CREATED, Json(definition)))
 }
 
 pub async fn upgrade_definition(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(options): Query<UpgradeOptions>,
 ) -> Result<Json<Definition>, AppError> {
     let db_pool = state.db_pool.clone();
     let http_client = state.http_client.clone();
     let s3_client = state.s3_client.clone();
 
-    let definition = definitions_services::update_definition_from_source(
+    let (definition, upgraded) = definitions_services::update_definition_from_source(
         &mut db_pool.get()?,
         &http_client,
         &s3_client,
+        &state.upload_options,
         &id,
+        options,
+        |event| hooks_services::approve(&state, event),
     )
     .await?;
 
+    if upgraded {
+        state.events.publish(LifecycleEvent::DefinitionUpgraded {
+            definition: definition.clone(),
+        });
+    }
+
     Ok(Json(definition))
 }
 
+pub async fn invoke_definition(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<InvokeDefinitionRequest>,
+) -> Result<Json<Value>, AppError> {
+    let result = invocation_services::invoke_definition(&state, &id, request.arguments).await?;
+
+    Ok(Json(json!({ "result": result })))
+}
+
+pub async fn get_definition_proxy(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<ProxyBinding>, AppError> {
+    let mut conn = state.db_pool.get()?;
+
+    let binding =
+        tokio::task::spawn_blocking(move || proxies_services::get_binding(&mut conn, &id))
+            .await??;
+
+    Ok(Json(binding))
+}
+
+pub async fn set_definition_proxy(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<ProxyBindingRequest>,
+) -> Result<Json<ProxyBinding>, AppError> {
+    request.validate()?;
+
+    let definition = load_definition(&state, id).await?;
+    let module = load_module(&state, request.module_id.clone()).await?;
+    if module.type_ != ModuleType::Proxy {
+        return Err(AppError::bad_request(format!(
+            "Module '{}' is not a proxy",
+            module.id
+        )));
+    }
+    proxies_services::ensure_reachable(&module, &request.upstream_url)?;
+
+    let binding = request.into_binding(definition.id);
+    let mut conn = state.db_pool.get()?;
+
+    let binding =
+        tokio::task::spawn_blocking(move || proxies_services::set_binding(&mut conn, &binding))
+            .await??;
+
+    Ok(Json(binding))
+}
+
+pub async fn delete_definition_proxy(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let mut conn = state.db_pool.get()?;
+    let id_for_thread = id.clone();
+
+    let rows_deleted = tokio::task::spawn_blocking(move || {
+        proxies_services::delete_binding(&mut conn, &id_for_thread)
+    })
+    .await??;
+
+    if rows_deleted == 0 {
+        return Err(AppError::not_found(format!(
+            "Definition '{}' has no proxy binding",
+            id
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn load_definition(state: &AppState, id: String) -> Result<Definition, AppError> {
+    let mut conn = state.db_pool.get()?;
+
+    let definition =
+        tokio::task::spawn_blocking(move || definitions_services::get_definition(&mut conn, &id))
+            .await??;
+
+    Ok(definition)
+}
+
+pub async fn get_definition_configuration(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, AppError> {
+    let definition = load_definition(&state, id).await?;
+
+    let configuration = configuration_services::get_configuration(
+        &state.s3_client,
+        "definitions",
+        &definition.configuration_object_key,
+    )
+    .await?;
+
+    Ok(Json(configuration))
+}
+
+pub async fn set_definition_configuration(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(configuration): Json<Value>,
+) -> Result<Json<Value>, AppError> {
+    let definition = load_definition(&state, id).await?;
+
+    let configuration = configuration_services::set_configuration(
+        &state.s3_client,
+        "definitions",
+        &definition.configuration_object_key,
+        configuration,
+    )
+    .await?;
+
+    Ok(Json(configuration))
+}
+
+pub async fn update_definition_configuration(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(patch): Json<Value>,
+) -> Result<Json<Value>, AppError> {
+    let definition = load_definition(&state, id).await?;
+
+    let configuration = configuration_services::update_configuration(
+        &state.s3_client,
+        "definitions",
+        &definition.configuration_object_key,
+        &patch,
+    )
+    .await?;
+
+    Ok(Json(configuration))
+}
+
+pub async fn reset_definition_configuration(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, AppError> {
+    let definition = load_definition(&state, id).await?;
+
+    let configuration = configuration_services::reset_configuration(
+        &state.s3_client,
+        "definitions",
+        &definition.configuration_object_key,
+    )
+    .await?;
+
+    Ok(Json(configuration))
+}
+
+pub async fn get_definition_configuration_schema(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, AppError> {
+    let definition = load_definition(&state, id).await?;
+
+    let schema = configuration_services::get_configuration_schema(
+        &state.s3_client,
+        "definitions",
+        &definition.configuration_object_key,
+    )
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found(format!(
+            "Definition with id '{}' does not declare a configuration schema",
+            definition.id
+        ))
+    })?;
+
+    Ok(Json(schema))
+}
+
+pub async fn get_definition_secrets(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Secrets>, AppError> {
+    let definition = load_definition(&state, id).await?;
+
+    let secrets = secrets_services::get_secrets(
+        &state.s3_client,
+        &state.keyring,
+        "definitions",
+        &definition.secrets_object_key,
+    )
+    .await?;
+
+    Ok(Json(secrets))
+}
+
+pub async fn set_definition_secrets(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(secrets): Json<Secrets>,
+) -> Result<Json<Secrets>, AppError> {
+    let definition = load_definition(&state, id).await?;
+
+    let secrets = secrets_services::set_secrets(
+        &state.s3_client,
+        &state.keyring,
+        "definitions",
+        &definition.secrets_object_key,
+        secrets,
+    )
+    .await?;
+
+    Ok(Json(secrets))
+}
+
+pub async fn update_definition_secrets(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(patch): Json<BTreeMap<String, Option<String>>>,
+) -> Result<Json<Secrets>, AppError> {
+    let definition = load_definition(&state, id).await?;
+
+    let secrets = secrets_services::update_secrets(
+        &state.s3_client,
+        &state.keyring,
+        "definitions",
+        &definition.secrets_object_key,
+        patch,
+    )
+    .await?;
+
+    Ok(Json(secrets))
+}
+
+pub async fn reset_definition_secrets(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let definition = load_definition(&state, id).await?;
+
+    secrets_services::reset_secrets(
+        &state.s3_client,
+        "definitions",
+        &definition.secrets_object_key,
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
 pub async fn list_modules(
     State(state): State<AppState>,
     Query(filter): Query<ModuleFilter>,
@@ -174,9 +536,19 @@ pub async fn create_module(
     let http_client = state.http_client.clone();
     let s3_client = state.s3_client.clone();
 
-    let module =
-        modules_services::create_module(&mut db_pool.get()?, &http_client, &s3_client, &payload)
-            .await?;
+    let module = modules_services::create_module(
+        &mut db_pool.get()?,
+        &http_client,
+        &s3_client,
+        &state.upload_options,
+        &payload,
+        |event| hooks_services::approve(&state, event),
+    )
+    .await?;
+
+    state.events.publish(LifecycleEvent::ModuleCreated {
+        module: module.clone(),
+    });
 
     Ok((StatusCode::CREATED, Json(module)))
 }
@@ -196,23 +568,21 @@ pub async fn get_module(
 pub async fn delete_module(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<StatusCode, AppError> {
+) -> Result<Response, AppError> {
     let mut conn = state.db_pool.get()?;
     let id_for_thread = id.clone();
 
-    let rows_deleted = tokio::task::spawn_blocking(move || {
+    let module = tokio::task::spawn_blocking(move || {
         modules_services::delete_module(&mut conn, &id_for_thread)
     })
-    .await??;
+    .await??
+    .ok_or_else(|| AppError::not_found(format!("Module with id '{}' not found", id)))?;
 
-    if rows_deleted == 0 {
-        return Err(AppError::not_found(format!(
-            "Module with id '{}' not found",
-            id
-        )));
-    }
+    state.events.publish(LifecycleEvent::ModuleDeleted { id });
 
-    Ok(StatusCode::NO_CONTENT)
+    let report = modules_services::delete_objects(&state.s3_client, &module).await;
+
+    Ok(deletion_response(report))
 }
 
 pub async fn update_module(
@@ -225,10 +595,39 @@ pub async fn update_module(
     let update = request.into_changeset();
     let mut conn = state.db_pool.get()?;
 
-    let module = tokio::task::spawn_blocking(move || {
-        modules_services::update_module(&mut conn, &id, &update)
-    })
-    .await??;
+    let (previous, module) = db::transaction_checked(
+        &mut conn,
+        |conn| modules_services::update_module(conn, &id, &update).map_err(AppError::from),
+        |(previous, module)| {
+            let enabled = (!previous.is_enabled && module.is_enabled).then(|| {
+                LifecycleEvent::ModuleEnabled {
+                    module: module.clone(),
+                }
+            });
+            async {
+                match enabled {
+                    Some(event) => hooks_services::approve(&state, event).await,
+                    None => Ok(()),
+                }
+            }
+        },
+    )
+    .await?;
+
+    state.events.publish(LifecycleEvent::ModuleUpdated {
+        module: module.clone(),
+    });
+    if previous.is_enabled != module.is_enabled {
+        state.events.publish(if module.is_enabled {
+            LifecycleEvent::ModuleEnabled {
+                module: module.clone(),
+            }
+        } else {
+            LifecycleEvent::ModuleDisabled {
+                module: module.clone(),
+            }
+        });
+    }
 
     Ok(Json(module))
 }
@@ -247,28 +646,667 @@ pub async fn install_module(
         &mut db_pool.get()?,
         &http_client,
         &s3_client,
+        &state.upload_options,
         &request.source,
+        &request.capabilities,
+        |event| hooks_services::approve(&state, event),
     )
     .await?;
 
+    state.events.publish(LifecycleEvent::ModuleCreated {
+        module: module.clone(),
+    });
+
     Ok((StatusCode::CREATED, Json(module)))
 }
 
 pub async fn upgrade_module(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(options): Query<UpgradeOptions>,
 ) -> Result<Json<Module>, AppError> {
     let db_pool = state.db_pool.clone();
     let http_client = state.http_client.clone();
     let s3_client = state.s3_client.clone();
 
-    let module = modules_services::update_module_from_source(
+    let (module, upgraded) = modules_services::update_module_from_source(
         &mut db_pool.get()?,
         &http_client,
         &s3_client,
+        &state.upload_options,
         &id,
+        options,
+        |event| hooks_services::approve(&state, event),
     )
     .await?;
 
+    if upgraded {
+        state.events.publish(LifecycleEvent::ModuleUpgraded {
+            module: module.clone(),
+        });
+    }
+
     Ok(Json(module))
 }
+
+pub async fn get_module_interceptor(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<InterceptorBinding>, AppError> {
+    let mut conn = state.db_pool.get()?;
+
+    let binding =
+        tokio::task::spawn_blocking(move || interceptors_services::get_binding(&mut conn, &id))
+            .await??;
+
+    Ok(Json(binding))
+}
+
+pub async fn set_module_interceptor(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<InterceptorBindingRequest>,
+) -> Result<Json<InterceptorBinding>, AppError> {
+    request.validate()?;
+
+    let module = load_module(&state, id).await?;
+    if module.type_ != ModuleType::Interceptor {
+        return Err(AppError::bad_request(format!(
+            "Module '{}' is not an interceptor",
+            module.id
+        )));
+    }
+
+    let binding = request.into_binding(module.id);
+    let mut conn = state.db_pool.get()?;
+
+    let binding = tokio::task::spawn_blocking(move || {
+        interceptors_services::set_binding(&mut conn, &binding)
+    })
+    .await??;
+
+    Ok(Json(binding))
+}
+
+pub async fn delete_module_interceptor(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let mut conn = state.db_pool.get()?;
+    let id_for_thread = id.clone();
+
+    let rows_deleted = tokio::task::spawn_blocking(move || {
+        interceptors_services::delete_binding(&mut conn, &id_for_thread)
+    })
+    .await??;
+
+    if rows_deleted == 0 {
+        return Err(AppError::not_found(format!(
+            "Module '{}' has no interceptor binding",
+            id
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_module_hook(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<HookSubscription>, AppError> {
+    let mut conn = state.db_pool.get()?;
+
+    let subscription =
+        tokio::task::spawn_blocking(move || hooks_services::get_subscription(&mut conn, &id))
+            .await??;
+
+    Ok(Json(subscription))
+}
+
+pub async fn set_module_hook(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<HookSubscriptionRequest>,
+) -> Result<Json<HookSubscription>, AppError> {
+    request.validate()?;
+
+    let module = load_module(&state, id).await?;
+    if module.type_ != ModuleType::Hook {
+        return Err(AppError::bad_request(format!(
+            "Module '{}' is not a hook",
+            module.id
+        )));
+    }
+
+    let subscription = request.into_subscription(module.id);
+    let mut conn = state.db_pool.get()?;
+
+    let subscription = tokio::task::spawn_blocking(move || {
+        hooks_services::set_subscription(&mut conn, &subscription)
+    })
+    .await??;
+
+    Ok(Json(subscription))
+}
+
+pub async fn delete_module_hook(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let mut conn = state.db_pool.get()?;
+    let id_for_thread = id.clone();
+
+    let rows_deleted = tokio::task::spawn_blocking(move || {
+        hooks_services::delete_subscription(&mut conn, &id_for_thread)
+    })
+    .await??;
+
+    if rows_deleted == 0 {
+        return Err(AppError::not_found(format!(
+            "Module '{}' has no hook subscription",
+            id
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn create_module_execution(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<CreateExecutionRequest>,
+) -> Result<(StatusCode, Json<Execution>), AppError> {
+    request.validate()?;
+
+    let module = load_module(&state, id).await?;
+    if module.type_ != ModuleType::Sandbox {
+        return Err(AppError::bad_request(format!(
+            "Module '{}' is not a sandbox",
+            module.id
+        )));
+    }
+
+    let input = CommandInput {
+        args: request.args,
+        stdin: request.stdin,
+    };
+    let (execution, handle) = executions_services::start_execution(&state, &module, input).await?;
+
+    match tokio::time::timeout(Duration::from_millis(request.wait_ms), handle).await {
+        Ok(finished) => Ok((StatusCode::CREATED, Json(finished??))),
+        Err(_) => Ok((StatusCode::ACCEPTED, Json(execution))),
+    }
+}
+
+pub async fn list_module_executions(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(filter): Query<ExecutionFilter>,
+) -> Result<Json<Vec<Execution>>, AppError> {
+    let mut conn = state.db_pool.get()?;
+
+    let executions = tokio::task::spawn_blocking(move || {
+        executions_services::list_executions(&mut conn, &id, &filter)
+    })
+    .await??;
+
+    Ok(Json(executions))
+}
+
+pub async fn get_module_execution(
+    State(state): State<AppState>,
+    Path((id, execution_id)): Path<(String, Uuid)>,
+) -> Result<Json<Execution>, AppError> {
+    let mut conn = state.db_pool.get()?;
+
+    let execution = tokio::task::spawn_blocking(move || {
+        executions_services::get_execution(&mut conn, &id, execution_id)
+    })
+    .await??;
+
+    Ok(Json(execution))
+}
+
+async fn load_module(state: &AppState, id: String) -> Result<Module, AppError> {
+    let mut conn = state.db_pool.get()?;
+
+    let module =
+        tokio::task::spawn_blocking(move || modules_services::get_module(&mut conn, &id)).await??;
+
+    Ok(module)
+}
+
+pub async fn get_module_configuration(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, AppError> {
+    let module = load_module(&state, id).await?;
+
+    let configuration = configuration_services::get_configuration(
+        &state.s3_client,
+        "modules",
+        &module.configuration_object_key,
+    )
+    .await?;
+
+    Ok(Json(configuration))
+}
+
+pub async fn set_module_configuration(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(configuration): Json<Value>,
+) -> Result<Json<Value>, AppError> {
+    let module = load_module(&state, id).await?;
+
+    let configuration = configuration_services::set_configuration(
+        &state.s3_client,
+        "modules",
+        &module.configuration_object_key,
+        configuration,
+    )
+    .await?;
+
+    Ok(Json(configuration))
+}
+
+pub async fn update_module_configuration(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(patch): Json<Value>,
+) -> Result<Json<Value>, AppError> {
+    let module = load_module(&state, id).await?;
+
+    let configuration = configuration_services::update_configuration(
+        &state.s3_client,
+        "modules",
+        &module.configuration_object_key,
+        &patch,
+    )
+    .await?;
+
+    Ok(Json(configuration))
+}
+
+pub async fn reset_module_configuration(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, AppError> {
+    let module = load_module(&state, id).await?;
+
+    let configuration = configuration_services::reset_configuration(
+        &state.s3_client,
+        "modules",
+        &module.configuration_object_key,
+    )
+    .await?;
+
+    Ok(Json(configuration))
+}
+
+pub async fn get_module_configuration_schema(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, AppError> {
+    let module = load_module(&state, id).await?;
+
+    let schema = configuration_services::get_configuration_schema(
+        &state.s3_client,
+        "modules",
+        &module.configuration_object_key,
+    )
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found(format!(
+            "Module with id '{}' does not declare a configuration schema",
+            module.id
+        ))
+    })?;
+
+    Ok(Json(schema))
+}
+
+pub async fn get_module_secrets(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Secrets>, AppError> {
+    let module = load_module(&state, id).await?;
+
+    let secrets = secrets_services::get_secrets(
+        &state.s3_client,
+        &state.keyring,
+        "modules",
+        &module.secrets_object_key,
+    )
+    .await?;
+
+    Ok(Json(secrets))
+}
+
+pub async fn set_module_secrets(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(secrets): Json<Secrets>,
+) -> Result<Json<Secrets>, AppError> {
+    let module = load_module(&state, id).await?;
+
+    let secrets = secrets_services::set_secrets(
+        &state.s3_client,
+        &state.keyring,
+        "modules",
+        &module.secrets_object_key,
+        secrets,
+    )
+    .await?;
+
+    Ok(Json(secrets))
+}
+
+pub async fn update_module_secrets(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(patch): Json<BTreeMap<String, Option<String>>>,
+) -> Result<Json<Secrets>, AppError> {
+    let module = load_module(&state, id).await?;
+
+    let secrets = secrets_services::update_secrets(
+        &state.s3_client,
+        &state.keyring,
+        "modules",
+        &module.secrets_object_key,
+        patch,
+    )
+    .await?;
+
+    Ok(Json(secrets))
+}
+
+pub async fn reset_module_secrets(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let module = load_module(&state, id).await?;
+
+    secrets_services::reset_secrets(&state.s3_client, "modules", &module.secrets_object_key)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_languages(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<LanguageBinding>>, AppError> {
+    let mut conn = state.db_pool.get()?;
+
+    let bindings =
+        tokio::task::spawn_blocking(move || languages_services::list_bindings(&mut conn)).await??;
+
+    Ok(Json(bindings))
+}
+
+pub async fn get_language(
+    State(state): State<AppState>,
+    Path(definition_type): Path<String>,
+) -> Result<Json<LanguageBinding>, AppError> {
+    let mut conn = state.db_pool.get()?;
+
+    let binding = tokio::task::spawn_blocking(move || {
+        languages_services::get_binding(&mut conn, &definition_type)
+    })
+    .await??;
+
+    Ok(Json(binding))
+}
+
+pub async fn set_language(
+    State(state): State<AppState>,
+    Path(definition_type): Path<String>,
+    Json(request): Json<LanguageBindingRequest>,
+) -> Result<Json<LanguageBinding>, AppError> {
+    request.validate()?;
+
+    if !regex_utils::TYPE_IDENTIFIER.is_match(&definition_type) {
+        return Err(AppError::bad_request(format!(
+            "Invalid definition type '{}'",
+            definition_type
+        )));
+    }
+    if definition_type == http_definitions_services::HTTP_TYPE {
+        return Err(AppError::conflict(format!(
+            "Definitions of type '{}' are run by MCI itself",
+            definition_type
+        )));
+    }
+
+    let module = load_module(&state, request.module_id.clone()).await?;
+    if module.type_ != ModuleType::Language {
+        return Err(AppError::bad_request(format!(
+            "Module '{}' is not a language",
+            module.id
+        )));
+    }
+
+    let binding = request.into_binding(definition_type);
+    let mut conn = state.db_pool.get()?;
+
+    let binding =
+        tokio::task::spawn_blocking(move || languages_services::set_binding(&mut conn, &binding))
+            .await??;
+
+    Ok(Json(binding))
+}
+
+pub async fn delete_language(
+    State(state): State<AppState>,
+    Path(definition_type): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let mut conn = state.db_pool.get()?;
+    let type_for_thread = definition_type.clone();
+
+    let rows_deleted = tokio::task::spawn_blocking(move || {
+        languages_services::delete_binding(&mut conn, &type_for_thread)
+    })
+    .await??;
+
+    if rows_deleted == 0 {
+        return Err(AppError::not_found(format!(
+            "No language is bound to definition type '{}'",
+            definition_type
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn session_error(status: StatusCode, id: Option<Value>, message: &str) -> Response {
+    let response = jsonrpc::Response::error(
+        id.unwrap_or(Value::Null),
+        jsonrpc::INVALID_REQUEST,
+        message.to_string(),
+    );
+
+    (status, Json(response)).into_response()
+}
+
+/// Rejects requests a browser sent from an origin that is not allowed, so a
+/// page cannot reach the endpoint through DNS rebinding. Requests without an
+/// `Origin` header do not come from a browser page and pass.
+fn require_allowed_origin(
+    state: &AppState,
+    headers: &HeaderMap,
+    id: Option<Value>,
+) -> Result<(), Box<Response>> {
+    match headers.get(header::ORIGIN) {
+        None => Ok(()),
+        Some(origin)
+            if origin
+                .to_str()
+                .is_ok_and(|origin| state.mcp_sessions.allows_origin(origin)) =>
+        {
+            Ok(())
+        }
+        Some(_) => Err(Box::new(session_error(
+            StatusCode::FORBIDDEN,
+            id,
+            "Origin is not allowed",
+        ))),
+    }
+}
+
+/// Checks the session headers of a request: `400` when the session id is
+/// missing or `Mcp-Protocol-Version` differs from the version the session
+/// negotiated, and `404` when the session is unknown or has ended, so the
+/// client knows to initialize again.
+fn require_session<'a>(
+    state: &AppState,
+    headers: &'a HeaderMap,
+    id: Option<Value>,
+) -> Result<&'a str, Box<Response>> {
+    require_allowed_origin(state, headers, id.clone())?;
+
+    let header_value = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    let Some(session_id) = header_value(sessions::SESSION_HEADER) else {
+        return Err(Box::new(session_error(
+            StatusCode::BAD_REQUEST,
+            id,
+            "Missing Mcp-Session-Id header",
+        )));
+    };
+    let Some(protocol_version) = state.mcp_sessions.touch(session_id) else {
+        return Err(Box::new(session_error(
+            StatusCode::NOT_FOUND,
+            id,
+            "Unknown session",
+        )));
+    };
+
+    // Clients predating the header do not send it.
+    match header_value(sessions::PROTOCOL_VERSION_HEADER) {
+        Some(requested) if requested != protocol_version => Err(Box::new(session_error(
+            StatusCode::BAD_REQUEST,
+            id,
+            &format!(
+                "Mcp-Protocol-Version '{}' does not match the session's version '{}'",
+                requested, protocol_version
+            ),
+        ))),
+        _ => Ok(session_id),
+    }
+}
+
+fn accepts_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| value.contains("text/event-stream"))
+}
+
+/// Model Context Protocol endpoint (streamable HTTP transport). `initialize`
+/// starts a session whose id is returned in the `Mcp-Session-Id` header and
+/// must accompany every later message, along with the negotiated
+/// `Mcp-Protocol-Version`. Notifications are acknowledged with
+/// `202 Accepted` and no body. Tool calls from clients accepting
+/// `text/event-stream` are answered with a stream carrying their progress
+/// notifications followed by the response.
+pub async fn mcp(State(state): State<AppState>, headers: HeaderMap, body: Bytes) -> Response {
+    let request = match jsonrpc::parse(&body) {
+        Ok(request) => request,
+        Err(response) => return (StatusCode::BAD_REQUEST, Json(*response)).into_response(),
+    };
+
+    let initialize = request.method == "initialize";
+    let checked = if initialize {
+        require_allowed_origin(&state, &headers, request.id.clone())
+    } else {
+        require_session(&state, &headers, request.id.clone()).map(|_| ())
+    };
+    if let Err(response) = checked {
+        return *response;
+    }
+
+    if request.method == "tools/call"
+        && !request.is_notification()
+        && accepts_event_stream(&headers)
+    {
+        let events = mcp::handle_streaming(state, request, mcp::Transport::Http)
+            .map(|message| Event::default().json_data(message));
+        return Sse::new(events).into_response();
+    }
+
+    let Some(response) = mcp::handle(&state, request, None, mcp::Transport::Http).await else {
+        return StatusCode::ACCEPTED.into_response();
+    };
+
+    let protocol_version = response
+        .result
+        .as_ref()
+        .and_then(|result| result.get("protocolVersion"))
+        .and_then(Value::as_str);
+
+    match protocol_version {
+        Some(protocol_version) if initialize => {
+            let session_id = state.mcp_sessions.create(protocol_version);
+            ([(sessions::SESSION_HEADER, session_id)], Json(response)).into_response()
+        }
+        _ => Json(response).into_response(),
+    }
+}
+
+/// Opens the stream of server-initiated notifications for a session.
+pub async fn mcp_stream(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let session_id = match require_session(&state, &headers, None) {
+        Ok(session_id) => session_id,
+        Err(response) => return *response,
+    };
+    if !accepts_event_stream(&headers) {
+        return StatusCode::NOT_ACCEPTABLE.into_response();
+    }
+    let Some(receiver) = state.mcp_sessions.subscribe(session_id) else {
+        return session_error(StatusCode::NOT_FOUND, None, "Unknown session");
+    };
+
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(notification) => {
+                    return Some((Event::default().json_data(notification), receiver))
+                }
+                // A slow client misses what it fell behind on but keeps the stream.
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Ends a session. Its notification streams close.
+pub async fn mcp_close(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let session_id = match require_session(&state, &headers, None) {
+        Ok(session_id) => session_id,
+        Err(response) => return *response,
+    };
+
+    if state.mcp_sessions.remove(session_id) {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        session_error(StatusCode::NOT_FOUND, None, "Unknown session")
+    }
+}
+
+pub async fn rotate_secrets(
+    State(state): State<AppState>,
+) -> Result<Json<RotationReport>, AppError> {
+    let report = secrets_services::rotate_secrets(
+        &state.s3_client,
+        &state.keyring,
+        &secrets_services::SECRET_BUCKETS,
+    )
+    .await?;
+
+    Ok(Json(report))
+}
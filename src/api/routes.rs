@@ -1,6 +1,6 @@
 use crate::{api::handlers, AppState};
 use axum::{
-    routing::{delete, get, patch, post},
+    routing::{delete, get, patch, post, put},
     Router,
 };
 
@@ -16,6 +16,58 @@ pub fn routes() -> Router<AppState> {
             "/definitions/{id}/update",
             post(handlers::upgrade_definition),
         )
+        .route(
+            "/definitions/{id}/invoke",
+            post(handlers::invoke_definition),
+        )
+        .route(
+            "/definitions/{id}/proxy",
+            get(handlers::get_definition_proxy),
+        )
+        .route(
+            "/definitions/{id}/proxy",
+            put(handlers::set_definition_proxy),
+        )
+        .route(
+            "/definitions/{id}/proxy",
+            delete(handlers::delete_definition_proxy),
+        )
+        .route(
+            "/definitions/{id}/configuration",
+            get(handlers::get_definition_configuration),
+        )
+        .route(
+            "/definitions/{id}/configuration",
+            put(handlers::set_definition_configuration),
+        )
+        .route(
+            "/definitions/{id}/configuration",
+            patch(handlers::update_definition_configuration),
+        )
+        .route(
+            "/definitions/{id}/configuration",
+            delete(handlers::reset_definition_configuration),
+        )
+        .route(
+            "/definitions/{id}/configuration/schema",
+            get(handlers::get_definition_configuration_schema),
+        )
+        .route(
+            "/definitions/{id}/secret",
+            get(handlers::get_definition_secrets),
+        )
+        .route(
+            "/definitions/{id}/secret",
+            put(handlers::set_definition_secrets),
+        )
+        .route(
+            "/definitions/{id}/secret",
+            patch(handlers::update_definition_secrets),
+        )
+        .route(
+            "/definitions/{id}/secret",
+            delete(handlers::reset_definition_secrets),
+        )
         .route("/modules", get(handlers::list_modules))
         .route("/modules", post(handlers::create_module))
         .route("/modules/install", post(handlers::install_module))
@@ -23,16 +75,71 @@ pub fn routes() -> Router<AppState> {
         .route("/modules/{id}", delete(handlers::delete_module))
         .route("/modules/{id}", patch(handlers::update_module))
         .route("/modules/{id}/update", post(handlers::upgrade_module))
+        .route(
+            "/modules/{id}/configuration",
+            get(handlers::get_module_configuration),
+        )
+        .route(
+            "/modules/{id}/configuration",
+            put(handlers::set_module_configuration),
+        )
+        .route(
+            "/modules/{id}/configuration",
+            patch(handlers::update_module_configuration),
+        )
+        .route(
+            "/modules/{id}/configuration",
+            delete(handlers::reset_module_configuration),
+        )
+        .route(
+            "/modules/{id}/configuration/schema",
+            get(handlers::get_module_configuration_schema),
+        )
+        .route("/modules/{id}/secret", get(handlers::get_module_secrets))
+        .route("/modules/{id}/secret", put(handlers::set_module_secrets))
+        .route(
+            "/modules/{id}/secret",
+            patch(handlers::update_module_secrets),
+        )
+        .route(
+            "/modules/{id}/secret",
+            delete(handlers::reset_module_secrets),
+        )
+        .route(
+            "/modules/{id}/interceptor",
+            get(handlers::get_module_interceptor),
+        )
+        .route(
+            "/modules/{id}/interceptor",
+            put(handlers::set_module_interceptor),
+        )
+        .route(
+            "/modules/{id}/interceptor",
+            delete(handlers::delete_module_interceptor),
+        )
+        .route("/modules/{id}/hook", get(handlers::get_module_hook))
+        .route("/modules/{id}/hook", put(handlers::set_module_hook))
+        .route("/modules/{id}/hook", delete(handlers::delete_module_hook))
+        .route(
+            "/modules/{id}/executions",
+            get(handlers::list_module_executions),
+        )
+        .route(
+            "/modules/{id}/executions",
+            post(handlers::create_module_execution),
+        )
+        .route(
+            "/modules/{id}/executions/{execution_id}",
+            get(handlers::get_module_execution),
+        )
+        .route("/languages", get(handlers::list_languages))
+        .route("/languages/{type}", get(handlers::get_language))
+        .route("/languages/{type}", put(handlers::set_language))
+        .route("/languages/{type}", delete(handlers::delete_language))
+        .route("/mcp", post(handlers::mcp))
+        .route("/mcp", get(handlers::mcp_stream))
+        .route("/mcp", delete(handlers::mcp_close))
+        .route("/admin/secrets/rotate", post(handlers::rotate_secrets))
 
-    //.route("/definitions/{id}/configuration", get(handlers::get_definition_configuration))
-    // .route("/definitions/{id}/configuration", put(handlers::set_definition_configuration))
-    // .route("/definitions/{id}/configuration", patch(handlers::update_definition_configuration))
-    // .route("/definitions/{id}/configuration", delete(handlers::reset_definition_configuration))
-    // .route("/definitions/{id}/configuration/schema", get(handlers::get_definition_configuration_schema))
-    //
-    // .route("/definitions/{id}/secret", get(handlers::get_definition_secrets))
-    // .route("/definitions/{id}/secret", put(handlers::set_definition_secrets))
-    // .route("/definitions/{id}/secret", patch(handlers::update_definition_secrets))
-    // .route("/definitions/{id}/secret", delete(handlers::reset_definition_secrets))
     // .route("/definitions/{id}/secret/schema", get(handlers::get_definition_secrets_schema))
 }
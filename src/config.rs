@@ -12,6 +12,12 @@ pub struct Config {
     pub s3_region: String,
     pub s3_access_key: String,
     pub s3_secret_key: String,
+    pub s3_upload_part_size: usize,
+    pub s3_upload_concurrency: usize,
+    pub secrets_master_key: Option<String>,
+    pub secrets_master_key_version: u32,
+    pub secrets_retired_master_keys: Option<String>,
+    pub mcp_allowed_origins: Option<String>,
 }
 
 impl Config {
@@ -28,6 +34,9 @@ impl Config {
             .set_default("s3_region", "us-east-1")?
             .set_default("s3_access_key", "none")?
             .set_default("s3_secret_key", "none")?
+            .set_default("s3_upload_part_size", 16 * 1024 * 1024)?
+            .set_default("s3_upload_concurrency", 4)?
+            .set_default("secrets_master_key_version", 1)?
             .add_source(Environment::with_prefix("MCI"))
             .build()?;
 
@@ -14,7 +14,10 @@ impl ConfigTestExt for Config {
             .set_default("address", "0.0.0.0:7687")?
             .set_default("s3_region", "us-east-1")?
             .set_default("s3_access_key", "none")?
-            .set_default("s3_secret_key", "none")?;
+            .set_default("s3_secret_key", "none")?
+            .set_default("s3_upload_part_size", 16 * 1024 * 1024)?
+            .set_default("s3_upload_concurrency", 4)?
+            .set_default("secrets_master_key_version", 1)?;
 
         for (key, value) in values {
             builder = builder.set_override(key, value)?;
@@ -44,8 +47,45 @@ fn test_minimal_valid_configuration() {
     assert_eq!(config.s3_region, "us-east-1");
     assert_eq!(config.s3_access_key, "none");
     assert_eq!(config.s3_secret_key, "none");
+    assert_eq!(config.s3_upload_part_size, 16 * 1024 * 1024);
+    assert_eq!(config.s3_upload_concurrency, 4);
     assert_eq!(config.key_path, None);
     assert_eq!(config.cert_path, None);
+    assert_eq!(config.secrets_master_key, None);
+    assert_eq!(config.secrets_master_key_version, 1);
+    assert_eq!(config.secrets_retired_master_keys, None);
+    assert_eq!(config.mcp_allowed_origins, None);
+}
+
+#[test]
+fn test_secrets_master_key() {
+    let mut map = minimal_config();
+    map.insert(
+        "secrets_master_key",
+        "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=",
+    );
+
+    let config = Config::from_map(map).expect("Failed to load config");
+
+    assert_eq!(
+        config.secrets_master_key,
+        Some("MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=".to_string())
+    );
+}
+
+#[test]
+fn test_secrets_master_key_rotation() {
+    let mut map = minimal_config();
+    map.insert("secrets_master_key_version", "2");
+    map.insert("secrets_retired_master_keys", "1:b2xkLWtleQ==");
+
+    let config = Config::from_map(map).expect("Failed to load config");
+
+    assert_eq!(config.secrets_master_key_version, 2);
+    assert_eq!(
+        config.secrets_retired_master_keys,
+        Some("1:b2xkLWtleQ==".to_string())
+    );
 }
 
 #[test]
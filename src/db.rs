@@ -1,7 +1,11 @@
+use crate::errors::AppError;
 use diesel::{
+    connection::{AnsiTransactionManager, TransactionManager},
     prelude::PgConnection,
     r2d2::{ConnectionManager, Pool, PooledConnection},
 };
+use std::future::Future;
+use tracing::warn;
 
 pub type PgPool = Pool<ConnectionManager<PgConnection>>;
 pub type DbConnection = PooledConnection<ConnectionManager<PgConnection>>;
@@ -14,3 +18,40 @@ pub fn create_pool(database_url: &str) -> PgPool {
         .build(manager)
         .expect("Failed to create pool")
 }
+
+/// Runs `write` in a transaction that is only committed once `check` accepts
+/// what it returned. Unlike `Connection::transaction`, the check may await,
+/// so a change can be held back while hooks approve it.
+pub async fn transaction_checked<T, E, F, Fut>(
+    conn: &mut DbConnection,
+    write: impl FnOnce(&mut DbConnection) -> Result<T, E>,
+    check: F,
+) -> Result<T, E>
+where
+    E: From<diesel::result::Error> + From<AppError>,
+    F: FnOnce(&T) -> Fut,
+    Fut: Future<Output = Result<(), AppError>>,
+{
+    AnsiTransactionManager::begin_transaction(&mut **conn)?;
+
+    let result = match write(conn) {
+        Ok(value) => match check(&value).await {
+            Ok(()) => Ok(value),
+            Err(err) => Err(E::from(err)),
+        },
+        Err(err) => Err(err),
+    };
+
+    match result {
+        Ok(value) => {
+            AnsiTransactionManager::commit_transaction(&mut **conn)?;
+            Ok(value)
+        }
+        Err(err) => {
+            if let Err(rollback_err) = AnsiTransactionManager::rollback_transaction(&mut **conn) {
+                warn!("Failed to roll back transaction: {}", rollback_err);
+            }
+            Err(err)
+        }
+    }
+}
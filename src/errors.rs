@@ -3,20 +3,33 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
+use serde::Serialize;
 use serde_json::json;
 use std::fmt;
 use validator::ValidationErrors;
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldViolation {
+    pub pointer: String,
+    pub message: String,
+}
+
 #[derive(Debug)]
 pub enum AppError {
     NotFound(String),
     Conflict(String),
     BadRequest(String),
+    Forbidden(String),
     Validation(ValidationErrors),
+    SchemaValidation(Vec<FieldViolation>),
 
     UnsupportedScheme(String),
     InvalidSource(String),
 
+    ModuleExecution(String),
+    Upstream(String),
+    NotConfigured(String),
+
     Internal(anyhow::Error),
     Pool(diesel::r2d2::PoolError),
     Database(diesel::result::Error),
@@ -29,13 +42,25 @@ impl fmt::Display for AppError {
             AppError::Conflict(msg) => write!(f, "Conflict: {}", msg),
             AppError::NotFound(msg) => write!(f, "Not found: {}", msg),
             AppError::BadRequest(msg) => write!(f, "Bad request: {}", msg),
+            AppError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
             AppError::Validation(err) => write!(f, "Validation error: {}", err),
+            AppError::SchemaValidation(violations) => {
+                write!(
+                    f,
+                    "Validation error: {}",
+                    format_field_violations(violations)
+                )
+            }
 
             AppError::UnsupportedScheme(scheme) => write!(f, "Unsupported scheme: '{}'", scheme),
             AppError::InvalidSource(msg) => {
                 write!(f, "Invalid  source: {}", msg)
             }
 
+            AppError::ModuleExecution(msg) => write!(f, "Module execution failed: {}", msg),
+            AppError::Upstream(msg) => write!(f, "Upstream request failed: {}", msg),
+            AppError::NotConfigured(msg) => write!(f, "Not configured: {}", msg),
+
             AppError::Internal(err) => write!(f, "Internal error: {}", err),
             AppError::Database(err) => write!(f, "Database error: {}", err),
             AppError::TaskJoin(err) => write!(f, "Task join error: {}", err),
@@ -75,21 +100,35 @@ impl From<tokio::task::JoinError> for AppError {
 
 impl From<anyhow::Error> for AppError {
     fn from(err: anyhow::Error) -> Self {
-        AppError::Internal(err)
+        match err.downcast::<AppError>() {
+            Ok(app_error) => app_error,
+            Err(err) => AppError::Internal(err),
+        }
     }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        let details = match &self {
+            AppError::SchemaValidation(violations) => Some(json!(violations)),
+            _ => None,
+        };
+
         let (status, error_type, message) = match &self {
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, "bad_request", msg.clone()),
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, "not_found", msg.clone()),
             AppError::Conflict(msg) => (StatusCode::CONFLICT, "conflict", msg.clone()),
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, "forbidden", msg.clone()),
             AppError::Validation(errors) => (
                 StatusCode::BAD_REQUEST,
                 "validation_error",
                 format_validation_errors(errors),
             ),
+            AppError::SchemaValidation(violations) => (
+                StatusCode::BAD_REQUEST,
+                "validation_error",
+                format_field_violations(violations),
+            ),
 
             AppError::InvalidSource(msg) => {
                 (StatusCode::BAD_REQUEST, "invalid_source", msg.clone())
@@ -100,6 +139,16 @@ impl IntoResponse for AppError {
                 format!("Unsupported scheme: '{}'", scheme),
             ),
 
+            AppError::ModuleExecution(msg) => {
+                (StatusCode::BAD_GATEWAY, "module_error", msg.clone())
+            }
+            AppError::Upstream(msg) => (StatusCode::BAD_GATEWAY, "upstream_error", msg.clone()),
+            AppError::NotConfigured(msg) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "not_configured",
+                msg.clone(),
+            ),
+
             AppError::Database(err) => {
                 tracing::error!("Database error: {:?}", err);
                 (
@@ -134,12 +183,15 @@ impl IntoResponse for AppError {
             }
         };
 
-        let body = Json(json!({
-            "error": {
-                "type": error_type,
-                "message": message,
-            }
-        }));
+        let mut error = json!({
+            "type": error_type,
+            "message": message,
+        });
+        if let Some(details) = details {
+            error["details"] = details;
+        }
+
+        let body = Json(json!({ "error": error }));
 
         (status, body).into_response()
     }
@@ -162,6 +214,14 @@ fn format_validation_errors(errors: &ValidationErrors) -> String {
     messages.join(", ")
 }
 
+fn format_field_violations(violations: &[FieldViolation]) -> String {
+    violations
+        .iter()
+        .map(|violation| format!("{}: {}", violation.pointer, violation.message))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 impl AppError {
     pub fn bad_request(msg: impl Into<String>) -> Self {
         AppError::BadRequest(msg.into())
@@ -175,10 +235,18 @@ impl AppError {
         AppError::Conflict(msg.into())
     }
 
+    pub fn forbidden(msg: impl Into<String>) -> Self {
+        AppError::Forbidden(msg.into())
+    }
+
     pub fn internal(err: impl Into<anyhow::Error>) -> Self {
         AppError::Internal(err.into())
     }
 
+    pub fn schema_validation(violations: Vec<FieldViolation>) -> Self {
+        AppError::SchemaValidation(violations)
+    }
+
     pub fn invalid_source(msg: impl Into<String>) -> Self {
         AppError::InvalidSource(msg.into())
     }
@@ -186,6 +254,18 @@ impl AppError {
     pub fn unsupported_scheme(scheme: impl Into<String>) -> Self {
         AppError::UnsupportedScheme(scheme.into())
     }
+
+    pub fn module_execution(msg: impl Into<String>) -> Self {
+        AppError::ModuleExecution(msg.into())
+    }
+
+    pub fn upstream(msg: impl Into<String>) -> Self {
+        AppError::Upstream(msg.into())
+    }
+
+    pub fn not_configured(msg: impl Into<String>) -> Self {
+        AppError::NotConfigured(msg.into())
+    }
 }
 
 #[cfg(test)]
@@ -251,6 +251,18 @@ fn test_from_anyhow_error() {
     }
 }
 
+#[test]
+fn test_from_anyhow_error_preserves_app_error() {
+    let anyhow_err = anyhow::Error::from(AppError::bad_request("bad configuration"))
+        .context("Failed to store configuration");
+    let app_error = AppError::from(anyhow_err);
+
+    match app_error {
+        AppError::BadRequest(msg) => assert_eq!(msg, "bad configuration"),
+        _ => panic!("Expected BadRequest variant"),
+    }
+}
+
 #[test]
 fn test_from_validation_errors() {
     let test = TestStruct {
@@ -297,3 +309,92 @@ fn test_internal_display() {
     assert!(display.contains("Internal error"));
     assert!(display.contains("boom"));
 }
+
+#[tokio::test]
+async fn test_app_error_schema_validation_response() {
+    let error = AppError::schema_validation(vec![FieldViolation {
+        pointer: "/timeout".to_string(),
+        message: "\"soon\" is not of type \"integer\"".to_string(),
+    }]);
+    let response = error.into_response();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = response.into_body();
+    let bytes = body.collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+    assert_eq!(json["error"]["type"], "validation_error");
+    assert_eq!(json["error"]["details"][0]["pointer"], "/timeout");
+    assert!(json["error"]["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("/timeout: "));
+}
+
+#[tokio::test]
+async fn test_app_error_module_execution_response() {
+    let error = AppError::module_execution("wasm trap: unreachable");
+    let response = error.into_response();
+
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+
+    let body = response.into_body();
+    let bytes = body.collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+    assert_eq!(json["error"]["type"], "module_error");
+    assert!(json["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("unreachable"));
+}
+
+#[tokio::test]
+async fn test_app_error_forbidden_response() {
+    let error = AppError::forbidden("Request rejected by interceptor 'pii'");
+    let response = error.into_response();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let body = response.into_body();
+    let bytes = body.collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+    assert_eq!(json["error"]["type"], "forbidden");
+    assert_eq!(
+        json["error"]["message"],
+        "Request rejected by interceptor 'pii'"
+    );
+}
+
+#[tokio::test]
+async fn test_app_error_upstream_response() {
+    let error = AppError::upstream("Upstream returned status 503");
+    let response = error.into_response();
+
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+    assert_eq!(json["error"]["type"], "upstream_error");
+    assert_eq!(json["error"]["message"], "Upstream returned status 503");
+}
+
+#[tokio::test]
+async fn test_app_error_not_configured_response() {
+    let error = AppError::not_configured("Secrets keyring is not configured");
+    let response = error.into_response();
+
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+    assert_eq!(json["error"]["type"], "not_configured");
+    assert_eq!(
+        json["error"]["message"],
+        "Secrets keyring is not configured"
+    );
+}
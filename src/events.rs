@@ -0,0 +1,133 @@
+use crate::models::{Definition, Module};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+const EVENT_BUS_CAPACITY: usize = 256;
+
+/// The kinds of registry lifecycle events hooks can subscribe to.
+/// `*_enabled` and `*_disabled` follow the `*_updated` event of an update
+/// that actually toggled the resource, and `*_upgraded` is only sent when an
+/// upgrade installed different content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventType {
+    DefinitionCreated,
+    DefinitionUpdated,
+    DefinitionUpgraded,
+    DefinitionEnabled,
+    DefinitionDisabled,
+    DefinitionDeleted,
+    ModuleCreated,
+    ModuleUpdated,
+    ModuleUpgraded,
+    ModuleEnabled,
+    ModuleDisabled,
+    ModuleDeleted,
+}
+
+impl EventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventType::DefinitionCreated => "definition_created",
+            EventType::DefinitionUpdated => "definition_updated",
+            EventType::DefinitionUpgraded => "definition_upgraded",
+            EventType::DefinitionEnabled => "definition_enabled",
+            EventType::DefinitionDisabled => "definition_disabled",
+            EventType::DefinitionDeleted => "definition_deleted",
+            EventType::ModuleCreated => "module_created",
+            EventType::ModuleUpdated => "module_updated",
+            EventType::ModuleUpgraded => "module_upgraded",
+            EventType::ModuleEnabled => "module_enabled",
+            EventType::ModuleDisabled => "module_disabled",
+            EventType::ModuleDeleted => "module_deleted",
+        }
+    }
+
+    /// Whether hooks may approve this kind of change before it is committed.
+    pub fn is_approvable(&self) -> bool {
+        matches!(
+            self,
+            EventType::DefinitionCreated
+                | EventType::DefinitionUpgraded
+                | EventType::DefinitionEnabled
+                | EventType::ModuleCreated
+                | EventType::ModuleUpgraded
+                | EventType::ModuleEnabled
+        )
+    }
+}
+
+/// A change to the registry. Serialized with an `event` tag matching
+/// [`EventType`], this is the payload hook modules receive.
+///
+/// Events are published once the change is committed. Creates, upgrades and
+/// enables are first sent to the hooks approving them, marked
+/// `"pending": true`, and any of those hooks can reject the change.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LifecycleEvent {
+    DefinitionCreated { definition: Definition },
+    DefinitionUpdated { definition: Definition },
+    DefinitionUpgraded { definition: Definition },
+    DefinitionEnabled { definition: Definition },
+    DefinitionDisabled { definition: Definition },
+    DefinitionDeleted { id: String },
+    ModuleCreated { module: Module },
+    ModuleUpdated { module: Module },
+    ModuleUpgraded { module: Module },
+    ModuleEnabled { module: Module },
+    ModuleDisabled { module: Module },
+    ModuleDeleted { id: String },
+}
+
+impl LifecycleEvent {
+    pub fn event_type(&self) -> EventType {
+        match self {
+            LifecycleEvent::DefinitionCreated { .. } => EventType::DefinitionCreated,
+            LifecycleEvent::DefinitionUpdated { .. } => EventType::DefinitionUpdated,
+            LifecycleEvent::DefinitionUpgraded { .. } => EventType::DefinitionUpgraded,
+            LifecycleEvent::DefinitionEnabled { .. } => EventType::DefinitionEnabled,
+            LifecycleEvent::DefinitionDisabled { .. } => EventType::DefinitionDisabled,
+            LifecycleEvent::DefinitionDeleted { .. } => EventType::DefinitionDeleted,
+            LifecycleEvent::ModuleCreated { .. } => EventType::ModuleCreated,
+            LifecycleEvent::ModuleUpdated { .. } => EventType::ModuleUpdated,
+            LifecycleEvent::ModuleUpgraded { .. } => EventType::ModuleUpgraded,
+            LifecycleEvent::ModuleEnabled { .. } => EventType::ModuleEnabled,
+            LifecycleEvent::ModuleDisabled { .. } => EventType::ModuleDisabled,
+            LifecycleEvent::ModuleDeleted { .. } => EventType::ModuleDeleted,
+        }
+    }
+}
+
+/// Fans lifecycle events out to every subscriber. Publishing never blocks; a
+/// subscriber that falls too far behind skips the events it missed.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<LifecycleEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: LifecycleEvent) {
+        // Having no subscribers is not an error; the event is simply dropped.
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<LifecycleEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+#[path = "events_tests.rs"]
+mod tests;
@@ -0,0 +1,43 @@
+use super::*;
+use serde_json::json;
+
+#[test]
+fn test_event_type_matches_serialized_name() {
+    let event_type: EventType = serde_json::from_value(json!("module_upgraded")).unwrap();
+
+    assert_eq!(event_type, EventType::ModuleUpgraded);
+    assert_eq!(event_type.as_str(), "module_upgraded");
+    assert_eq!(
+        serde_json::to_value(EventType::DefinitionDisabled).unwrap(),
+        json!(EventType::DefinitionDisabled.as_str())
+    );
+}
+
+#[test]
+fn test_lifecycle_event_payload() {
+    let event = LifecycleEvent::DefinitionDeleted {
+        id: "acme::search".to_string(),
+    };
+
+    assert_eq!(event.event_type(), EventType::DefinitionDeleted);
+    assert_eq!(
+        serde_json::to_value(&event).unwrap(),
+        json!({ "event": "definition_deleted", "id": "acme::search" })
+    );
+}
+
+#[tokio::test]
+async fn test_event_bus_delivers_to_subscribers() {
+    let bus = EventBus::new();
+    bus.publish(LifecycleEvent::ModuleDeleted {
+        id: "dropped".to_string(),
+    });
+
+    let mut receiver = bus.subscribe();
+    bus.publish(LifecycleEvent::ModuleDeleted {
+        id: "acme::pii".to_string(),
+    });
+
+    let event = receiver.recv().await.unwrap();
+    assert!(matches!(event, LifecycleEvent::ModuleDeleted { id } if id == "acme::pii"));
+}
@@ -1,7 +1,7 @@
 use axum::Router;
 use axum_server::{tls_rustls::RustlsConfig, Handle};
 use futures::Future;
-use std::{net::SocketAddr, path::PathBuf};
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
 use tower_http::trace::TraceLayer;
 use tracing::{info, warn};
 
@@ -9,10 +9,14 @@ pub mod api;
 pub mod config;
 pub mod db;
 pub mod errors;
+pub mod events;
 pub mod http;
+pub mod mcp;
 pub mod models;
+pub mod runtime;
 pub mod s3;
 pub mod schema;
+pub mod secrets;
 pub mod services;
 pub mod utils;
 
@@ -21,6 +25,11 @@ pub struct AppState {
     pub db_pool: db::PgPool,
     pub http_client: reqwest::Client,
     pub s3_client: aws_sdk_s3::Client,
+    pub upload_options: s3::UploadOptions,
+    pub keyring: Arc<secrets::Keyring>,
+    pub runtime: runtime::Runtime,
+    pub events: events::EventBus,
+    pub mcp_sessions: mcp::sessions::Sessions,
 }
 
 pub fn app(app_state: AppState) -> Router {
@@ -30,13 +39,9 @@ pub fn app(app_state: AppState) -> Router {
         .with_state(app_state)
 }
 
-pub async fn serve(
-    config: &config::Config,
-    handle: Handle<std::net::SocketAddr>,
-) -> Result<
-    (impl Future<Output = Result<(), std::io::Error>>, SocketAddr),
-    Box<dyn std::error::Error>,
-> {
+/// Connects to the database and storage described by `config` and loads the
+/// secrets keyring.
+pub async fn create_state(config: &config::Config) -> Result<AppState, Box<dyn std::error::Error>> {
     let db_pool = db::create_pool(&config.database_url);
     let http_client = http::create_client(30)?;
     let s3_client = s3::create_client(
@@ -46,12 +51,47 @@ pub async fn serve(
         &config.s3_region,
     )
     .await;
+    let upload_options =
+        s3::UploadOptions::new(config.s3_upload_part_size, config.s3_upload_concurrency)?;
+    let keyring = secrets::Keyring::from_config(config)?;
+    let runtime = runtime::Runtime::new()?;
 
-    let app = app(AppState {
+    Ok(AppState {
         db_pool,
         http_client,
         s3_client,
-    });
+        upload_options,
+        keyring: Arc::new(keyring),
+        runtime,
+        events: events::EventBus::new(),
+        mcp_sessions: mcp::sessions::Sessions::from_config(config),
+    })
+}
+
+pub async fn serve(
+    config: &config::Config,
+    handle: Handle<std::net::SocketAddr>,
+) -> Result<
+    (impl Future<Output = Result<(), std::io::Error>>, SocketAddr),
+    Box<dyn std::error::Error>,
+> {
+    let state = create_state(config).await?;
+
+    if config.secrets_master_key.is_none() {
+        warn!("No secrets master key configured. Secret endpoints will reject requests.");
+    }
+
+    tokio::spawn(services::hooks_services::run_dispatcher(
+        state.clone(),
+        state.events.subscribe(),
+    ));
+    tokio::spawn(services::executions_services::run_reaper(state.clone()));
+    tokio::spawn(mcp::run_list_change_notifier(
+        state.mcp_sessions.clone(),
+        state.events.subscribe(),
+    ));
+
+    let app = app(state);
 
     let addr: SocketAddr = config
         .address
@@ -1,16 +1,8 @@
-use mci::{self, config::Config};
-use tracing::info;
+use mci::{self, config::Config, s3, secrets::Keyring, services::secrets_services};
+use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
 
-#[tokio::main]
-async fn main() {
-    let config = Config::from_env().expect("Failed to load configuration from environment");
-
-    tracing_subscriber::fmt()
-        .with_target(false)
-        .with_env_filter(EnvFilter::new(&config.log_level))
-        .init();
-
+async fn run_server(config: &Config) {
     let handle = axum_server::Handle::new();
     let shutdown_handle = handle.clone();
 
@@ -24,7 +16,7 @@ async fn main() {
         shutdown_handle.graceful_shutdown(Some(std::time::Duration::from_secs(30)));
     });
 
-    let (server_future, addr) = mci::serve(&config, handle)
+    let (server_future, addr) = mci::serve(config, handle)
         .await
         .expect("Failed to start server");
 
@@ -32,3 +24,76 @@ async fn main() {
 
     server_future.await.expect("Server failed to run");
 }
+
+async fn rotate_secrets(config: &Config) {
+    let keyring = Keyring::from_config(config).expect("Failed to load secrets master keys");
+    let s3_client = s3::create_client(
+        &config.s3_url,
+        &config.s3_access_key,
+        &config.s3_secret_key,
+        &config.s3_region,
+    )
+    .await;
+
+    let report =
+        secrets_services::rotate_secrets(&s3_client, &keyring, &secrets_services::SECRET_BUCKETS)
+            .await
+            .expect("Failed to rotate secrets");
+
+    info!(
+        "Rotated {} secret objects to key version {} ({} already current)",
+        report.rotated, report.key_version, report.unchanged
+    );
+
+    if !report.failed.is_empty() {
+        error!("Failed to rotate: {}", report.failed.join(", "));
+        std::process::exit(1);
+    }
+}
+
+/// Speaks MCP over stdin/stdout so local clients can launch MCI as a
+/// subprocess. Logs go to stderr to keep stdout free for protocol messages.
+async fn run_stdio(config: &Config) {
+    let state = mci::create_state(config)
+        .await
+        .expect("Failed to initialize application state");
+
+    info!("Serving MCP over stdio");
+
+    mci::mcp::stdio::serve(
+        state,
+        tokio::io::BufReader::new(tokio::io::stdin()),
+        tokio::io::stdout(),
+    )
+    .await
+    .expect("MCP stdio transport failed");
+}
+
+#[tokio::main]
+async fn main() {
+    let config = Config::from_env().expect("Failed to load configuration from environment");
+
+    let command = std::env::args().nth(1);
+
+    let subscriber = tracing_subscriber::fmt()
+        .with_target(false)
+        .with_env_filter(EnvFilter::new(&config.log_level));
+    if command.as_deref() == Some("stdio") {
+        subscriber.with_writer(std::io::stderr).init();
+    } else {
+        subscriber.init();
+    }
+
+    match command.as_deref() {
+        None | Some("serve") => run_server(&config).await,
+        Some("stdio") => run_stdio(&config).await,
+        Some("rotate-secrets") => rotate_secrets(&config).await,
+        Some(command) => {
+            error!(
+                "Unknown command '{}'. Expected 'serve', 'stdio' or 'rotate-secrets'",
+                command
+            );
+            std::process::exit(2);
+        }
+    }
+}
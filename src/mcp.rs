@@ -0,0 +1,267 @@
+use crate::{
+    errors::AppError,
+    events::{EventType, LifecycleEvent},
+    services::invocation_services::Progress,
+    AppState,
+};
+use futures::{
+    channel::{
+        mpsc::{self, UnboundedSender},
+        oneshot,
+    },
+    future, stream, Stream, StreamExt,
+};
+use jsonrpc::{Notification, Request, Response, INTERNAL_ERROR, INVALID_PARAMS, METHOD_NOT_FOUND};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sessions::Sessions;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use tracing::{error, warn};
+
+pub mod jsonrpc;
+pub mod sessions;
+pub mod stdio;
+pub mod tools;
+
+/// Receives the notifications a request emits while it is being handled,
+/// such as progress updates, when the transport can deliver them.
+pub type Notifier = UnboundedSender<Notification>;
+
+/// How a client is connected, which decides what the server can offer it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    /// Streamable HTTP, whose sessions are sent
+    /// `notifications/tools/list_changed`.
+    Http,
+    /// A stdio process. Registry changes are made through the HTTP server and
+    /// its events never reach this process, so tool list changes go
+    /// unannounced.
+    Stdio,
+}
+
+/// Protocol revisions this server speaks, newest first.
+pub const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InitializeParams {
+    protocol_version: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ListToolsParams {
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CallToolParams {
+    name: String,
+    #[serde(default = "empty_arguments")]
+    arguments: Value,
+}
+
+fn empty_arguments() -> Value {
+    json!({})
+}
+
+type MethodResult = Result<Value, (i64, String)>;
+
+/// Handles one Model Context Protocol message. Returns the response to send,
+/// or `None` for notifications, which are never answered.
+pub async fn handle(
+    state: &AppState,
+    request: Request,
+    notifier: Option<&Notifier>,
+    transport: Transport,
+) -> Option<Response> {
+    let id = request.id.clone()?;
+
+    let result = match request.method.as_str() {
+        "initialize" => initialize(request.params, transport),
+        "ping" => Ok(json!({})),
+        "tools/list" => list_tools(state, request.params).await,
+        "tools/call" => call_tool(state, request.params, notifier).await,
+        method => Err((METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+    };
+
+    Some(match result {
+        Ok(result) => Response::success(id, result),
+        Err((code, message)) => Response::error(id, code, message),
+    })
+}
+
+/// A message sent to the client while a request is being answered.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Outgoing {
+    Notification(Notification),
+    Response(Response),
+}
+
+/// Handles `request` in the background and streams the notifications it
+/// emits, followed by its response. For transports that can deliver more
+/// than one message per request.
+pub fn handle_streaming(
+    state: AppState,
+    request: Request,
+    transport: Transport,
+) -> impl Stream<Item = Outgoing> {
+    let (notifier, notifications) = mpsc::unbounded();
+    let (respond, response) = oneshot::channel();
+
+    tokio::spawn(async move {
+        let response = handle(&state, request, Some(&notifier), transport).await;
+        // Closing the notifier ends the notification part of the stream.
+        drop(notifier);
+        let _ = respond.send(response);
+    });
+
+    let response = stream::once(response)
+        .filter_map(|response| future::ready(response.ok().flatten()))
+        .map(Outgoing::Response);
+
+    notifications.map(Outgoing::Notification).chain(response)
+}
+
+fn params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, (i64, String)> {
+    serde_json::from_value(params).map_err(|err| (INVALID_PARAMS, err.to_string()))
+}
+
+fn internal_error(err: AppError) -> (i64, String) {
+    error!("MCP request failed: {}", err);
+    (INTERNAL_ERROR, "Internal error".to_string())
+}
+
+/// Agrees on the client's protocol revision when we support it and offers our
+/// newest one otherwise, as the version negotiation rules require.
+pub fn negotiate_version(requested: &str) -> &'static str {
+    PROTOCOL_VERSIONS
+        .iter()
+        .find(|version| **version == requested)
+        .unwrap_or(&PROTOCOL_VERSIONS[0])
+}
+
+fn initialize(raw_params: Value, transport: Transport) -> MethodResult {
+    let params: InitializeParams = params(raw_params)?;
+
+    Ok(json!({
+        "protocolVersion": negotiate_version(&params.protocol_version),
+        "capabilities": {
+            "tools": { "listChanged": transport == Transport::Http },
+        },
+        "serverInfo": {
+            "name": "mci",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+    }))
+}
+
+/// Answers `tools/list` one page at a time. Cursors are the offset of the
+/// page they point at.
+async fn list_tools(state: &AppState, raw_params: Value) -> MethodResult {
+    let params: ListToolsParams = if raw_params.is_null() {
+        ListToolsParams::default()
+    } else {
+        params(raw_params)?
+    };
+    let offset = match params.cursor {
+        Some(cursor) => cursor
+            .parse::<u16>()
+            .map_err(|_| (INVALID_PARAMS, format!("Invalid cursor: {}", cursor)))?,
+        None => 0,
+    };
+
+    let (tools, next_offset) = tools::list_tools(state, i32::from(offset))
+        .await
+        .map_err(internal_error)?;
+
+    let mut result = json!({ "tools": tools });
+    if let Some(next_offset) = next_offset {
+        result["nextCursor"] = json!(next_offset.to_string());
+    }
+
+    Ok(result)
+}
+
+/// Turns invocation progress into `notifications/progress` messages for the
+/// token the client supplied in the request's `_meta`.
+fn progress_reporter(
+    params: &Value,
+    notifier: Option<&Notifier>,
+) -> Box<dyn Fn(Progress) + Send + Sync> {
+    let token = params
+        .get("_meta")
+        .and_then(|meta| meta.get("progressToken"))
+        .cloned();
+
+    match (token, notifier.cloned()) {
+        (Some(token), Some(notifier)) => Box::new(move |progress: Progress| {
+            let notification = Notification::new(
+                "notifications/progress",
+                json!({
+                    "progressToken": token,
+                    "progress": progress.step,
+                    "total": progress.total,
+                    "message": progress.message,
+                }),
+            );
+            // The client may have gone away; the call still runs to completion.
+            let _ = notifier.unbounded_send(notification);
+        }),
+        _ => Box::new(|_| {}),
+    }
+}
+
+async fn call_tool(
+    state: &AppState,
+    raw_params: Value,
+    notifier: Option<&Notifier>,
+) -> MethodResult {
+    let report = progress_reporter(&raw_params, notifier);
+    let params: CallToolParams = params(raw_params)?;
+
+    let tool = tools::find_tool(state, &params.name)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| (INVALID_PARAMS, format!("Unknown tool: {}", params.name)))?;
+
+    tools::call_tool(state, &tool, params.arguments, report.as_ref())
+        .await
+        .map_err(internal_error)
+}
+
+/// Whether `event` changes the tools offered to clients.
+pub fn changes_tool_list(event: &LifecycleEvent) -> bool {
+    matches!(
+        event.event_type(),
+        EventType::DefinitionEnabled
+            | EventType::DefinitionDisabled
+            | EventType::DefinitionUpgraded
+            | EventType::DefinitionDeleted
+    )
+}
+
+fn tool_list_changed() -> Notification {
+    Notification::new("notifications/tools/list_changed", Value::Null)
+}
+
+/// Tells connected sessions to refetch their tool list whenever an event
+/// from `receiver` changes it, until the bus closes.
+pub async fn run_list_change_notifier(sessions: Sessions, mut receiver: Receiver<LifecycleEvent>) {
+    loop {
+        match receiver.recv().await {
+            Ok(event) if changes_tool_list(&event) => sessions.broadcast(&tool_list_changed()),
+            Ok(_) => {}
+            Err(RecvError::Lagged(skipped)) => {
+                // One of the skipped events may have changed the list.
+                warn!("MCP notifier fell behind and skipped {} events", skipped);
+                sessions.broadcast(&tool_list_changed());
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "mcp_tests.rs"]
+mod tests;
@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const VERSION: &str = "2.0";
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
+/// A JSON-RPC 2.0 request, or a notification when `id` is absent.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Request {
+    pub jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub params: Value,
+}
+
+impl Request {
+    pub fn is_notification(&self) -> bool {
+        self.id.is_none()
+    }
+}
+
+/// A message from the server that expects no reply.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Notification {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub params: Value,
+}
+
+impl Notification {
+    pub fn new(method: impl Into<String>, params: Value) -> Self {
+        Self {
+            jsonrpc: VERSION.to_string(),
+            method: method.into(),
+            params,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ErrorObject {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Response {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorObject>,
+}
+
+impl Response {
+    pub fn success(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: VERSION.to_string(),
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn error(id: Value, code: i64, message: impl Into<String>) -> Self {
+        Self {
+            jsonrpc: VERSION.to_string(),
+            id,
+            result: None,
+            error: Some(ErrorObject {
+                code,
+                message: message.into(),
+                data: None,
+            }),
+        }
+    }
+}
+
+/// Parses a single JSON-RPC message. Failures come back as the error
+/// response to send, with a null id since the request's id is unknown.
+pub fn parse(bytes: &[u8]) -> Result<Request, Box<Response>> {
+    let value: Value = serde_json::from_slice(bytes)
+        .map_err(|err| Response::error(Value::Null, PARSE_ERROR, err.to_string()))?;
+
+    let request: Request = serde_json::from_value(value)
+        .map_err(|err| Response::error(Value::Null, INVALID_REQUEST, err.to_string()))?;
+
+    if request.jsonrpc != VERSION {
+        return Err(Box::new(Response::error(
+            request.id.unwrap_or(Value::Null),
+            INVALID_REQUEST,
+            "Unsupported JSON-RPC version",
+        )));
+    }
+
+    Ok(request)
+}
+
+#[cfg(test)]
+#[path = "jsonrpc_tests.rs"]
+mod tests;
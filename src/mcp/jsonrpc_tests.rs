@@ -0,0 +1,49 @@
+use super::*;
+use serde_json::json;
+
+#[test]
+fn test_parse_request() {
+    let request = parse(br#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#).unwrap();
+
+    assert_eq!(request.id, Some(json!(1)));
+    assert_eq!(request.method, "tools/list");
+    assert_eq!(request.params, Value::Null);
+    assert!(!request.is_notification());
+}
+
+#[test]
+fn test_parse_notification() {
+    let request = parse(br#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#).unwrap();
+
+    assert!(request.is_notification());
+}
+
+#[test]
+fn test_parse_invalid_json() {
+    let response = parse(b"{not json").unwrap_err();
+
+    assert_eq!(response.id, Value::Null);
+    assert_eq!(response.error.unwrap().code, PARSE_ERROR);
+}
+
+#[test]
+fn test_parse_invalid_request() {
+    let batch = parse(br#"[{"jsonrpc":"2.0","id":1,"method":"ping"}]"#).unwrap_err();
+    assert_eq!(batch.error.unwrap().code, INVALID_REQUEST);
+
+    let version = parse(br#"{"jsonrpc":"1.0","id":7,"method":"ping"}"#).unwrap_err();
+    assert_eq!(version.id, json!(7));
+    assert_eq!(version.error.unwrap().code, INVALID_REQUEST);
+}
+
+#[test]
+fn test_response_serialization() {
+    assert_eq!(
+        serde_json::to_value(Response::success(json!(1), json!({}))).unwrap(),
+        json!({ "jsonrpc": "2.0", "id": 1, "result": {} })
+    );
+    assert_eq!(
+        serde_json::to_value(Response::error(json!("a"), METHOD_NOT_FOUND, "nope")).unwrap(),
+        json!({ "jsonrpc": "2.0", "id": "a", "error": { "code": -32601, "message": "nope" } })
+    );
+}
@@ -0,0 +1,175 @@
+use super::jsonrpc::Notification;
+use crate::config::Config;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Header carrying the session id on every request after `initialize`.
+pub const SESSION_HEADER: &str = "mcp-session-id";
+/// Header carrying the negotiated protocol version on every request after
+/// `initialize`.
+pub const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+
+const NOTIFICATION_CAPACITY: usize = 64;
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const MAX_SESSIONS: usize = 1024;
+
+struct Session {
+    protocol_version: String,
+    notifications: broadcast::Sender<Notification>,
+    last_seen: Instant,
+}
+
+impl Session {
+    /// A session with an open notification stream is never idle.
+    fn is_expired(&self, idle_timeout: Duration) -> bool {
+        self.notifications.receiver_count() == 0 && self.last_seen.elapsed() > idle_timeout
+    }
+}
+
+/// MCP sessions of the streamable HTTP transport, keyed by the id handed out
+/// in the `Mcp-Session-Id` header.
+///
+/// Sessions idle for longer than the idle timeout expire, and once the
+/// maximum number of sessions is reached the least recently used one is
+/// ended to make room. Clients of an ended session get `404` and initialize
+/// again. Browser requests are only accepted from the allowed origins, which
+/// guards local servers against DNS rebinding.
+#[derive(Clone)]
+pub struct Sessions {
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+    idle_timeout: Duration,
+    max_sessions: usize,
+    allowed_origins: Arc<Vec<String>>,
+}
+
+impl Default for Sessions {
+    fn default() -> Self {
+        Self {
+            sessions: Arc::default(),
+            idle_timeout: IDLE_TIMEOUT,
+            max_sessions: MAX_SESSIONS,
+            allowed_origins: Arc::default(),
+        }
+    }
+}
+
+impl Sessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sessions accepting the origins listed, comma separated, in
+    /// `MCI_MCP_ALLOWED_ORIGINS`.
+    pub fn from_config(config: &Config) -> Self {
+        let allowed_origins = config
+            .mcp_allowed_origins
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(|origin| origin.trim().trim_end_matches('/'))
+            .filter(|origin| !origin.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        Self::new().with_allowed_origins(allowed_origins)
+    }
+
+    pub fn with_limits(mut self, idle_timeout: Duration, max_sessions: usize) -> Self {
+        self.idle_timeout = idle_timeout;
+        self.max_sessions = max_sessions;
+        self
+    }
+
+    pub fn with_allowed_origins(mut self, allowed_origins: Vec<String>) -> Self {
+        self.allowed_origins = Arc::new(allowed_origins);
+        self
+    }
+
+    /// Whether requests sent with the `Origin` header `origin` are accepted.
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        let origin = origin.trim_end_matches('/');
+
+        self.allowed_origins
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(origin))
+    }
+
+    /// Starts a session that negotiated `protocol_version` and returns its id.
+    pub fn create(&self, protocol_version: &str) -> String {
+        let id = Uuid::new_v4().simple().to_string();
+        let (notifications, _) = broadcast::channel(NOTIFICATION_CAPACITY);
+
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, session| !session.is_expired(self.idle_timeout));
+        if sessions.len() >= self.max_sessions {
+            let least_recent = sessions
+                .iter()
+                .min_by_key(|(_, session)| session.last_seen)
+                .map(|(id, _)| id.clone());
+            if let Some(least_recent) = least_recent {
+                sessions.remove(&least_recent);
+            }
+        }
+
+        sessions.insert(
+            id.clone(),
+            Session {
+                protocol_version: protocol_version.to_string(),
+                notifications,
+                last_seen: Instant::now(),
+            },
+        );
+
+        id
+    }
+
+    /// Marks session `id` as active and returns the protocol version it
+    /// negotiated, or `None` when the session is unknown or has expired.
+    pub fn touch(&self, id: &str) -> Option<String> {
+        let mut sessions = self.sessions.lock().unwrap();
+
+        match sessions.get_mut(id) {
+            Some(session) if !session.is_expired(self.idle_timeout) => {
+                session.last_seen = Instant::now();
+                Some(session.protocol_version.clone())
+            }
+            Some(_) => {
+                sessions.remove(id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Subscribes to the notifications sent to session `id`. The receiver
+    /// closes when the session ends.
+    pub fn subscribe(&self, id: &str) -> Option<broadcast::Receiver<Notification>> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|session| session.notifications.subscribe())
+    }
+
+    /// Sends `notification` to every session with an open stream.
+    pub fn broadcast(&self, notification: &Notification) {
+        for session in self.sessions.lock().unwrap().values() {
+            // Sessions without a listening stream simply miss the message.
+            let _ = session.notifications.send(notification.clone());
+        }
+    }
+
+    /// Ends session `id`. Returns whether it existed.
+    pub fn remove(&self, id: &str) -> bool {
+        self.sessions.lock().unwrap().remove(id).is_some()
+    }
+}
+
+#[cfg(test)]
+#[path = "sessions_tests.rs"]
+mod tests;
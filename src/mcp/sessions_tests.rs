@@ -0,0 +1,74 @@
+use super::*;
+use serde_json::json;
+
+#[test]
+fn test_session_lifecycle() {
+    let sessions = Sessions::new();
+    let id = sessions.create("2025-06-18");
+
+    assert_eq!(sessions.touch(&id).as_deref(), Some("2025-06-18"));
+    assert_eq!(sessions.touch("unknown"), None);
+
+    assert!(sessions.remove(&id));
+    assert_eq!(sessions.touch(&id), None);
+    assert!(!sessions.remove(&id));
+}
+
+#[test]
+fn test_idle_sessions_expire() {
+    let sessions = Sessions::new().with_limits(Duration::ZERO, MAX_SESSIONS);
+    let idle = sessions.create("2025-06-18");
+    let streaming = sessions.create("2025-06-18");
+    let _stream = sessions.subscribe(&streaming).unwrap();
+
+    std::thread::sleep(Duration::from_millis(5));
+
+    assert_eq!(sessions.touch(&idle), None);
+    assert!(sessions.touch(&streaming).is_some());
+}
+
+#[test]
+fn test_least_recently_used_session_makes_room() {
+    let sessions = Sessions::new().with_limits(IDLE_TIMEOUT, 2);
+    let first = sessions.create("2025-06-18");
+    let second = sessions.create("2025-06-18");
+    assert!(sessions.touch(&first).is_some());
+
+    let third = sessions.create("2025-06-18");
+
+    assert!(sessions.touch(&first).is_some());
+    assert_eq!(sessions.touch(&second), None);
+    assert!(sessions.touch(&third).is_some());
+}
+
+#[test]
+fn test_allows_origin() {
+    let sessions =
+        Sessions::new().with_allowed_origins(vec!["https://app.example.com".to_string()]);
+
+    assert!(sessions.allows_origin("https://app.example.com"));
+    assert!(sessions.allows_origin("https://APP.example.com/"));
+    assert!(!sessions.allows_origin("http://app.example.com"));
+    assert!(!sessions.allows_origin("https://evil.example.com"));
+    assert!(!Sessions::new().allows_origin("http://localhost:3000"));
+}
+
+#[tokio::test]
+async fn test_broadcast_reaches_subscribed_sessions() {
+    let sessions = Sessions::new();
+    let first = sessions.create("2025-06-18");
+    let second = sessions.create("2025-03-26");
+
+    let mut first_rx = sessions.subscribe(&first).unwrap();
+    let mut second_rx = sessions.subscribe(&second).unwrap();
+    assert!(sessions.subscribe("unknown").is_none());
+
+    let notification = Notification::new("notifications/tools/list_changed", json!(null));
+    sessions.broadcast(&notification);
+
+    assert_eq!(first_rx.recv().await.unwrap(), notification);
+    assert_eq!(second_rx.recv().await.unwrap(), notification);
+
+    sessions.remove(&first);
+    assert!(first_rx.recv().await.is_err());
+}
@@ -0,0 +1,61 @@
+use super::{handle_streaming, jsonrpc, Outgoing, Transport};
+use crate::AppState;
+use futures::{channel::mpsc, StreamExt};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Serves MCP over the stdio transport: newline-delimited JSON-RPC messages
+/// are read from `input` and answered on `output`. Requests are handled
+/// concurrently, so a long tool call does not hold up pings. Returns once
+/// `input` ends and every pending request has been answered.
+pub async fn serve<R, W>(state: AppState, input: R, mut output: W) -> std::io::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let (outgoing, mut pending) = mpsc::unbounded::<Outgoing>();
+
+    let read = async move {
+        let mut lines = input.lines();
+
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let request = match jsonrpc::parse(line.as_bytes()) {
+                Ok(request) => request,
+                Err(response) => {
+                    let _ = outgoing.unbounded_send(Outgoing::Response(*response));
+                    continue;
+                }
+            };
+
+            let mut messages = handle_streaming(state.clone(), request, Transport::Stdio);
+            let outgoing = outgoing.clone();
+            tokio::spawn(async move {
+                while let Some(message) = messages.next().await {
+                    if outgoing.unbounded_send(message).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        Ok::<_, std::io::Error>(())
+    };
+
+    let write = async {
+        while let Some(message) = pending.next().await {
+            let mut line = serde_json::to_vec(&message)?;
+            line.push(b'\n');
+            output.write_all(&line).await?;
+            output.flush().await?;
+        }
+
+        Ok::<_, std::io::Error>(())
+    };
+
+    let (read, write) = tokio::join!(read, write);
+
+    read.and(write)
+}
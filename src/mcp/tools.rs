@@ -0,0 +1,193 @@
+use crate::{
+    errors::AppError,
+    models::Definition,
+    s3,
+    services::{
+        definitions_services::{self, DefinitionFilter, SortBy, SortOrder},
+        invocation_services::{self, Progress},
+    },
+    utils::schema_utils,
+    AppState,
+};
+use diesel::OptionalExtension;
+use futures::{stream, StreamExt};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tracing::warn;
+
+/// Tools returned per `tools/list` page.
+pub const PAGE_SIZE: i32 = 100;
+/// Definition documents read at once while building a page.
+const DOCUMENT_READS: usize = 8;
+
+/// An enabled definition as presented to MCP clients.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tool {
+    pub name: String,
+    pub title: String,
+    pub description: String,
+    pub input_schema: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+}
+
+/// Schema advertised for definitions whose document does not declare one.
+fn open_input_schema() -> Value {
+    json!({ "type": "object" })
+}
+
+/// Builds the tool for `definition`. The stored definition document provides
+/// the name, description and schemas; the registry row fills in whatever the
+/// document leaves out, and also covers documents stored before their
+/// structure was validated on install.
+pub fn tool_from_document(definition: &Definition, document: Option<&Value>) -> Tool {
+    let field = |name: &str| document.and_then(|document| document.get(name));
+    let text = |name: &str| field(name).and_then(Value::as_str).map(str::to_string);
+
+    Tool {
+        name: definition.id.clone(),
+        title: text("name").unwrap_or_else(|| definition.name.clone()),
+        description: text("description").unwrap_or_else(|| definition.description.clone()),
+        input_schema: field("input_schema")
+            .filter(|schema| schema.is_object())
+            .cloned()
+            .unwrap_or_else(open_input_schema),
+        output_schema: field("output_schema")
+            .filter(|schema| schema.is_object())
+            .cloned(),
+    }
+}
+
+async fn read_document(
+    state: &AppState,
+    definition: &Definition,
+) -> Result<Option<Value>, AppError> {
+    let bytes = s3::get_bytes(
+        &state.s3_client,
+        "definitions",
+        &definition.definition_object_key,
+    )
+    .await?;
+
+    Ok(bytes.and_then(|bytes| serde_json::from_slice(&bytes).ok()))
+}
+
+async fn load_tool(state: &AppState, definition: &Definition) -> Result<Tool, AppError> {
+    let document = read_document(state, definition).await?;
+
+    Ok(tool_from_document(definition, document.as_ref()))
+}
+
+/// Returns the page of tools starting at `offset`, and the offset of the next
+/// page when there is one. A definition whose document cannot be read is
+/// still listed, described by its registry row alone.
+pub async fn list_tools(
+    state: &AppState,
+    offset: i32,
+) -> Result<(Vec<Tool>, Option<i32>), AppError> {
+    let filter = DefinitionFilter {
+        is_enabled: Some(true),
+        limit: Some(PAGE_SIZE + 1),
+        offset: Some(offset),
+        sort_by: Some(SortBy::Id),
+        sort_order: Some(SortOrder::Asc),
+        ..Default::default()
+    };
+    let mut conn = state.db_pool.get()?;
+
+    let mut definitions = tokio::task::spawn_blocking(move || {
+        definitions_services::list_definitions(&mut conn, &filter)
+    })
+    .await??;
+
+    let next_offset = (definitions.len() > PAGE_SIZE as usize).then_some(offset + PAGE_SIZE);
+    definitions.truncate(PAGE_SIZE as usize);
+
+    let tools = stream::iter(definitions)
+        .map(|definition| async move {
+            match read_document(state, &definition).await {
+                Ok(document) => tool_from_document(&definition, document.as_ref()),
+                Err(err) => {
+                    warn!(definition = %definition.id, "Failed to read definition document: {}", err);
+                    tool_from_document(&definition, None)
+                }
+            }
+        })
+        .buffered(DOCUMENT_READS)
+        .collect()
+        .await;
+
+    Ok((tools, next_offset))
+}
+
+/// Looks up the enabled definition exposed as tool `name`.
+pub async fn find_tool(state: &AppState, name: &str) -> Result<Option<Tool>, AppError> {
+    let mut conn = state.db_pool.get()?;
+    let name = name.to_string();
+
+    let definition = tokio::task::spawn_blocking(move || {
+        definitions_services::get_definition(&mut conn, &name).optional()
+    })
+    .await??;
+
+    match definition {
+        Some(definition) if definition.is_enabled => Ok(Some(load_tool(state, &definition).await?)),
+        _ => Ok(None),
+    }
+}
+
+/// Calls `tool` and wraps the outcome as an MCP `CallToolResult`. Failures of
+/// the tool itself are reported in the result so the model can see them;
+/// only infrastructure failures become errors.
+pub async fn call_tool(
+    state: &AppState,
+    tool: &Tool,
+    arguments: Value,
+    report: &(dyn Fn(Progress) + Send + Sync),
+) -> Result<Value, AppError> {
+    if let Err(err) = schema_utils::validate_instance(&tool.input_schema, &arguments) {
+        return Ok(error_result(&err));
+    }
+
+    match invocation_services::invoke_definition_with_progress(state, &tool.name, arguments, report)
+        .await
+    {
+        Ok(result) => Ok(success_result(result)),
+        Err(
+            err @ (AppError::Internal(_)
+            | AppError::Pool(_)
+            | AppError::Database(_)
+            | AppError::TaskJoin(_)),
+        ) => Err(err),
+        Err(err) => Ok(error_result(&err)),
+    }
+}
+
+fn success_result(result: Value) -> Value {
+    let text = match &result {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+
+    let mut call_result = json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": false,
+    });
+    if result.is_object() {
+        call_result["structuredContent"] = result;
+    }
+
+    call_result
+}
+
+fn error_result(err: &AppError) -> Value {
+    json!({
+        "content": [{ "type": "text", "text": err.to_string() }],
+        "isError": true,
+    })
+}
+
+#[cfg(test)]
+#[path = "tools_tests.rs"]
+mod tests;
@@ -0,0 +1,76 @@
+use super::*;
+
+fn definition() -> Definition {
+    Definition {
+        id: "weather".to_string(),
+        type_: "http".to_string(),
+        is_enabled: true,
+        name: "Weather".to_string(),
+        description: "Registry description".to_string(),
+        definition_object_key: "weather".to_string(),
+        configuration_object_key: "weather".to_string(),
+        secrets_object_key: "weather".to_string(),
+        digest: "sha256:abc123".to_string(),
+        source_url: None,
+        is_pinned: true,
+    }
+}
+
+#[test]
+fn test_tool_from_document() {
+    let document = json!({
+        "name": "Current weather",
+        "description": "Looks up the weather for a city",
+        "input_schema": {
+            "type": "object",
+            "properties": { "city": { "type": "string" } },
+            "required": ["city"]
+        },
+        "output_schema": { "type": "object" }
+    });
+
+    let tool = tool_from_document(&definition(), Some(&document));
+
+    assert_eq!(tool.name, "weather");
+    assert_eq!(tool.title, "Current weather");
+    assert_eq!(tool.description, "Looks up the weather for a city");
+    assert_eq!(tool.input_schema["required"], json!(["city"]));
+    assert_eq!(tool.output_schema, Some(json!({ "type": "object" })));
+}
+
+#[test]
+fn test_tool_from_document_falls_back_to_registry() {
+    let tool = tool_from_document(&definition(), None);
+
+    assert_eq!(tool.title, "Weather");
+    assert_eq!(tool.description, "Registry description");
+    assert_eq!(tool.input_schema, json!({ "type": "object" }));
+    assert_eq!(tool.output_schema, None);
+
+    let serialized = serde_json::to_value(&tool).unwrap();
+    assert!(serialized.get("inputSchema").is_some());
+    assert!(serialized.get("outputSchema").is_none());
+}
+
+#[test]
+fn test_success_result() {
+    let structured = success_result(json!({ "temperature": 21 }));
+    assert_eq!(structured["isError"], false);
+    assert_eq!(structured["content"][0]["text"], r#"{"temperature":21}"#);
+    assert_eq!(
+        structured["structuredContent"],
+        json!({ "temperature": 21 })
+    );
+
+    let text = success_result(json!("sunny"));
+    assert_eq!(text["content"][0]["text"], "sunny");
+    assert!(text.get("structuredContent").is_none());
+}
+
+#[test]
+fn test_error_result() {
+    let result = error_result(&AppError::forbidden("blocked by policy"));
+
+    assert_eq!(result["isError"], true);
+    assert_eq!(result["content"][0]["text"], "Forbidden: blocked by policy");
+}
@@ -0,0 +1,66 @@
+use super::*;
+
+#[test]
+fn test_negotiate_version() {
+    assert_eq!(negotiate_version("2025-03-26"), "2025-03-26");
+    assert_eq!(negotiate_version("2024-11-05"), "2024-11-05");
+    assert_eq!(negotiate_version("1999-01-01"), PROTOCOL_VERSIONS[0]);
+}
+
+#[test]
+fn test_initialize() {
+    let params = json!({
+        "protocolVersion": "2025-03-26",
+        "capabilities": {},
+        "clientInfo": { "name": "test", "version": "1.0" }
+    });
+    let result = initialize(params.clone(), Transport::Http).unwrap();
+
+    assert_eq!(result["protocolVersion"], "2025-03-26");
+    assert_eq!(result["serverInfo"]["name"], "mci");
+    assert_eq!(result["capabilities"]["tools"]["listChanged"], true);
+
+    // The stdio process never hears about registry changes.
+    let result = initialize(params, Transport::Stdio).unwrap();
+    assert_eq!(result["capabilities"]["tools"]["listChanged"], false);
+}
+
+#[test]
+fn test_initialize_requires_protocol_version() {
+    let (code, _) = initialize(json!({}), Transport::Http).unwrap_err();
+
+    assert_eq!(code, INVALID_PARAMS);
+}
+
+#[test]
+fn test_changes_tool_list() {
+    let deleted = LifecycleEvent::DefinitionDeleted {
+        id: "acme::search".to_string(),
+    };
+    let module_deleted = LifecycleEvent::ModuleDeleted {
+        id: "acme::pii".to_string(),
+    };
+
+    assert!(changes_tool_list(&deleted));
+    assert!(!changes_tool_list(&module_deleted));
+}
+
+#[tokio::test]
+async fn test_list_change_notifier_broadcasts_to_sessions() {
+    let bus = crate::events::EventBus::new();
+    let sessions = Sessions::new();
+    let session_id = sessions.create(PROTOCOL_VERSIONS[0]);
+    let mut notifications = sessions.subscribe(&session_id).unwrap();
+
+    tokio::spawn(run_list_change_notifier(sessions.clone(), bus.subscribe()));
+    bus.publish(LifecycleEvent::ModuleDeleted {
+        id: "acme::pii".to_string(),
+    });
+    bus.publish(LifecycleEvent::DefinitionDeleted {
+        id: "acme::search".to_string(),
+    });
+
+    let notification = notifications.recv().await.unwrap();
+    assert_eq!(notification.method, "notifications/tools/list_changed");
+    assert!(notifications.try_recv().is_err());
+}
@@ -1,16 +1,24 @@
 use crate::{
-    schema::{definitions, modules, sql_types},
+    errors::AppError,
+    events::EventType,
+    schema::{
+        definitions, executions, hook_subscriptions, interceptor_bindings, language_bindings,
+        modules, proxy_bindings, sql_types,
+    },
     utils::regex_utils,
 };
+use chrono::{DateTime, Utc};
 use diesel::{
     deserialize::{self, FromSql},
     pg::{Pg, PgValue},
     prelude::*,
     serialize::{self, IsNull, Output, ToSql},
+    sql_types::Jsonb,
     AsExpression, FromSqlRow,
 };
 use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, io::Write};
+use uuid::Uuid;
 use validator::{Validate, ValidationError};
 
 fn validate_digest(digest: &str) -> Result<(), ValidationError> {
@@ -21,6 +29,9 @@ fn validate_digest(digest: &str) -> Result<(), ValidationError> {
     })?;
     let hash_regex = match algorithm {
         "sha256" => &regex_utils::SHA256,
+        "sha384" => &regex_utils::SHA384,
+        "sha512" => &regex_utils::SHA512,
+        "blake3" => &regex_utils::BLAKE3,
         _ => {
             let mut error = ValidationError::new("unsupported_digest_algorithm");
             error.add_param(Cow::from("value"), &digest);
@@ -39,7 +50,7 @@ fn validate_digest(digest: &str) -> Result<(), ValidationError> {
     }
 }
 
-#[derive(Queryable, Selectable, Serialize, Deserialize)]
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
 #[diesel(table_name = definitions)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Definition {
@@ -53,6 +64,8 @@ pub struct Definition {
     pub secrets_object_key: String,
     pub digest: String,
     pub source_url: Option<String>,
+    /// Whether the installed content was checked against a published digest.
+    pub is_pinned: bool,
 }
 
 #[derive(Insertable, Deserialize, Validate)]
@@ -81,6 +94,8 @@ pub struct NewDefinition {
 
     #[validate(url)]
     pub source_url: Option<String>,
+
+    pub is_pinned: bool,
 }
 
 #[derive(AsChangeset, Default, Deserialize, Validate)]
@@ -102,6 +117,22 @@ pub struct UpdateDefinition {
 
     #[validate(url)]
     pub source_url: Option<String>,
+
+    #[serde(skip)]
+    pub is_pinned: Option<bool>,
+}
+
+/// Operator choices for upgrading content from its recorded source.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+pub struct UpgradeOptions {
+    /// Lets content installed with a pinned digest upgrade to an upstream
+    /// that no longer publishes one.
+    #[serde(default)]
+    pub allow_unpinned: bool,
+    /// Resets a stored configuration the upgraded schema rejects to the new
+    /// defaults instead of refusing the upgrade.
+    #[serde(default)]
+    pub reset_configuration: bool,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -137,10 +168,33 @@ impl UpdateDefinitionRequest {
             description: self.description,
             digest: self.digest,
             source_url: self.source_url,
+            is_pinned: None,
         }
     }
 }
 
+/// Checks the id of a definition or module before anything is stored under
+/// it. The id doubles as an object key in a bucket shared with the
+/// configuration and secrets of every other resource, so it must not be able
+/// to name any of those.
+pub fn ensure_valid_id(id: &str) -> Result<(), AppError> {
+    let length = id.chars().count();
+    if !(3..=64).contains(&length) {
+        return Err(AppError::bad_request(format!(
+            "Id '{}' must be between 3 and 64 characters long",
+            id
+        )));
+    }
+    if !regex_utils::NAMESPACE_ID.is_match(id) {
+        return Err(AppError::bad_request(format!(
+            "Id '{}' may only contain letters, digits, '_', '.' and '-'",
+            id
+        )));
+    }
+
+    Ok(())
+}
+
 fn validate_digest_with_file_url(
     digest: &Option<String>,
     file_url: &Option<String>,
@@ -168,16 +222,21 @@ pub enum ModuleType {
     Hook,
 }
 
-impl ToSql<sql_types::ModuleType, Pg> for ModuleType {
-    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
-        let value = match self {
+impl ModuleType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
             ModuleType::Language => "language",
             ModuleType::Sandbox => "sandbox",
             ModuleType::Interceptor => "interceptor",
             ModuleType::Proxy => "proxy",
             ModuleType::Hook => "hook",
-        };
-        out.write_all(value.as_bytes())?;
+        }
+    }
+}
+
+impl ToSql<sql_types::ModuleType, Pg> for ModuleType {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
         Ok(IsNull::No)
     }
 }
@@ -195,7 +254,98 @@ impl FromSql<sql_types::ModuleType, Pg> for ModuleType {
     }
 }
 
-#[derive(Queryable, Selectable, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreopenedDir {
+    pub host_path: String,
+    pub guest_path: String,
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+/// The privileges a module is granted beyond pure computation. Everything not
+/// listed here is denied.
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, AsExpression, FromSqlRow,
+)]
+#[diesel(sql_type = Jsonb)]
+#[serde(default, deny_unknown_fields)]
+pub struct Capabilities {
+    /// Host directories mounted into the module's WASI filesystem.
+    pub preopened_dirs: Vec<PreopenedDir>,
+    /// Hosts reachable through `mci.http_request`. `*.example.com` matches
+    /// any subdomain of `example.com`.
+    pub allowed_hosts: Vec<String>,
+    /// Names of MCI process environment variables exposed to the module.
+    pub environment: Vec<String>,
+}
+
+impl Capabilities {
+    pub fn allows_host(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+
+        self.allowed_hosts.iter().any(|pattern| {
+            let pattern = pattern.to_ascii_lowercase();
+            match pattern.strip_prefix("*.") {
+                Some(suffix) => host
+                    .strip_suffix(suffix)
+                    .is_some_and(|prefix| prefix.ends_with('.')),
+                None => host == pattern,
+            }
+        })
+    }
+
+    /// Describes every capability in `self` that `granted` does not cover.
+    pub fn ungranted(&self, granted: &Capabilities) -> Vec<String> {
+        let mut missing = Vec::new();
+
+        for dir in &self.preopened_dirs {
+            let covered = granted.preopened_dirs.iter().any(|grant| {
+                grant.host_path == dir.host_path
+                    && grant.guest_path == dir.guest_path
+                    && (dir.read_only || !grant.read_only)
+            });
+            if !covered {
+                missing.push(format!(
+                    "directory '{}' at '{}'{}",
+                    dir.host_path,
+                    dir.guest_path,
+                    if dir.read_only { "" } else { " (read-write)" }
+                ));
+            }
+        }
+
+        for host in &self.allowed_hosts {
+            if !granted.allowed_hosts.contains(host) {
+                missing.push(format!("host '{}'", host));
+            }
+        }
+
+        for name in &self.environment {
+            if !granted.environment.contains(name) {
+                missing.push(format!("environment variable '{}'", name));
+            }
+        }
+
+        missing
+    }
+}
+
+impl ToSql<Jsonb, Pg> for Capabilities {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(&[1])?;
+        serde_json::to_writer(out, self)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Jsonb, Pg> for Capabilities {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let value = <serde_json::Value as FromSql<Jsonb, Pg>>::from_sql(bytes)?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
 #[diesel(table_name = modules)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Module {
@@ -209,6 +359,12 @@ pub struct Module {
     pub secrets_object_key: String,
     pub digest: String,
     pub source_url: Option<String>,
+    pub max_memory_bytes: i64,
+    pub fuel_limit: i64,
+    pub timeout_ms: i32,
+    pub capabilities: Capabilities,
+    /// Whether the installed module was checked against a published digest.
+    pub is_pinned: bool,
 }
 
 #[derive(Insertable, Deserialize, Validate)]
@@ -236,6 +392,10 @@ pub struct NewModule {
 
     #[validate(url)]
     pub source_url: Option<String>,
+
+    pub capabilities: Capabilities,
+
+    pub is_pinned: bool,
 }
 
 #[derive(AsChangeset, Default, Deserialize, Validate)]
@@ -254,6 +414,21 @@ pub struct UpdateModule {
 
     #[validate(url)]
     pub source_url: Option<String>,
+
+    #[validate(range(min = 65536, max = 4_294_967_296i64))]
+    pub max_memory_bytes: Option<i64>,
+
+    #[validate(range(min = 1))]
+    pub fuel_limit: Option<i64>,
+
+    #[validate(range(min = 1, max = 300000))]
+    pub timeout_ms: Option<i32>,
+
+    #[serde(skip)]
+    pub capabilities: Option<Capabilities>,
+
+    #[serde(skip)]
+    pub is_pinned: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -275,6 +450,15 @@ pub struct UpdateModuleRequest {
 
     #[validate(url)]
     pub source_url: Option<String>,
+
+    #[validate(range(min = 65536, max = 4_294_967_296i64))]
+    pub max_memory_bytes: Option<i64>,
+
+    #[validate(range(min = 1))]
+    pub fuel_limit: Option<i64>,
+
+    #[validate(range(min = 1, max = 300000))]
+    pub timeout_ms: Option<i32>,
 }
 
 impl UpdateModuleRequest {
@@ -285,6 +469,11 @@ impl UpdateModuleRequest {
             description: self.description,
             digest: self.digest,
             source_url: self.source_url,
+            max_memory_bytes: self.max_memory_bytes,
+            fuel_limit: self.fuel_limit,
+            timeout_ms: self.timeout_ms,
+            capabilities: None,
+            is_pinned: None,
         }
     }
 }
@@ -293,6 +482,242 @@ fn validate_module_update_request(req: &UpdateModuleRequest) -> Result<(), Valid
     validate_digest_with_file_url(&req.digest, &req.file_url)
 }
 
+/// Places an interceptor module in the chain around definition invocations.
+/// Lower priorities run first on requests and last on responses. A binding
+/// with no definition ids or types applies to every definition.
+#[derive(Debug, Clone, Queryable, Selectable, Insertable, AsChangeset, Serialize, Deserialize)]
+#[diesel(table_name = interceptor_bindings)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct InterceptorBinding {
+    pub module_id: String,
+    pub priority: i32,
+    pub definition_ids: Vec<String>,
+    pub definition_types: Vec<String>,
+}
+
+impl InterceptorBinding {
+    pub fn applies_to(&self, definition: &Definition) -> bool {
+        if self.definition_ids.is_empty() && self.definition_types.is_empty() {
+            return true;
+        }
+
+        self.definition_ids.contains(&definition.id)
+            || self.definition_types.contains(&definition.type_)
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct InterceptorBindingRequest {
+    #[serde(default)]
+    pub priority: i32,
+
+    #[serde(default)]
+    #[validate(length(max = 256))]
+    pub definition_ids: Vec<String>,
+
+    #[serde(default)]
+    #[validate(length(max = 256))]
+    pub definition_types: Vec<String>,
+}
+
+impl InterceptorBindingRequest {
+    pub fn into_binding(self, module_id: String) -> InterceptorBinding {
+        InterceptorBinding {
+            module_id,
+            priority: self.priority,
+            definition_ids: self.definition_ids,
+            definition_types: self.definition_types,
+        }
+    }
+}
+
+/// Routes invocations of a definition through a proxy module, which forwards
+/// them to `upstream_url`.
+#[derive(Debug, Clone, Queryable, Selectable, Insertable, AsChangeset, Serialize, Deserialize)]
+#[diesel(table_name = proxy_bindings)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ProxyBinding {
+    pub definition_id: String,
+    pub module_id: String,
+    pub upstream_url: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ProxyBindingRequest {
+    #[validate(length(min = 3, max = 64))]
+    pub module_id: String,
+
+    #[validate(url)]
+    pub upstream_url: String,
+}
+
+impl ProxyBindingRequest {
+    pub fn into_binding(self, definition_id: String) -> ProxyBinding {
+        ProxyBinding {
+            definition_id,
+            module_id: self.module_id,
+            upstream_url: self.upstream_url,
+        }
+    }
+}
+
+/// Makes definitions of `definition_type` executable by evaluating their
+/// stored source with a language module.
+#[derive(Debug, Clone, Queryable, Selectable, Insertable, AsChangeset, Serialize, Deserialize)]
+#[diesel(table_name = language_bindings)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct LanguageBinding {
+    pub definition_type: String,
+    pub module_id: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct LanguageBindingRequest {
+    #[validate(length(min = 3, max = 64))]
+    pub module_id: String,
+}
+
+impl LanguageBindingRequest {
+    pub fn into_binding(self, definition_type: String) -> LanguageBinding {
+        LanguageBinding {
+            definition_type,
+            module_id: self.module_id,
+        }
+    }
+}
+
+/// The lifecycle events a hook module is invoked for.
+#[derive(Debug, Clone, Queryable, Selectable, Insertable, AsChangeset, Serialize, Deserialize)]
+#[diesel(table_name = hook_subscriptions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct HookSubscription {
+    pub module_id: String,
+    /// Events the hook is notified of once committed.
+    pub events: Vec<String>,
+    /// Events the hook must approve before they are committed.
+    pub approvals: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "validate_subscription_request"))]
+pub struct HookSubscriptionRequest {
+    #[serde(default)]
+    pub events: Vec<EventType>,
+
+    #[serde(default)]
+    pub approvals: Vec<EventType>,
+}
+
+fn validate_subscription_request(req: &HookSubscriptionRequest) -> Result<(), ValidationError> {
+    if req.events.is_empty() && req.approvals.is_empty() {
+        let mut error = ValidationError::new("empty_subscription");
+        error.message = Some("subscribe to at least one event or approval".into());
+        return Err(error);
+    }
+    if let Some(event) = req.approvals.iter().find(|event| !event.is_approvable()) {
+        let mut error = ValidationError::new("unapprovable_event");
+        error.add_param(Cow::from("value"), &event.as_str());
+        error.message = Some("only created, upgraded and enabled events can be approved".into());
+        return Err(error);
+    }
+    Ok(())
+}
+
+fn event_names(events: &[EventType]) -> Vec<String> {
+    let mut names: Vec<String> = events
+        .iter()
+        .map(|event| event.as_str().to_string())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+impl HookSubscriptionRequest {
+    pub fn into_subscription(self, module_id: String) -> HookSubscription {
+        HookSubscription {
+            module_id,
+            events: event_names(&self.events),
+            approvals: event_names(&self.approvals),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[diesel(sql_type = sql_types::ExecutionStatus)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionStatus {
+    Running,
+    /// The command ran to completion; see the exit code for its outcome.
+    Completed,
+    /// The module trapped, ran out of fuel or timed out.
+    Failed,
+}
+
+impl ExecutionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExecutionStatus::Running => "running",
+            ExecutionStatus::Completed => "completed",
+            ExecutionStatus::Failed => "failed",
+        }
+    }
+}
+
+impl ToSql<sql_types::ExecutionStatus, Pg> for ExecutionStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<sql_types::ExecutionStatus, Pg> for ExecutionStatus {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"running" => Ok(ExecutionStatus::Running),
+            b"completed" => Ok(ExecutionStatus::Completed),
+            b"failed" => Ok(ExecutionStatus::Failed),
+            _ => Err("Unrecognized enum variant for ExecutionStatus".into()),
+        }
+    }
+}
+
+/// A run of a sandbox module.
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = executions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Execution {
+    pub id: Uuid,
+    pub module_id: String,
+    pub status: ExecutionStatus,
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub duration_ms: Option<i64>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = executions)]
+pub struct NewExecution {
+    pub id: Uuid,
+    pub module_id: String,
+}
+
+#[derive(AsChangeset)]
+#[diesel(table_name = executions)]
+pub struct FinishedExecution {
+    pub status: ExecutionStatus,
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub error: Option<String>,
+    pub finished_at: DateTime<Utc>,
+    pub duration_ms: i64,
+}
+
 #[derive(Serialize)]
 pub struct Build {
     pub id: i32,
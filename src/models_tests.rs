@@ -6,6 +6,32 @@ fn test_validate_digest_valid_sha256() {
     assert!(validate_digest(digest).is_ok());
 }
 
+#[test]
+fn test_validate_digest_valid_other_algorithms() {
+    assert!(validate_digest(&format!("sha384:{}", "a".repeat(96))).is_ok());
+    assert!(validate_digest(&format!("sha512:{}", "a".repeat(128))).is_ok());
+    assert!(validate_digest(&format!("blake3:{}", "a".repeat(64))).is_ok());
+}
+
+#[test]
+fn test_validate_digest_hash_length_matches_algorithm() {
+    let result = validate_digest(&format!("sha512:{}", "a".repeat(64)));
+    assert_eq!(result.unwrap_err().code, "invalid_hash_format");
+}
+
+#[test]
+fn test_ensure_valid_id() {
+    assert!(ensure_valid_id("weather.api-v2").is_ok());
+
+    for id in ["secrets/foo", "ab", "../x", &"a".repeat(65), "spa ce"] {
+        assert!(
+            matches!(ensure_valid_id(id), Err(AppError::BadRequest(_))),
+            "{} should be rejected",
+            id
+        );
+    }
+}
+
 #[test]
 fn test_validate_digest_missing_colon() {
     let digest = "sha256e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
@@ -106,3 +132,137 @@ fn test_update_definition_no_digest_no_file_url_accepted() {
     };
     assert!(req.validate().is_ok());
 }
+
+#[test]
+fn test_update_module_limits_validated() {
+    let request = |max_memory_bytes, fuel_limit, timeout_ms| UpdateModuleRequest {
+        is_enabled: None,
+        name: None,
+        description: None,
+        file_url: None,
+        digest: None,
+        source_url: None,
+        max_memory_bytes,
+        fuel_limit,
+        timeout_ms,
+    };
+
+    assert!(
+        request(Some(16 * 1024 * 1024), Some(1_000_000), Some(2_000))
+            .validate()
+            .is_ok()
+    );
+    assert!(request(Some(1024), None, None).validate().is_err());
+    assert!(request(None, Some(0), None).validate().is_err());
+    assert!(request(None, None, Some(0)).validate().is_err());
+}
+
+#[test]
+fn test_capabilities_allows_host() {
+    let capabilities = Capabilities {
+        allowed_hosts: vec!["api.example.com".to_string(), "*.internal.net".to_string()],
+        ..Default::default()
+    };
+
+    assert!(capabilities.allows_host("api.example.com"));
+    assert!(capabilities.allows_host("API.Example.com"));
+    assert!(capabilities.allows_host("db.internal.net"));
+
+    assert!(!capabilities.allows_host("example.com"));
+    assert!(!capabilities.allows_host("internal.net"));
+    assert!(!capabilities.allows_host("evilinternal.net"));
+}
+
+#[test]
+fn test_capabilities_ungranted() {
+    let dir = |read_only| PreopenedDir {
+        host_path: "/srv/data".to_string(),
+        guest_path: "/data".to_string(),
+        read_only,
+    };
+    let granted = Capabilities {
+        preopened_dirs: vec![dir(true)],
+        allowed_hosts: vec!["api.example.com".to_string()],
+        environment: vec!["LANG".to_string()],
+    };
+
+    let within = Capabilities {
+        preopened_dirs: vec![dir(true)],
+        environment: vec!["LANG".to_string()],
+        ..Default::default()
+    };
+    assert!(within.ungranted(&granted).is_empty());
+
+    let beyond = Capabilities {
+        preopened_dirs: vec![dir(false)],
+        allowed_hosts: vec!["other.example.com".to_string()],
+        environment: vec!["HOME".to_string()],
+    };
+    assert_eq!(
+        beyond.ungranted(&granted),
+        vec![
+            "directory '/srv/data' at '/data' (read-write)".to_string(),
+            "host 'other.example.com'".to_string(),
+            "environment variable 'HOME'".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_interceptor_binding_applies_to() {
+    let definition = Definition {
+        id: "acme::search".to_string(),
+        type_: "http".to_string(),
+        is_enabled: true,
+        name: "Search".to_string(),
+        description: "Search the web".to_string(),
+        definition_object_key: "acme::search".to_string(),
+        configuration_object_key: "acme::search".to_string(),
+        secrets_object_key: "acme::search".to_string(),
+        digest: "sha256:abc123".to_string(),
+        source_url: None,
+        is_pinned: true,
+    };
+    let binding = |ids: &[&str], types: &[&str]| InterceptorBinding {
+        module_id: "acme::pii".to_string(),
+        priority: 0,
+        definition_ids: ids.iter().map(|id| id.to_string()).collect(),
+        definition_types: types.iter().map(|type_| type_.to_string()).collect(),
+    };
+
+    assert!(binding(&[], &[]).applies_to(&definition));
+    assert!(binding(&["acme::search"], &[]).applies_to(&definition));
+    assert!(binding(&["acme::other"], &["http"]).applies_to(&definition));
+    assert!(!binding(&["acme::other"], &[]).applies_to(&definition));
+    assert!(!binding(&[], &["python"]).applies_to(&definition));
+}
+
+#[test]
+fn test_hook_subscription_request_validation() {
+    let request = |events: &[EventType], approvals: &[EventType]| HookSubscriptionRequest {
+        events: events.to_vec(),
+        approvals: approvals.to_vec(),
+    };
+
+    assert!(request(&[], &[]).validate().is_err());
+    assert!(request(&[], &[EventType::ModuleEnabled]).validate().is_ok());
+    assert!(request(&[EventType::ModuleDeleted], &[]).validate().is_ok());
+    assert!(request(&[], &[EventType::ModuleDeleted])
+        .validate()
+        .is_err());
+
+    let subscription = request(
+        &[EventType::ModuleDeleted],
+        &[
+            EventType::ModuleEnabled,
+            EventType::DefinitionCreated,
+            EventType::ModuleEnabled,
+        ],
+    )
+    .into_subscription("gate".to_string());
+    assert_eq!(subscription.events, vec!["module_deleted"]);
+    assert_eq!(
+        subscription.approvals,
+        vec!["definition_created", "module_enabled"]
+    );
+}
@@ -0,0 +1,611 @@
+use crate::{
+    errors::AppError,
+    models::{Capabilities, Module, ModuleType},
+    s3,
+    utils::{digest_utils, wasm_utils},
+};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use tokio::sync::Semaphore;
+use wasmtime::{Engine, Instance, Linker, Store, StoreLimits, StoreLimitsBuilder, Trap};
+use wasmtime_wasi::{
+    p1::WasiP1Ctx,
+    p2::pipe::{MemoryInputPipe, MemoryOutputPipe},
+    DirPerms, FilePerms, I32Exit, WasiCtxBuilder,
+};
+
+pub mod host;
+
+const MODULES_BUCKET: &str = "modules";
+const MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+/// How long past its timeout an invocation may stay blocked in a host call,
+/// which the epoch deadline cannot interrupt, before it is abandoned.
+const TIMEOUT_GRACE: Duration = Duration::from_millis(250);
+const COMPILE_CACHE_CAPACITY: usize = 64;
+
+/// Per-instance state available to host functions.
+pub struct HostState {
+    pub module_id: String,
+    pub capabilities: Capabilities,
+    pub http_client: reqwest::Client,
+    pub timeout: Duration,
+    pub max_response_bytes: usize,
+    wasi: WasiP1Ctx,
+    limits: StoreLimits,
+}
+
+/// Resources a single module invocation may consume before it is stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecutionLimits {
+    pub max_memory_bytes: u64,
+    pub fuel: u64,
+    pub timeout: Duration,
+}
+
+impl Default for ExecutionLimits {
+    fn default() -> Self {
+        Self {
+            max_memory_bytes: 64 * 1024 * 1024,
+            fuel: 1_000_000_000,
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl From<&Module> for ExecutionLimits {
+    fn from(module: &Module) -> Self {
+        Self {
+            max_memory_bytes: module.max_memory_bytes.max(0) as u64,
+            fuel: module.fuel_limit.max(0) as u64,
+            timeout: Duration::from_millis(module.timeout_ms.max(0) as u64),
+        }
+    }
+}
+
+/// A compiled module ready to be instantiated.
+#[derive(Clone)]
+pub struct LoadedModule {
+    pub id: String,
+    pub module_type: ModuleType,
+    pub limits: ExecutionLimits,
+    pub capabilities: Capabilities,
+    module: wasmtime::Module,
+}
+
+impl LoadedModule {
+    pub fn new(id: impl Into<String>, module_type: ModuleType, module: wasmtime::Module) -> Self {
+        Self {
+            id: id.into(),
+            module_type,
+            limits: ExecutionLimits::default(),
+            capabilities: Capabilities::default(),
+            module,
+        }
+    }
+
+    pub fn with_limits(mut self, limits: ExecutionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CommandInput {
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub stdin: String,
+}
+
+/// What a sandbox run produced. Output written before a trap, fuel
+/// exhaustion or timeout stopped the module is kept alongside the error.
+#[derive(Debug)]
+pub struct CommandOutput {
+    /// The module's exit code, or why it was stopped before exiting.
+    pub exit_code: Result<i32, AppError>,
+    pub stdout: String,
+    pub stderr: String,
+    /// How long the module ran, not counting the wait for a free slot.
+    pub duration: Duration,
+}
+
+/// Compiled modules keyed by digest. Once full, the least recently used
+/// module is dropped, so upgraded and deleted modules do not stay compiled.
+struct CompileCache {
+    capacity: usize,
+    clock: u64,
+    entries: HashMap<String, (wasmtime::Module, u64)>,
+}
+
+impl CompileCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            clock: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, digest: &str) -> Option<wasmtime::Module> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.entries.get_mut(digest).map(|(module, used)| {
+            *used = clock;
+            module.clone()
+        })
+    }
+
+    fn insert(&mut self, digest: String, module: wasmtime::Module) {
+        if !self.entries.contains_key(&digest) && self.entries.len() >= self.capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(digest, _)| digest.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        self.clock += 1;
+        self.entries.insert(digest, (module, self.clock));
+    }
+}
+
+/// Embedded WebAssembly runtime for installed modules.
+///
+/// Sandbox modules are WASI commands: `_start` runs with the given arguments
+/// and stdin, and stdout/stderr are captured. Every other module type
+/// exchanges JSON documents through its linear memory: the host calls
+/// `mci_alloc(len)` to reserve an input buffer, writes the input there and
+/// calls the type's entrypoint with `(ptr, len)`. The entrypoint returns its
+/// output buffer packed as `ptr << 32 | len`.
+///
+/// All modules may import WASI preview 1 and the host functions in
+/// [`host`], restricted to the module's [`Capabilities`]: only granted
+/// directories are preopened, only granted environment variables are visible
+/// and only granted hosts are reachable. Redirects are never followed on a
+/// module's behalf, so every hop it makes is checked against its grant. The
+/// most recently used compiled modules are cached by digest.
+///
+/// Each invocation runs on the blocking thread pool under the module's
+/// [`ExecutionLimits`], and at most one invocation per CPU runs at a time so
+/// guest code cannot starve the async workers serving HTTP requests. An
+/// invocation still blocked in a host call, such as a WASI sleep, shortly after
+/// its timeout is abandoned: its slot is freed and the timeout reported, while
+/// the thread finishes on its own and traps once it is back in guest code.
+#[derive(Clone)]
+pub struct Runtime {
+    engine: Engine,
+    linker: Arc<Linker<HostState>>,
+    cache: Arc<Mutex<CompileCache>>,
+    permits: Arc<Semaphore>,
+    http_client: reqwest::Client,
+}
+
+impl Runtime {
+    pub fn new() -> anyhow::Result<Self> {
+        let mut config = wasmtime::Config::new();
+        config.consume_fuel(true).epoch_interruption(true);
+        let engine = Engine::new(&config)?;
+        spawn_epoch_ticker(&engine);
+
+        let mut linker = Linker::new(&engine);
+        wasmtime_wasi::p1::add_to_linker_sync(&mut linker, |state: &mut HostState| {
+            &mut state.wasi
+        })?;
+        host::add_to_linker(&mut linker)?;
+
+        let http_client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?;
+
+        Ok(Self {
+            engine,
+            linker: Arc::new(linker),
+            cache: Arc::new(Mutex::new(CompileCache::new(COMPILE_CACHE_CAPACITY))),
+            permits: Arc::new(Semaphore::new(
+                thread::available_parallelism().map_or(1, |n| n.get()),
+            )),
+            http_client,
+        })
+    }
+
+    fn cached(&self, digest: &str) -> Option<wasmtime::Module> {
+        self.cache.lock().unwrap().get(digest)
+    }
+
+    /// Verifies `bytes` against `digest` and compiles them, reusing an earlier
+    /// compilation of the same digest.
+    pub fn compile(&self, digest: &str, bytes: &[u8]) -> Result<wasmtime::Module, AppError> {
+        if let Some(module) = self.cached(digest) {
+            return Ok(module);
+        }
+
+        digest_utils::verify_digest(digest, bytes)
+            .context("Stored module failed digest verification")?;
+        let module =
+            wasmtime::Module::new(&self.engine, bytes).context("Failed to compile module")?;
+
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(digest.to_string(), module.clone());
+
+        Ok(module)
+    }
+
+    /// Loads an enabled module from the modules bucket and compiles it.
+    pub async fn load(
+        &self,
+        s3_client: &aws_sdk_s3::Client,
+        module: &Module,
+    ) -> Result<LoadedModule, AppError> {
+        if !module.is_enabled {
+            return Err(AppError::conflict(format!(
+                "Module '{}' is disabled",
+                module.id
+            )));
+        }
+
+        let compiled = match self.cached(&module.digest) {
+            Some(compiled) => compiled,
+            None => {
+                let bytes = s3::get_bytes(s3_client, MODULES_BUCKET, &module.module_object_key)
+                    .await?
+                    .ok_or_else(|| {
+                        AppError::not_found(format!("Module file for '{}' is missing", module.id))
+                    })?;
+
+                let runtime = self.clone();
+                let digest = module.digest.clone();
+                tokio::task::spawn_blocking(move || runtime.compile(&digest, &bytes)).await??
+            }
+        };
+
+        Ok(LoadedModule::new(&module.id, module.type_, compiled)
+            .with_limits(ExecutionLimits::from(module))
+            .with_capabilities(module.capabilities.clone()))
+    }
+
+    pub async fn language_eval(
+        &self,
+        loaded: &LoadedModule,
+        request: &Value,
+    ) -> Result<Value, AppError> {
+        self.call(
+            loaded,
+            ModuleType::Language,
+            wasm_utils::LANGUAGE_EVAL_EXPORT,
+            request,
+        )
+        .await
+    }
+
+    pub async fn intercept_request(
+        &self,
+        loaded: &LoadedModule,
+        request: &Value,
+    ) -> Result<Value, AppError> {
+        self.call(
+            loaded,
+            ModuleType::Interceptor,
+            wasm_utils::INTERCEPT_REQUEST_EXPORT,
+            request,
+        )
+        .await
+    }
+
+    pub async fn intercept_response(
+        &self,
+        loaded: &LoadedModule,
+        response: &Value,
+    ) -> Result<Value, AppError> {
+        self.call(
+            loaded,
+            ModuleType::Interceptor,
+            wasm_utils::INTERCEPT_RESPONSE_EXPORT,
+            response,
+        )
+        .await
+    }
+
+    pub async fn proxy_forward(
+        &self,
+        loaded: &LoadedModule,
+        request: &Value,
+    ) -> Result<Value, AppError> {
+        self.call(
+            loaded,
+            ModuleType::Proxy,
+            wasm_utils::PROXY_FORWARD_EXPORT,
+            request,
+        )
+        .await
+    }
+
+    pub async fn hook_handle(
+        &self,
+        loaded: &LoadedModule,
+        event: &Value,
+    ) -> Result<Value, AppError> {
+        self.call(
+            loaded,
+            ModuleType::Hook,
+            wasm_utils::HOOK_HANDLE_EXPORT,
+            event,
+        )
+        .await
+    }
+
+    /// Runs a sandbox command once a runtime slot is free. Fails only when the
+    /// module cannot be run at all; how the run itself ended is reported in
+    /// the returned [`CommandOutput`].
+    pub async fn run_command(
+        &self,
+        loaded: &LoadedModule,
+        input: CommandInput,
+    ) -> Result<CommandOutput, AppError> {
+        ensure_type(loaded, ModuleType::Sandbox)?;
+
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .context("Runtime is shut down")?;
+        let stdout = MemoryOutputPipe::new(MAX_OUTPUT_BYTES);
+        let stderr = MemoryOutputPipe::new(MAX_OUTPUT_BYTES);
+        let started = Instant::now();
+        let task = {
+            let runtime = self.clone();
+            let module = loaded.clone();
+            let (stdout, stderr) = (stdout.clone(), stderr.clone());
+            tokio::task::spawn_blocking(move || {
+                runtime.start_command(&module, input, &stdout, &stderr)
+            })
+        };
+
+        let exit_code =
+            match tokio::time::timeout(loaded.limits.timeout + TIMEOUT_GRACE, task).await {
+                Ok(result) => result?.map_err(|err| execution_error(loaded, err)),
+                Err(_) => Err(timeout_error(loaded)),
+            };
+
+        Ok(CommandOutput {
+            exit_code,
+            stdout: String::from_utf8_lossy(&stdout.contents()).into_owned(),
+            stderr: String::from_utf8_lossy(&stderr.contents()).into_owned(),
+            duration: started.elapsed(),
+        })
+    }
+
+    async fn call(
+        &self,
+        loaded: &LoadedModule,
+        module_type: ModuleType,
+        export: &'static str,
+        input: &Value,
+    ) -> Result<Value, AppError> {
+        ensure_type(loaded, module_type)?;
+
+        let input = serde_json::to_vec(input).context("Failed to serialize module input")?;
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .context("Runtime is shut down")?;
+        let task = {
+            let runtime = self.clone();
+            let module = loaded.clone();
+            tokio::task::spawn_blocking(move || runtime.call_blocking(&module, export, &input))
+        };
+        let output = tokio::time::timeout(loaded.limits.timeout + TIMEOUT_GRACE, task)
+            .await
+            .map_err(|_| timeout_error(loaded))??
+            .map_err(|err| execution_error(loaded, err))?;
+
+        serde_json::from_slice(&output).map_err(|err| {
+            AppError::module_execution(format!(
+                "Module '{}' returned invalid JSON: {}",
+                loaded.id, err
+            ))
+        })
+    }
+
+    fn new_store(
+        &self,
+        loaded: &LoadedModule,
+        wasi: WasiP1Ctx,
+    ) -> anyhow::Result<Store<HostState>> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(usize::try_from(loaded.limits.max_memory_bytes).unwrap_or(usize::MAX))
+            .trap_on_grow_failure(true)
+            .build();
+
+        let mut store = Store::new(
+            &self.engine,
+            HostState {
+                module_id: loaded.id.clone(),
+                capabilities: loaded.capabilities.clone(),
+                http_client: self.http_client.clone(),
+                timeout: loaded.limits.timeout,
+                max_response_bytes: loaded.limits.max_memory_bytes as usize,
+                wasi,
+                limits,
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(loaded.limits.fuel)?;
+        store.set_epoch_deadline(loaded.limits.timeout.div_duration_f64(EPOCH_TICK).ceil() as u64);
+
+        Ok(store)
+    }
+
+    fn instantiate(
+        &self,
+        store: &mut Store<HostState>,
+        loaded: &LoadedModule,
+    ) -> anyhow::Result<Instance> {
+        let instance = self.linker.instantiate(&mut *store, &loaded.module)?;
+
+        // Reactor modules built for WASI expect `_initialize` before any export.
+        if let Ok(initialize) = instance.get_typed_func::<(), ()>(&mut *store, "_initialize") {
+            initialize.call(&mut *store, ())?;
+        }
+
+        Ok(instance)
+    }
+
+    fn call_blocking(
+        &self,
+        loaded: &LoadedModule,
+        export: &str,
+        input: &[u8],
+    ) -> anyhow::Result<Vec<u8>> {
+        let mut store = self.new_store(loaded, wasi_builder(loaded)?.build_p1())?;
+        let instance = self.instantiate(&mut store, loaded)?;
+
+        let memory = instance
+            .get_memory(&mut store, wasm_utils::MEMORY_EXPORT)
+            .context("Module does not export its memory")?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, wasm_utils::ALLOC_EXPORT)?;
+        let entrypoint = instance.get_typed_func::<(i32, i32), i64>(&mut store, export)?;
+
+        let len = i32::try_from(input.len()).context("Module input is too large")?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory
+            .write(&mut store, ptr as u32 as usize, input)
+            .context("Module allocated an input buffer outside its memory")?;
+
+        let packed = entrypoint.call(&mut store, (ptr, len))? as u64;
+        let out_ptr = (packed >> 32) as usize;
+        let out_len = (packed & 0xffff_ffff) as usize;
+
+        if out_len > MAX_OUTPUT_BYTES {
+            anyhow::bail!("Module output exceeds {} bytes", MAX_OUTPUT_BYTES);
+        }
+
+        let output = memory
+            .data(&store)
+            .get(out_ptr..out_ptr + out_len)
+            .context("Module returned an output buffer outside its memory")?;
+
+        Ok(output.to_vec())
+    }
+
+    /// Runs the command's `_start` with its output going to `stdout` and
+    /// `stderr`, and returns its exit code.
+    fn start_command(
+        &self,
+        loaded: &LoadedModule,
+        input: CommandInput,
+        stdout: &MemoryOutputPipe,
+        stderr: &MemoryOutputPipe,
+    ) -> anyhow::Result<i32> {
+        let wasi = wasi_builder(loaded)?
+            .arg(&loaded.id)
+            .args(&input.args)
+            .stdin(MemoryInputPipe::new(input.stdin.into_bytes()))
+            .stdout(stdout.clone())
+            .stderr(stderr.clone())
+            .build_p1();
+
+        let mut store = self.new_store(loaded, wasi)?;
+        let instance = self.linker.instantiate(&mut store, &loaded.module)?;
+        let start = instance.get_typed_func::<(), ()>(&mut store, wasm_utils::START_EXPORT)?;
+
+        match start.call(&mut store, ()) {
+            Ok(()) => Ok(0),
+            Err(err) => match err.downcast_ref::<I32Exit>() {
+                Some(exit) => Ok(exit.0),
+                None => Err(err),
+            },
+        }
+    }
+}
+
+/// Builds a WASI context exposing only what the module's capabilities grant.
+fn wasi_builder(loaded: &LoadedModule) -> anyhow::Result<WasiCtxBuilder> {
+    let mut builder = WasiCtxBuilder::new();
+
+    for dir in &loaded.capabilities.preopened_dirs {
+        let (dir_perms, file_perms) = if dir.read_only {
+            (DirPerms::READ, FilePerms::READ)
+        } else {
+            (DirPerms::all(), FilePerms::all())
+        };
+        builder
+            .preopened_dir(&dir.host_path, &dir.guest_path, dir_perms, file_perms)
+            .with_context(|| format!("Failed to preopen directory '{}'", dir.host_path))?;
+    }
+
+    for name in &loaded.capabilities.environment {
+        if let Ok(value) = std::env::var(name) {
+            builder.env(name, value);
+        }
+    }
+
+    Ok(builder)
+}
+
+fn ensure_type(loaded: &LoadedModule, expected: ModuleType) -> Result<(), AppError> {
+    if loaded.module_type != expected {
+        return Err(AppError::bad_request(format!(
+            "Module '{}' is a {} module, not a {} module",
+            loaded.id,
+            loaded.module_type.as_str(),
+            expected.as_str()
+        )));
+    }
+    Ok(())
+}
+
+fn execution_error(loaded: &LoadedModule, err: anyhow::Error) -> AppError {
+    match err.downcast_ref::<Trap>() {
+        Some(Trap::OutOfFuel) => AppError::module_execution(format!(
+            "Module '{}' exceeded its fuel limit of {}",
+            loaded.id, loaded.limits.fuel
+        )),
+        Some(Trap::Interrupt) => timeout_error(loaded),
+        _ => AppError::module_execution(format!("Module '{}' failed: {:#}", loaded.id, err)),
+    }
+}
+
+fn timeout_error(loaded: &LoadedModule) -> AppError {
+    AppError::module_execution(format!(
+        "Module '{}' exceeded its timeout of {} ms",
+        loaded.id,
+        loaded.limits.timeout.as_millis()
+    ))
+}
+
+/// Advances the engine epoch every [`EPOCH_TICK`] so store deadlines act as a
+/// wall-clock timeout. The thread exits once the engine is dropped.
+fn spawn_epoch_ticker(engine: &Engine) {
+    let engine = engine.weak();
+
+    thread::spawn(move || {
+        while let Some(engine) = engine.upgrade() {
+            engine.increment_epoch();
+            drop(engine);
+            thread::sleep(EPOCH_TICK);
+        }
+    });
+}
+
+#[cfg(test)]
+#[path = "runtime_tests.rs"]
+mod tests;
@@ -0,0 +1,175 @@
+use super::HostState;
+use crate::utils::wasm_utils::{ALLOC_EXPORT, HOST_MODULE, MEMORY_EXPORT};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use tracing::{debug, error, info, trace, warn};
+use wasmtime::{Caller, Extern, Linker, Memory};
+
+/// Registers the `mci` host functions on `linker`.
+///
+/// - `log(level: i32, ptr: i32, len: i32)`: writes a UTF-8 message to the MCI
+///   log. Levels are 0 = trace, 1 = debug, 2 = info, 3 = warn, 4 = error.
+/// - `http_request(ptr: i32, len: i32) -> i64`: performs the JSON-encoded
+///   [`HttpRequest`] and returns the JSON-encoded [`HttpResponse`] (or an
+///   `{"error": ...}` object) in a buffer obtained from `mci_alloc`, packed
+///   like entrypoint results. Only hosts in the module's `allowed_hosts` can
+///   be reached; anything else traps. Redirects are returned to the module
+///   rather than followed, and response bodies larger than the module's
+///   memory limit fail the call.
+pub fn add_to_linker(linker: &mut Linker<HostState>) -> Result<()> {
+    linker.func_wrap(HOST_MODULE, "log", log)?;
+    linker.func_wrap(HOST_MODULE, "http_request", http_request)?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HttpRequest {
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    pub body: String,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+fn log(mut caller: Caller<'_, HostState>, level: i32, ptr: i32, len: i32) -> Result<()> {
+    let message = String::from_utf8_lossy(&read_bytes(&mut caller, ptr, len)?).into_owned();
+    let module_id = caller.data().module_id.as_str();
+
+    match level {
+        0 => trace!(module = module_id, "{}", message),
+        1 => debug!(module = module_id, "{}", message),
+        2 => info!(module = module_id, "{}", message),
+        3 => warn!(module = module_id, "{}", message),
+        _ => error!(module = module_id, "{}", message),
+    }
+
+    Ok(())
+}
+
+fn http_request(mut caller: Caller<'_, HostState>, ptr: i32, len: i32) -> Result<i64> {
+    let request: HttpRequest = serde_json::from_slice(&read_bytes(&mut caller, ptr, len)?)
+        .context("Invalid http_request payload")?;
+    let url = url::Url::parse(&request.url).context("Invalid http_request URL")?;
+    let host = url.host_str().unwrap_or_default();
+
+    if !caller.data().capabilities.allows_host(host) {
+        anyhow::bail!("Module is not allowed to reach host '{}'", host);
+    }
+
+    let method = reqwest::Method::from_bytes(request.method.as_bytes())
+        .context("Invalid http_request method")?;
+    let mut builder = caller
+        .data()
+        .http_client
+        .request(method, url)
+        .timeout(caller.data().timeout);
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+    if let Some(body) = request.body {
+        builder = builder.body(body);
+    }
+
+    // Host calls run on the runtime's blocking threads, so waiting here does
+    // not tie up an async worker.
+    let max_response_bytes = caller.data().max_response_bytes;
+    let result = tokio::runtime::Handle::current().block_on(async move {
+        let mut response = builder.send().await?;
+        if response
+            .content_length()
+            .is_some_and(|length| length > max_response_bytes as u64)
+        {
+            anyhow::bail!(
+                "Response body exceeds the module's limit of {} bytes",
+                max_response_bytes
+            );
+        }
+
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(|value| (name.to_string(), value.to_string()))
+            })
+            .collect();
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if body.len() + chunk.len() > max_response_bytes {
+                anyhow::bail!(
+                    "Response body exceeds the module's limit of {} bytes",
+                    max_response_bytes
+                );
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(HttpResponse {
+            status,
+            headers,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    });
+
+    let output = match result {
+        Ok(response) => serde_json::to_vec(&response)?,
+        Err(err) => serde_json::to_vec(&json!({ "error": err.to_string() }))?,
+    };
+
+    write_bytes(&mut caller, &output)
+}
+
+fn memory(caller: &mut Caller<'_, HostState>) -> Result<Memory> {
+    match caller.get_export(MEMORY_EXPORT) {
+        Some(Extern::Memory(memory)) => Ok(memory),
+        _ => anyhow::bail!("Module does not export its memory"),
+    }
+}
+
+fn read_bytes(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> Result<Vec<u8>> {
+    let memory = memory(caller)?;
+
+    memory
+        .data(&caller)
+        .get(ptr as u32 as usize..)
+        .and_then(|data| data.get(..len as u32 as usize))
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| anyhow::anyhow!("Host call read out of bounds"))
+}
+
+/// Copies `bytes` into a guest buffer from `mci_alloc` and returns it packed
+/// as `ptr << 32 | len`.
+fn write_bytes(caller: &mut Caller<'_, HostState>, bytes: &[u8]) -> Result<i64> {
+    let alloc = caller
+        .get_export(ALLOC_EXPORT)
+        .and_then(Extern::into_func)
+        .context("Module does not export mci_alloc")?
+        .typed::<i32, i32>(&*caller)?;
+
+    let len = i32::try_from(bytes.len()).context("Host call result is too large")?;
+    let ptr = alloc.call(&mut *caller, len)?;
+    memory(caller)?
+        .write(&mut *caller, ptr as u32 as usize, bytes)
+        .context("Module allocated a buffer outside its memory")?;
+
+    Ok(((ptr as u32 as i64) << 32) | len as i64)
+}
@@ -0,0 +1,373 @@
+use super::*;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+const ECHO_LANGUAGE: &str = r#"(module
+    (import "mci" "log" (func $log (param i32 i32 i32)))
+    (memory (export "memory") 1)
+    (func (export "mci_alloc") (param i32) (result i32) i32.const 1024)
+    (func (export "mci_language_eval") (param $ptr i32) (param $len i32) (result i64)
+        (call $log (i32.const 1) (local.get $ptr) (local.get $len))
+        (i64.or
+            (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+            (i64.extend_i32_u (local.get $len)))))"#;
+
+const TRAPPING_HOOK: &str = r#"(module
+    (memory (export "memory") 1)
+    (func (export "mci_alloc") (param i32) (result i32) i32.const 0)
+    (func (export "mci_hook_handle") (param i32 i32) (result i64) unreachable))"#;
+
+const HELLO_SANDBOX: &str = r#"(module
+    (import "wasi_snapshot_preview1" "fd_write"
+        (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+    (memory (export "memory") 1)
+    (data (i32.const 8) "hello")
+    (func (export "_start")
+        (i32.store (i32.const 0) (i32.const 8))
+        (i32.store (i32.const 4) (i32.const 5))
+        (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 16)))
+        (call $proc_exit (i32.const 3))))"#;
+
+fn load(runtime: &Runtime, module_type: ModuleType, wat: &str) -> LoadedModule {
+    let bytes = wat::parse_str(wat).unwrap();
+    let digest = format!("sha256:{:x}", Sha256::digest(&bytes));
+    let module = runtime.compile(&digest, &bytes).unwrap();
+
+    LoadedModule::new("test-module", module_type, module)
+}
+
+#[tokio::test]
+async fn test_language_eval_round_trips_json() {
+    let runtime = Runtime::new().unwrap();
+    let loaded = load(&runtime, ModuleType::Language, ECHO_LANGUAGE);
+
+    let request = json!({ "source": "1 + 1", "arguments": [1, 2] });
+    let response = runtime.language_eval(&loaded, &request).await.unwrap();
+
+    assert_eq!(response, request);
+}
+
+#[tokio::test]
+async fn test_call_rejects_other_module_type() {
+    let runtime = Runtime::new().unwrap();
+    let loaded = load(&runtime, ModuleType::Language, ECHO_LANGUAGE);
+
+    let result = runtime.proxy_forward(&loaded, &json!({})).await;
+    assert!(matches!(result, Err(AppError::BadRequest(_))));
+}
+
+#[tokio::test]
+async fn test_trap_is_reported_as_module_error() {
+    let runtime = Runtime::new().unwrap();
+    let loaded = load(&runtime, ModuleType::Hook, TRAPPING_HOOK);
+
+    let result = runtime.hook_handle(&loaded, &json!({})).await;
+    assert!(matches!(result, Err(AppError::ModuleExecution(_))));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_run_command_captures_output_and_exit_code() {
+    let runtime = Runtime::new().unwrap();
+    let loaded = load(&runtime, ModuleType::Sandbox, HELLO_SANDBOX);
+
+    let output = runtime
+        .run_command(&loaded, CommandInput::default())
+        .await
+        .unwrap();
+
+    assert_eq!(output.exit_code.unwrap(), 3);
+    assert_eq!(output.stdout, "hello");
+    assert_eq!(output.stderr, "");
+}
+
+const HELLO_THEN_TRAP_SANDBOX: &str = r#"(module
+    (import "wasi_snapshot_preview1" "fd_write"
+        (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (memory (export "memory") 1)
+    (data (i32.const 8) "hello")
+    (func (export "_start")
+        (i32.store (i32.const 0) (i32.const 8))
+        (i32.store (i32.const 4) (i32.const 5))
+        (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 16)))
+        unreachable))"#;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_run_command_keeps_output_of_failed_runs() {
+    let runtime = Runtime::new().unwrap();
+    let loaded = load(&runtime, ModuleType::Sandbox, HELLO_THEN_TRAP_SANDBOX);
+
+    let output = runtime
+        .run_command(&loaded, CommandInput::default())
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        output.exit_code,
+        Err(AppError::ModuleExecution(_))
+    ));
+    assert_eq!(output.stdout, "hello");
+}
+
+#[test]
+fn test_compile_verifies_digest() {
+    let runtime = Runtime::new().unwrap();
+    let bytes = wat::parse_str(ECHO_LANGUAGE).unwrap();
+    let wrong_digest = format!("sha256:{:x}", Sha256::digest(b"other"));
+
+    assert!(runtime.compile(&wrong_digest, &bytes).is_err());
+}
+
+#[test]
+fn test_compile_cache_drops_least_recently_used() {
+    let runtime = Runtime::new().unwrap();
+    let module = wasmtime::Module::new(&runtime.engine, "(module)").unwrap();
+    let mut cache = CompileCache::new(2);
+
+    cache.insert("a".to_string(), module.clone());
+    cache.insert("b".to_string(), module.clone());
+    assert!(cache.get("a").is_some());
+    cache.insert("c".to_string(), module);
+
+    assert_eq!(cache.entries.len(), 2);
+    assert!(cache.get("a").is_some());
+    assert!(cache.get("b").is_none());
+    assert!(cache.get("c").is_some());
+}
+
+const SPINNING_HOOK: &str = r#"(module
+    (memory (export "memory") 1)
+    (func (export "mci_alloc") (param i32) (result i32) i32.const 0)
+    (func (export "mci_hook_handle") (param i32 i32) (result i64)
+        (loop $spin (br $spin))
+        i64.const 0))"#;
+
+const GROWING_HOOK: &str = r#"(module
+    (memory (export "memory") 1)
+    (data (i32.const 0) "{}")
+    (func (export "mci_alloc") (param i32) (result i32) i32.const 1024)
+    (func (export "mci_hook_handle") (param i32 i32) (result i64)
+        (drop (memory.grow (i32.const 16)))
+        i64.const 2))"#;
+
+fn module_error_message(result: Result<Value, AppError>) -> String {
+    match result {
+        Err(AppError::ModuleExecution(message)) => message,
+        other => panic!("Expected ModuleExecution error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_fuel_limit_stops_runaway_module() {
+    let runtime = Runtime::new().unwrap();
+    let loaded = load(&runtime, ModuleType::Hook, SPINNING_HOOK).with_limits(ExecutionLimits {
+        fuel: 10_000,
+        ..Default::default()
+    });
+
+    let message = module_error_message(runtime.hook_handle(&loaded, &json!({})).await);
+    assert!(message.contains("fuel limit"), "{}", message);
+}
+
+#[tokio::test]
+async fn test_timeout_stops_runaway_module() {
+    let runtime = Runtime::new().unwrap();
+    let loaded = load(&runtime, ModuleType::Hook, SPINNING_HOOK).with_limits(ExecutionLimits {
+        fuel: u64::MAX,
+        timeout: Duration::from_millis(50),
+        ..Default::default()
+    });
+
+    let message = module_error_message(runtime.hook_handle(&loaded, &json!({})).await);
+    assert!(message.contains("timeout of 50 ms"), "{}", message);
+}
+
+const SLEEPING_SANDBOX: &str = r#"(module
+    (import "wasi_snapshot_preview1" "poll_oneoff"
+        (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+    (memory (export "memory") 1)
+    (func (export "_start")
+        (i32.store (i32.const 16) (i32.const 1))
+        (i64.store (i32.const 24) (i64.const 60000000000))
+        (drop (call $poll_oneoff (i32.const 0) (i32.const 64) (i32.const 1) (i32.const 128)))))"#;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_timeout_abandons_module_blocked_in_host_call() {
+    let runtime = Runtime::new().unwrap();
+    let loaded =
+        load(&runtime, ModuleType::Sandbox, SLEEPING_SANDBOX).with_limits(ExecutionLimits {
+            timeout: Duration::from_millis(50),
+            ..Default::default()
+        });
+    let permits = runtime.permits.available_permits();
+
+    let output = runtime
+        .run_command(&loaded, CommandInput::default())
+        .await
+        .unwrap();
+
+    let message = module_error_message(output.exit_code.map(Value::from));
+    assert!(message.contains("timeout of 50 ms"), "{}", message);
+    assert!(output.duration < Duration::from_secs(5));
+    assert_eq!(runtime.permits.available_permits(), permits);
+}
+
+#[tokio::test]
+async fn test_memory_limit_stops_growth() {
+    let runtime = Runtime::new().unwrap();
+    let loaded = load(&runtime, ModuleType::Hook, GROWING_HOOK);
+
+    assert!(runtime.hook_handle(&loaded, &json!({})).await.is_ok());
+
+    let limited = loaded.with_limits(ExecutionLimits {
+        max_memory_bytes: 4 * 65536,
+        ..Default::default()
+    });
+    assert!(runtime.hook_handle(&limited, &json!({})).await.is_err());
+}
+
+const FETCHING_HOOK: &str = r#"(module
+    (import "mci" "http_request" (func $http_request (param i32 i32) (result i64)))
+    (memory (export "memory") 1)
+    (global $next (mut i32) (i32.const 1024))
+    (func (export "mci_alloc") (param $len i32) (result i32)
+        (local $ptr i32)
+        (local.set $ptr (global.get $next))
+        (global.set $next (i32.add (global.get $next) (local.get $len)))
+        (local.get $ptr))
+    (func (export "mci_hook_handle") (param $ptr i32) (param $len i32) (result i64)
+        (call $http_request (local.get $ptr) (local.get $len))))"#;
+
+fn fetching_hook(runtime: &Runtime, allowed_hosts: &[&str]) -> LoadedModule {
+    load(runtime, ModuleType::Hook, FETCHING_HOOK).with_capabilities(Capabilities {
+        allowed_hosts: allowed_hosts.iter().map(|host| host.to_string()).collect(),
+        ..Default::default()
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_http_request_reaches_allowed_host() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/status"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+        .mount(&server)
+        .await;
+
+    let runtime = Runtime::new().unwrap();
+    let loaded = fetching_hook(&runtime, &["127.0.0.1"]);
+
+    let response = runtime
+        .hook_handle(
+            &loaded,
+            &json!({ "url": format!("{}/status", server.uri()) }),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response["status"], 200);
+    assert_eq!(response["body"], "ok");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_http_request_returns_redirects_to_the_module() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/moved"))
+        .respond_with(
+            ResponseTemplate::new(302).insert_header("location", "http://169.254.169.254/"),
+        )
+        .mount(&server)
+        .await;
+
+    let runtime = Runtime::new().unwrap();
+    let loaded = fetching_hook(&runtime, &["127.0.0.1"]);
+
+    let response = runtime
+        .hook_handle(
+            &loaded,
+            &json!({ "url": format!("{}/moved", server.uri()) }),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response["status"], 302);
+    assert_eq!(response["headers"]["location"], "http://169.254.169.254/");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_http_request_fails_on_oversized_body() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/large"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("x".repeat(128 * 1024)))
+        .mount(&server)
+        .await;
+
+    let runtime = Runtime::new().unwrap();
+    let loaded = fetching_hook(&runtime, &["127.0.0.1"]).with_limits(ExecutionLimits {
+        max_memory_bytes: 64 * 1024,
+        ..Default::default()
+    });
+
+    let response = runtime
+        .hook_handle(
+            &loaded,
+            &json!({ "url": format!("{}/large", server.uri()) }),
+        )
+        .await
+        .unwrap();
+
+    assert!(
+        response["error"]
+            .as_str()
+            .unwrap_or_default()
+            .contains("exceeds the module's limit"),
+        "{}",
+        response
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_http_request_denies_ungranted_host() {
+    let runtime = Runtime::new().unwrap();
+    let loaded = fetching_hook(&runtime, &["api.example.com"]);
+
+    let message = module_error_message(
+        runtime
+            .hook_handle(&loaded, &json!({ "url": "http://127.0.0.1:9/" }))
+            .await,
+    );
+    assert!(
+        message.contains("not allowed to reach host '127.0.0.1'"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn test_wasi_builder_preopens_granted_directories() {
+    let runtime = Runtime::new().unwrap();
+    let loaded = load(&runtime, ModuleType::Sandbox, HELLO_SANDBOX);
+
+    let missing_dir = loaded.clone().with_capabilities(Capabilities {
+        preopened_dirs: vec![crate::models::PreopenedDir {
+            host_path: "/definitely/not/a/real/dir".to_string(),
+            guest_path: "/data".to_string(),
+            read_only: true,
+        }],
+        ..Default::default()
+    });
+
+    assert!(wasi_builder(&loaded).is_ok());
+    assert!(wasi_builder(&missing_dir).is_err());
+}
@@ -1,11 +1,17 @@
+use crate::utils::digest_utils;
 use anyhow::{Context, Result};
 use aws_sdk_s3::{
     config::{Credentials, Region},
     primitives::ByteStream,
+    types::{CompletedMultipartUpload, CompletedPart},
     Client,
 };
-use bytes::Bytes;
-use sha2::{Digest, Sha256};
+use bytes::{Bytes, BytesMut};
+use futures::future::join_all;
+use serde::Serialize;
+use std::collections::HashMap;
+use tokio::task::JoinSet;
+use tracing::warn;
 
 pub async fn create_client(
     endpoint_url: &str,
@@ -29,52 +35,543 @@ pub async fn create_client(
     Client::from_conf(s3_config)
 }
 
+/// Inspects the bytes of an upload as they stream to S3. The object is only
+/// stored once every check has accepted it.
+pub trait UploadCheck: Send {
+    fn update(&mut self, chunk: &[u8]) -> Result<()>;
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+impl UploadCheck for digest_utils::DigestVerifier {
+    fn update(&mut self, chunk: &[u8]) -> Result<()> {
+        digest_utils::DigestVerifier::update(self, chunk);
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.verify()
+    }
+}
+
+impl UploadCheck for digest_utils::DigestRecorder {
+    fn update(&mut self, chunk: &[u8]) -> Result<()> {
+        digest_utils::DigestRecorder::update(self, chunk);
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        digest_utils::DigestRecorder::finish(*self);
+        Ok(())
+    }
+}
+
+/// How large objects are split up for multipart uploads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UploadOptions {
+    /// Size of each part. Bodies no larger than this are stored with a
+    /// single request.
+    pub part_size: usize,
+    /// Number of parts uploaded at the same time.
+    pub concurrency: usize,
+}
+
+/// Smallest part S3 accepts, apart from the last one of an upload.
+pub const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// Largest number of parts in a multipart upload.
+const MAX_PARTS: i32 = 10_000;
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        Self {
+            part_size: 16 * 1024 * 1024,
+            concurrency: 4,
+        }
+    }
+}
+
+impl UploadOptions {
+    pub fn new(part_size: usize, concurrency: usize) -> Result<Self> {
+        if part_size < MIN_PART_SIZE {
+            anyhow::bail!(
+                "Upload part size must be at least {} bytes, got {}",
+                MIN_PART_SIZE,
+                part_size
+            );
+        }
+        if concurrency == 0 {
+            anyhow::bail!("Upload concurrency must be at least 1");
+        }
+
+        Ok(Self {
+            part_size,
+            concurrency,
+        })
+    }
+}
+
+/// Stores a document that is already in memory with a single request. Upload
+/// options only bound how much of a stream is buffered, so they have nothing
+/// to add here; S3 accepts single requests far larger than any document.
+pub async fn put_object(
+    client: &Client,
+    bucket: &str,
+    key: &str,
+    body: Bytes,
+    metadata: HashMap<String, String>,
+) -> Result<()> {
+    client
+        .put_object()
+        .bucket(bucket)
+        .key(key)
+        .set_metadata(Some(metadata))
+        .body(ByteStream::from(body))
+        .send()
+        .await
+        .context("Failed to upload object to S3")?;
+
+    Ok(())
+}
+
+/// A multipart upload in progress. Parts are sent in the background, at most
+/// `concurrency` at a time, and the object only appears once it is completed.
+/// An upload dropped before it was completed or aborted, e.g. because the
+/// request carrying it was cancelled, is aborted in the background.
+struct MultipartUpload {
+    client: Client,
+    bucket: String,
+    key: String,
+    upload_id: String,
+    concurrency: usize,
+    next_part_number: i32,
+    in_flight: JoinSet<Result<CompletedPart>>,
+    completed: Vec<CompletedPart>,
+    finished: bool,
+}
+
+impl MultipartUpload {
+    async fn start(
+        client: &Client,
+        bucket: &str,
+        key: &str,
+        metadata: HashMap<String, String>,
+        options: &UploadOptions,
+    ) -> Result<Self> {
+        let upload_id = client
+            .create_multipart_upload()
+            .bucket(bucket)
+            .key(key)
+            .set_metadata(Some(metadata))
+            .send()
+            .await
+            .context("Failed to start multipart upload to S3")?
+            .upload_id
+            .context("S3 did not return a multipart upload id")?;
+
+        Ok(Self {
+            client: client.clone(),
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            upload_id,
+            concurrency: options.concurrency,
+            next_part_number: 1,
+            in_flight: JoinSet::new(),
+            completed: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// Waits for the oldest parts until fewer than `limit` are in flight.
+    async fn drain_to(&mut self, limit: usize) -> Result<()> {
+        while self.in_flight.len() > limit {
+            if let Some(part) = self.in_flight.join_next().await {
+                self.completed.push(part??);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn send_part(&mut self, part: Bytes) -> Result<()> {
+        if self.next_part_number > MAX_PARTS {
+            anyhow::bail!(
+                "Object needs more than {} parts; increase the upload part size",
+                MAX_PARTS
+            );
+        }
+        self.drain_to(self.concurrency - 1).await?;
+
+        let part_number = self.next_part_number;
+        self.next_part_number += 1;
+
+        let request = self
+            .client
+            .upload_part()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .upload_id(&self.upload_id)
+            .part_number(part_number)
+            .body(ByteStream::from(part));
+        self.in_flight.spawn(async move {
+            let response = request
+                .send()
+                .await
+                .with_context(|| format!("Failed to upload part {} to S3", part_number))?;
+
+            Ok(CompletedPart::builder()
+                .set_e_tag(response.e_tag)
+                .part_number(part_number)
+                .build())
+        });
+
+        Ok(())
+    }
+
+    async fn complete(mut self) -> Result<()> {
+        self.drain_to(0).await?;
+        self.completed.sort_by_key(|part| part.part_number);
+
+        self.client
+            .complete_multipart_upload()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .upload_id(&self.upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(std::mem::take(&mut self.completed)))
+                    .build(),
+            )
+            .send()
+            .await
+            .context("Failed to complete multipart upload to S3")?;
+        self.finished = true;
+
+        Ok(())
+    }
+
+    /// Discards the parts sent so far, so S3 does not keep storing them.
+    async fn abort(mut self) -> Result<()> {
+        self.in_flight.shutdown().await;
+        self.finished = true;
+
+        self.client
+            .abort_multipart_upload()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .upload_id(&self.upload_id)
+            .send()
+            .await
+            .context("Failed to abort multipart upload to S3")?;
+
+        Ok(())
+    }
+}
+
+impl Drop for MultipartUpload {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            warn!("Multipart upload of '{}' was left unfinished", self.key);
+            return;
+        };
+
+        let request = self
+            .client
+            .abort_multipart_upload()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .upload_id(&self.upload_id);
+        let key = self.key.clone();
+        runtime.spawn(async move {
+            if let Err(err) = request.send().await {
+                warn!("Failed to abort multipart upload of '{}': {}", key, err);
+            }
+        });
+    }
+}
+
+fn run_checks(checks: &mut [Box<dyn UploadCheck>], chunk: &[u8]) -> Result<()> {
+    checks.iter_mut().try_for_each(|check| check.update(chunk))
+}
+
+fn finish_checks(checks: Vec<Box<dyn UploadCheck>>) -> Result<()> {
+    checks.into_iter().try_for_each(|check| check.finish())
+}
+
+/// Sends the rest of `body` as parts of `upload`, starting with `buffer`,
+/// and completes it once every check accepts the data.
+async fn send_parts(
+    upload: &mut MultipartUpload,
+    mut body: ByteStream,
+    mut buffer: BytesMut,
+    mut checks: Vec<Box<dyn UploadCheck>>,
+    part_size: usize,
+) -> Result<()> {
+    loop {
+        while buffer.len() >= part_size {
+            upload
+                .send_part(buffer.split_to(part_size).freeze())
+                .await?;
+        }
+
+        match body
+            .try_next()
+            .await
+            .context("Failed to read upload body")?
+        {
+            Some(chunk) => {
+                run_checks(&mut checks, &chunk)?;
+                buffer.extend_from_slice(&chunk);
+            }
+            None => break,
+        }
+    }
+
+    if !buffer.is_empty() {
+        upload.send_part(buffer.freeze()).await?;
+    }
+    upload.drain_to(0).await?;
+
+    finish_checks(checks)
+}
+
 pub async fn put_stream(
     client: &Client,
     bucket: &str,
     key: &str,
     body: ByteStream,
     expected_digest: Option<&str>,
+    options: &UploadOptions,
 ) -> Result<()> {
-    let body = if let Some(expected_digest) = expected_digest {
-        let (algorithm, expected_hash) = expected_digest
-            .split_once(':')
-            .ok_or_else(|| anyhow::anyhow!("Invalid digest format, expected 'algorithm:hash'"))?;
-
-        let mut all_bytes = Vec::new();
-        let computed_hash = match algorithm {
-            "sha256" => {
-                let bytes = body.collect().await?.into_bytes();
-                let mut hasher = Sha256::new();
-                hasher.update(&bytes);
-                all_bytes.extend_from_slice(&bytes);
-                format!("{:x}", hasher.finalize())
+    put_stream_with_metadata(
+        client,
+        bucket,
+        key,
+        body,
+        expected_digest,
+        HashMap::new(),
+        options,
+    )
+    .await
+}
+
+pub async fn put_stream_with_metadata(
+    client: &Client,
+    bucket: &str,
+    key: &str,
+    body: ByteStream,
+    expected_digest: Option<&str>,
+    metadata: HashMap<String, String>,
+    options: &UploadOptions,
+) -> Result<()> {
+    let mut checks: Vec<Box<dyn UploadCheck>> = Vec::new();
+    if let Some(expected_digest) = expected_digest {
+        checks.push(Box::new(digest_utils::DigestVerifier::new(
+            expected_digest,
+        )?));
+    }
+
+    put_checked(client, bucket, key, body, checks, metadata, options).await
+}
+
+/// Uploads `body` to `key` once every check accepts it, holding at most a
+/// few parts in memory. Bodies that fit in one part are checked in full and
+/// then stored with a single request. Larger ones go up as a multipart
+/// upload that is only completed when the checks pass and is aborted
+/// otherwise, so a rejected or failed upload never replaces the current
+/// object.
+pub async fn put_checked(
+    client: &Client,
+    bucket: &str,
+    key: &str,
+    mut body: ByteStream,
+    mut checks: Vec<Box<dyn UploadCheck>>,
+    metadata: HashMap<String, String>,
+    options: &UploadOptions,
+) -> Result<()> {
+    let mut buffer = BytesMut::new();
+
+    while buffer.len() <= options.part_size {
+        match body
+            .try_next()
+            .await
+            .context("Failed to read upload body")?
+        {
+            Some(chunk) => {
+                run_checks(&mut checks, &chunk)?;
+                buffer.extend_from_slice(&chunk);
             }
-            _ => anyhow::bail!("Unsupported hash algorithm: {}", algorithm),
-        };
+            None => {
+                finish_checks(checks)?;
+                return put_object(client, bucket, key, buffer.freeze(), metadata).await;
+            }
+        }
+    }
 
-        if computed_hash != expected_hash {
-            anyhow::bail!(
-                "Digest mismatch: expected {}, got {}:{}",
-                expected_digest,
-                algorithm,
-                computed_hash
-            );
+    let mut upload = MultipartUpload::start(client, bucket, key, metadata, options).await?;
+    match send_parts(&mut upload, body, buffer, checks, options.part_size).await {
+        Ok(()) => upload.complete().await,
+        Err(err) => {
+            if let Err(abort_err) = upload.abort().await {
+                warn!(
+                    "Failed to abort multipart upload of '{}': {:#}",
+                    key, abort_err
+                );
+            }
+            Err(err)
         }
+    }
+}
 
-        ByteStream::from(Bytes::from(all_bytes))
-    } else {
-        body
+/// An object as read from S3, with the ETag of the revision that was read.
+pub struct StoredObject {
+    pub bytes: Bytes,
+    pub metadata: HashMap<String, String>,
+    pub e_tag: Option<String>,
+}
+
+pub async fn get_bytes(client: &Client, bucket: &str, key: &str) -> Result<Option<Bytes>> {
+    Ok(get_object(client, bucket, key)
+        .await?
+        .map(|object| object.bytes))
+}
+
+pub async fn get_object(client: &Client, bucket: &str, key: &str) -> Result<Option<StoredObject>> {
+    let response = match client.get_object().bucket(bucket).key(key).send().await {
+        Ok(response) => response,
+        Err(err)
+            if err
+                .as_service_error()
+                .is_some_and(|service_err| service_err.is_no_such_key()) =>
+        {
+            return Ok(None);
+        }
+        Err(err) => return Err(err).context("Failed to download object from S3"),
     };
 
-    client
+    let e_tag = response.e_tag;
+    let metadata = response.metadata.unwrap_or_default();
+    let bytes = response
+        .body
+        .collect()
+        .await
+        .context("Failed to read object body from S3")?
+        .into_bytes();
+
+    Ok(Some(StoredObject {
+        bytes,
+        metadata,
+        e_tag,
+    }))
+}
+
+/// Replaces `key` only if it is still at revision `e_tag`. Returns `false`
+/// when the object was changed in the meantime and nothing was written.
+pub async fn put_if_match(
+    client: &Client,
+    bucket: &str,
+    key: &str,
+    body: Bytes,
+    metadata: HashMap<String, String>,
+    e_tag: &str,
+) -> Result<bool> {
+    let result = client
         .put_object()
         .bucket(bucket)
         .key(key)
-        .body(body)
+        .if_match(e_tag)
+        .set_metadata(Some(metadata))
+        .body(ByteStream::from(body))
+        .send()
+        .await;
+
+    match result {
+        Ok(_) => Ok(true),
+        Err(err)
+            if err
+                .raw_response()
+                .is_some_and(|response| response.status().as_u16() == 412) =>
+        {
+            Ok(false)
+        }
+        Err(err) => Err(err).context("Failed to upload object to S3"),
+    }
+}
+
+pub async fn delete(client: &Client, bucket: &str, key: &str) -> Result<()> {
+    client
+        .delete_object()
+        .bucket(bucket)
+        .key(key)
         .send()
         .await
-        .context("Failed to upload object to S3")?;
+        .context("Failed to delete object from S3")?;
 
     Ok(())
 }
+
+/// Outcome of removing a set of objects. Keys that could not be removed are
+/// listed so they can be cleaned up by hand.
+#[derive(Debug, Default, Serialize)]
+pub struct DeletionReport {
+    pub deleted: usize,
+    pub failed: Vec<String>,
+}
+
+/// Removes every key in `keys`, carrying on past failures. Keys that do not
+/// exist count as removed.
+pub async fn delete_all(client: &Client, bucket: &str, keys: &[String]) -> DeletionReport {
+    let results = join_all(keys.iter().map(|key| delete(client, bucket, key))).await;
+
+    let mut report = DeletionReport::default();
+    for (key, result) in keys.iter().zip(results) {
+        match result {
+            Ok(()) => report.deleted += 1,
+            Err(err) => {
+                warn!("Failed to delete '{}/{}': {:#}", bucket, key, err);
+                report.failed.push(format!("{}/{}", bucket, key));
+            }
+        }
+    }
+
+    report
+}
+
+pub async fn get_metadata(
+    client: &Client,
+    bucket: &str,
+    key: &str,
+) -> Result<HashMap<String, String>> {
+    let response = client
+        .head_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .context("Failed to read object metadata from S3")?;
+
+    Ok(response.metadata.unwrap_or_default())
+}
+
+pub async fn list_keys(client: &Client, bucket: &str, prefix: &str) -> Result<Vec<String>> {
+    let mut keys = Vec::new();
+    let mut pages = client
+        .list_objects_v2()
+        .bucket(bucket)
+        .prefix(prefix)
+        .into_paginator()
+        .send();
+
+    while let Some(page) = pages.next().await {
+        let page = page.context("Failed to list objects in S3")?;
+        keys.extend(
+            page.contents()
+                .iter()
+                .filter_map(|obj| obj.key().map(String::from)),
+        );
+    }
+
+    Ok(keys)
+}
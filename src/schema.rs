@@ -1,6 +1,10 @@
 // @generated automatically by Diesel CLI.
 
 pub mod sql_types {
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "execution_status"))]
+    pub struct ExecutionStatus;
+
     #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "module_type"))]
     pub struct ModuleType;
@@ -23,6 +27,55 @@ diesel::table! {
         secrets_object_key -> Text,
         digest -> Text,
         source_url -> Nullable<Text>,
+        is_pinned -> Bool,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::ExecutionStatus;
+
+    executions (id) {
+        id -> Uuid,
+        #[max_length = 64]
+        module_id -> Varchar,
+        status -> ExecutionStatus,
+        exit_code -> Nullable<Int4>,
+        stdout -> Text,
+        stderr -> Text,
+        error -> Nullable<Text>,
+        started_at -> Timestamptz,
+        finished_at -> Nullable<Timestamptz>,
+        duration_ms -> Nullable<Int8>,
+        heartbeat_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    hook_subscriptions (module_id) {
+        #[max_length = 64]
+        module_id -> Varchar,
+        events -> Array<Text>,
+        approvals -> Array<Text>,
+    }
+}
+
+diesel::table! {
+    interceptor_bindings (module_id) {
+        #[max_length = 64]
+        module_id -> Varchar,
+        priority -> Int4,
+        definition_ids -> Array<Text>,
+        definition_types -> Array<Text>,
+    }
+}
+
+diesel::table! {
+    language_bindings (definition_type) {
+        #[max_length = 64]
+        definition_type -> Varchar,
+        #[max_length = 64]
+        module_id -> Varchar,
     }
 }
 
@@ -45,7 +98,37 @@ diesel::table! {
         secrets_object_key -> Text,
         digest -> Text,
         source_url -> Nullable<Text>,
+        max_memory_bytes -> Int8,
+        fuel_limit -> Int8,
+        timeout_ms -> Int4,
+        capabilities -> Jsonb,
+        is_pinned -> Bool,
+    }
+}
+
+diesel::table! {
+    proxy_bindings (definition_id) {
+        #[max_length = 64]
+        definition_id -> Varchar,
+        #[max_length = 64]
+        module_id -> Varchar,
+        upstream_url -> Text,
     }
 }
 
-diesel::allow_tables_to_appear_in_same_query!(definitions, modules,);
+diesel::joinable!(executions -> modules (module_id));
+diesel::joinable!(hook_subscriptions -> modules (module_id));
+diesel::joinable!(interceptor_bindings -> modules (module_id));
+diesel::joinable!(language_bindings -> modules (module_id));
+diesel::joinable!(proxy_bindings -> definitions (definition_id));
+diesel::joinable!(proxy_bindings -> modules (module_id));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    definitions,
+    executions,
+    hook_subscriptions,
+    interceptor_bindings,
+    language_bindings,
+    modules,
+    proxy_bindings,
+);
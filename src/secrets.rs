@@ -0,0 +1,232 @@
+use crate::{config::Config, errors::AppError};
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const ENVELOPE_FORMAT: u8 = 1;
+
+/// A secret document encrypted with a random data key, where the data key is
+/// itself encrypted ("wrapped") with the master key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope {
+    pub format: u8,
+    pub key_version: u32,
+    pub wrapped_key: String,
+    pub key_nonce: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+/// Master keys by version. New secrets are always sealed with the active
+/// version; any configured version can still open existing envelopes, which
+/// lets the master key be rotated without downtime.
+pub struct Keyring {
+    active_version: u32,
+    master_keys: HashMap<u32, Key<Aes256Gcm>>,
+}
+
+impl Keyring {
+    pub fn new(master_key: Option<&str>) -> Result<Self> {
+        Self::with_versions(1, master_key, &[])
+    }
+
+    pub fn with_versions(
+        active_version: u32,
+        master_key: Option<&str>,
+        retired_keys: &[(u32, String)],
+    ) -> Result<Self> {
+        let mut master_keys = HashMap::new();
+
+        for (version, key) in retired_keys {
+            if *version == active_version {
+                anyhow::bail!(
+                    "Retired secrets master key reuses active version {}",
+                    version
+                );
+            }
+            master_keys.insert(*version, decode_key(key)?);
+        }
+
+        if let Some(master_key) = master_key {
+            master_keys.insert(active_version, decode_key(master_key)?);
+        }
+
+        Ok(Self {
+            active_version,
+            master_keys,
+        })
+    }
+
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let retired_keys = config
+            .secrets_retired_master_keys
+            .as_deref()
+            .map(parse_retired_keys)
+            .transpose()?
+            .unwrap_or_default();
+
+        Self::with_versions(
+            config.secrets_master_key_version,
+            config.secrets_master_key.as_deref(),
+            &retired_keys,
+        )
+    }
+
+    pub fn active_version(&self) -> u32 {
+        self.active_version
+    }
+
+    fn master_key(&self, version: u32) -> Result<&Key<Aes256Gcm>> {
+        if self.master_keys.is_empty() {
+            return Err(AppError::not_configured(
+                "Secrets keyring is not configured; set MCI_SECRETS_MASTER_KEY",
+            )
+            .into());
+        }
+
+        self.master_keys.get(&version).ok_or_else(|| {
+            AppError::not_configured(format!(
+                "No secrets master key is configured for version {}",
+                version
+            ))
+            .into()
+        })
+    }
+
+    /// Encrypts `plaintext`, binding the ciphertext to `context` (the object
+    /// location) so an envelope cannot be replayed under another resource.
+    pub fn seal(&self, plaintext: &[u8], context: &str) -> Result<Envelope> {
+        let master_key = self.master_key(self.active_version)?;
+        let data_key = Aes256Gcm::generate_key(OsRng);
+
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = Aes256Gcm::new(&data_key)
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad: context.as_bytes(),
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt secrets"))?;
+
+        let key_nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let wrapped_key = Aes256Gcm::new(master_key)
+            .encrypt(&key_nonce, data_key.as_slice())
+            .map_err(|_| anyhow::anyhow!("Failed to wrap data key"))?;
+
+        Ok(Envelope {
+            format: ENVELOPE_FORMAT,
+            key_version: self.active_version,
+            wrapped_key: STANDARD.encode(wrapped_key),
+            key_nonce: STANDARD.encode(key_nonce),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        })
+    }
+
+    pub fn open(&self, envelope: &Envelope, context: &str) -> Result<Vec<u8>> {
+        let data_key = self.unwrap_data_key(envelope)?;
+        let nonce = decode_nonce(&envelope.nonce)?;
+        let ciphertext = STANDARD
+            .decode(&envelope.ciphertext)
+            .context("Invalid ciphertext encoding")?;
+
+        Aes256Gcm::new_from_slice(&data_key)
+            .context("Invalid data key length")?
+            .decrypt(
+                &nonce,
+                Payload {
+                    msg: &ciphertext,
+                    aad: context.as_bytes(),
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("Failed to decrypt secrets"))
+    }
+
+    /// Re-wraps the envelope's data key under the active master key. The
+    /// ciphertext itself is left untouched.
+    pub fn rewrap(&self, envelope: &Envelope) -> Result<Envelope> {
+        let data_key = self.unwrap_data_key(envelope)?;
+        let key_nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let wrapped_key = Aes256Gcm::new(self.master_key(self.active_version)?)
+            .encrypt(&key_nonce, data_key.as_slice())
+            .map_err(|_| anyhow::anyhow!("Failed to wrap data key"))?;
+
+        Ok(Envelope {
+            key_version: self.active_version,
+            wrapped_key: STANDARD.encode(wrapped_key),
+            key_nonce: STANDARD.encode(key_nonce),
+            ..envelope.clone()
+        })
+    }
+
+    fn unwrap_data_key(&self, envelope: &Envelope) -> Result<Vec<u8>> {
+        if envelope.format != ENVELOPE_FORMAT {
+            anyhow::bail!("Unsupported secrets envelope format: {}", envelope.format);
+        }
+
+        let master_key = self.master_key(envelope.key_version)?;
+        let key_nonce = decode_nonce(&envelope.key_nonce)?;
+        let wrapped_key = STANDARD
+            .decode(&envelope.wrapped_key)
+            .context("Invalid wrapped data key encoding")?;
+
+        Aes256Gcm::new(master_key)
+            .decrypt(&key_nonce, wrapped_key.as_slice())
+            .map_err(|_| anyhow::anyhow!("Failed to unwrap data key"))
+    }
+}
+
+/// Parses retired keys given as comma-separated `version:base64key` pairs.
+fn parse_retired_keys(input: &str) -> Result<Vec<(u32, String)>> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (version, key) = entry.split_once(':').ok_or_else(|| {
+                anyhow::anyhow!("Retired secrets master keys must be 'version:key'")
+            })?;
+            let version = version
+                .parse()
+                .with_context(|| format!("Invalid secrets master key version '{}'", version))?;
+
+            Ok((version, key.to_string()))
+        })
+        .collect()
+}
+
+fn decode_key(encoded: &str) -> Result<Key<Aes256Gcm>> {
+    let bytes = STANDARD
+        .decode(encoded.trim())
+        .context("Secrets master key must be base64 encoded")?;
+
+    if bytes.len() != 32 {
+        anyhow::bail!(
+            "Secrets master key must be 32 bytes, got {} bytes",
+            bytes.len()
+        );
+    }
+
+    Ok(*Key::<Aes256Gcm>::from_slice(&bytes))
+}
+
+fn decode_nonce(encoded: &str) -> Result<Nonce<<Aes256Gcm as AeadCore>::NonceSize>> {
+    let bytes = STANDARD.decode(encoded).context("Invalid nonce encoding")?;
+
+    if bytes.len() != 12 {
+        anyhow::bail!("Invalid nonce length: {}", bytes.len());
+    }
+
+    Ok(*Nonce::from_slice(&bytes))
+}
+
+#[cfg(test)]
+#[path = "secrets_tests.rs"]
+mod tests;
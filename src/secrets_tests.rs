@@ -0,0 +1,111 @@
+use super::*;
+
+fn generate_key() -> String {
+    STANDARD.encode(Aes256Gcm::generate_key(OsRng))
+}
+
+fn keyring() -> Keyring {
+    Keyring::new(Some(&generate_key())).unwrap()
+}
+
+#[test]
+fn test_seal_and_open_round_trip() {
+    let keyring = keyring();
+    let envelope = keyring
+        .seal(b"{\"API_KEY\":\"s3cr3t\"}", "definitions/a")
+        .unwrap();
+
+    let plaintext = keyring.open(&envelope, "definitions/a").unwrap();
+    assert_eq!(plaintext, b"{\"API_KEY\":\"s3cr3t\"}");
+}
+
+#[test]
+fn test_envelope_does_not_contain_plaintext() {
+    let keyring = keyring();
+    let envelope = keyring.seal(b"s3cr3t-value", "definitions/a").unwrap();
+    let serialized = serde_json::to_string(&envelope).unwrap();
+
+    assert!(!serialized.contains("s3cr3t-value"));
+}
+
+#[test]
+fn test_open_rejects_other_context() {
+    let keyring = keyring();
+    let envelope = keyring.seal(b"value", "definitions/a").unwrap();
+
+    assert!(keyring.open(&envelope, "definitions/b").is_err());
+}
+
+#[test]
+fn test_open_rejects_other_master_key() {
+    let envelope = keyring().seal(b"value", "definitions/a").unwrap();
+
+    assert!(keyring().open(&envelope, "definitions/a").is_err());
+}
+
+#[test]
+fn test_seal_without_master_key_fails() {
+    let keyring = Keyring::new(None).unwrap();
+
+    assert!(keyring.seal(b"value", "definitions/a").is_err());
+}
+
+#[test]
+fn test_new_rejects_invalid_keys() {
+    assert!(Keyring::new(Some("not base64!")).is_err());
+    assert!(Keyring::new(Some(&STANDARD.encode([0u8; 16]))).is_err());
+}
+
+#[test]
+fn test_open_with_retired_key_and_rewrap() {
+    let old_key = generate_key();
+    let new_key = generate_key();
+
+    let old_keyring = Keyring::new(Some(&old_key)).unwrap();
+    let envelope = old_keyring.seal(b"value", "definitions/a").unwrap();
+    assert_eq!(envelope.key_version, 1);
+
+    let rotated_keyring = Keyring::with_versions(2, Some(&new_key), &[(1, old_key)]).unwrap();
+    assert_eq!(
+        rotated_keyring.open(&envelope, "definitions/a").unwrap(),
+        b"value"
+    );
+
+    let rewrapped = rotated_keyring.rewrap(&envelope).unwrap();
+    assert_eq!(rewrapped.key_version, 2);
+    assert_eq!(rewrapped.ciphertext, envelope.ciphertext);
+
+    let new_only = Keyring::with_versions(2, Some(&new_key), &[]).unwrap();
+    assert_eq!(
+        new_only.open(&rewrapped, "definitions/a").unwrap(),
+        b"value"
+    );
+    assert!(new_only.open(&envelope, "definitions/a").is_err());
+}
+
+#[test]
+fn test_with_versions_rejects_duplicate_active_version() {
+    let key = generate_key();
+
+    assert!(Keyring::with_versions(1, Some(&key), &[(1, key.clone())]).is_err());
+}
+
+#[test]
+fn test_parse_retired_keys() {
+    let keys = parse_retired_keys("1:abc=, 2:def=").unwrap();
+    assert_eq!(keys, vec![(1, "abc=".to_string()), (2, "def=".to_string())]);
+
+    assert!(parse_retired_keys("abc").is_err());
+    assert!(parse_retired_keys("x:abc").is_err());
+}
+
+#[test]
+fn test_unconfigured_keyring_reports_it() {
+    let keyring = Keyring::new(None).unwrap();
+
+    let err = keyring.seal(b"{}", "definitions/a").unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<AppError>(),
+        Some(AppError::NotConfigured(_))
+    ));
+}
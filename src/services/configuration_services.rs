@@ -0,0 +1,243 @@
+use crate::{
+    errors::AppError,
+    s3,
+    utils::{json_utils, schema_utils},
+};
+use anyhow::Context;
+use aws_sdk_s3;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use tracing::warn;
+
+fn configuration_key(object_key: &str) -> String {
+    format!("configurations/{}", object_key)
+}
+
+fn default_configuration_key(object_key: &str) -> String {
+    format!("defaults/{}", object_key)
+}
+
+fn configuration_schema_key(object_key: &str) -> String {
+    format!("schemas/{}", object_key)
+}
+
+/// Every object that may hold configuration for `object_key`.
+pub fn stored_keys(object_key: &str) -> [String; 3] {
+    [
+        configuration_key(object_key),
+        default_configuration_key(object_key),
+        configuration_schema_key(object_key),
+    ]
+}
+
+fn ensure_object(configuration: &Value) -> Result<(), AppError> {
+    if !configuration.is_object() {
+        return Err(AppError::bad_request("Configuration must be a JSON object"));
+    }
+    Ok(())
+}
+
+async fn read_document(
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    key: &str,
+) -> Result<Option<Value>, AppError> {
+    let Some(bytes) = s3::get_bytes(s3_client, bucket, key).await? else {
+        return Ok(None);
+    };
+    let document = serde_json::from_slice(&bytes)
+        .with_context(|| format!("Stored configuration '{}' is not valid JSON", key))?;
+
+    Ok(Some(document))
+}
+
+async fn write_document(
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    key: &str,
+    document: &Value,
+) -> Result<(), AppError> {
+    let body = serde_json::to_vec(document).context("Failed to serialize configuration")?;
+
+    s3::put_object(s3_client, bucket, key, body.into(), HashMap::new())
+        .await
+        .context("Failed to upload configuration to S3")?;
+
+    Ok(())
+}
+
+async fn get_default_configuration(
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    object_key: &str,
+) -> Result<Value, AppError> {
+    let defaults = read_document(s3_client, bucket, &default_configuration_key(object_key)).await?;
+
+    Ok(defaults.unwrap_or_else(|| Value::Object(Map::new())))
+}
+
+pub async fn get_configuration(
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    object_key: &str,
+) -> Result<Value, AppError> {
+    match read_document(s3_client, bucket, &configuration_key(object_key)).await? {
+        Some(configuration) => Ok(configuration),
+        None => get_default_configuration(s3_client, bucket, object_key).await,
+    }
+}
+
+pub async fn get_configuration_schema(
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    object_key: &str,
+) -> Result<Option<Value>, AppError> {
+    read_document(s3_client, bucket, &configuration_schema_key(object_key)).await
+}
+
+pub async fn set_configuration(
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    object_key: &str,
+    configuration: Value,
+) -> Result<Value, AppError> {
+    ensure_object(&configuration)?;
+
+    if let Some(schema) = get_configuration_schema(s3_client, bucket, object_key).await? {
+        schema_utils::validate_instance(&schema, &configuration)?;
+    }
+
+    write_document(
+        s3_client,
+        bucket,
+        &configuration_key(object_key),
+        &configuration,
+    )
+    .await?;
+
+    Ok(configuration)
+}
+
+pub async fn update_configuration(
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    object_key: &str,
+    patch: &Value,
+) -> Result<Value, AppError> {
+    ensure_object(patch)?;
+
+    let mut configuration = get_configuration(s3_client, bucket, object_key).await?;
+    json_utils::merge_patch(&mut configuration, patch);
+
+    set_configuration(s3_client, bucket, object_key, configuration).await
+}
+
+pub async fn reset_configuration(
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    object_key: &str,
+) -> Result<Value, AppError> {
+    let defaults = get_default_configuration(s3_client, bucket, object_key).await?;
+
+    set_configuration(s3_client, bucket, object_key, defaults).await
+}
+
+/// Checks the configuration defaults and schema declared by a manifest before
+/// anything is uploaded, so a bad manifest does not leave stray objects behind.
+pub fn validate_manifest_configuration(
+    defaults: Option<&Value>,
+    schema: Option<&Value>,
+) -> Result<(), AppError> {
+    if let Some(defaults) = defaults {
+        ensure_object(defaults)?;
+    }
+
+    if let Some(schema) = schema {
+        schema_utils::ensure_valid_schema(schema)?;
+
+        if let Some(defaults) = defaults {
+            schema_utils::validate_instance(schema, defaults)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks the stored configuration against a schema an upgrade is about to
+/// install, failing with the schema's violations when it no longer fits.
+/// Upgrades run this before uploading anything so a rejected configuration
+/// leaves the installed content untouched.
+pub async fn ensure_configuration_fits(
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    object_key: &str,
+    schema: Option<&Value>,
+) -> Result<(), AppError> {
+    let Some(schema) = schema else {
+        return Ok(());
+    };
+
+    match read_document(s3_client, bucket, &configuration_key(object_key)).await? {
+        Some(current) => schema_utils::validate_instance(schema, &current),
+        None => Ok(()),
+    }
+}
+
+/// Stores the manifest-provided schema and the defaults that
+/// `reset_configuration` restores. The current configuration is seeded when
+/// none has been stored yet. One the schema rejects fails with its violations
+/// before anything is written, unless `reset_configuration` asks for it to be
+/// replaced by the defaults.
+pub async fn store_manifest_configuration(
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    object_key: &str,
+    defaults: Option<&Value>,
+    schema: Option<&Value>,
+    reset_configuration: bool,
+) -> Result<(), AppError> {
+    validate_manifest_configuration(defaults, schema)?;
+
+    let current_key = configuration_key(object_key);
+    let fits_schema = match read_document(s3_client, bucket, &current_key).await? {
+        None => false,
+        Some(current) => match schema {
+            Some(schema) => match schema_utils::validate_instance(schema, &current) {
+                Ok(()) => true,
+                Err(err) if reset_configuration => {
+                    warn!(
+                        "Resetting configuration '{}' to its defaults: {}",
+                        object_key, err
+                    );
+                    false
+                }
+                Err(err) => return Err(err),
+            },
+            None => true,
+        },
+    };
+
+    let schema_key = configuration_schema_key(object_key);
+    match schema {
+        Some(schema) => write_document(s3_client, bucket, &schema_key, schema).await?,
+        None => s3::delete(s3_client, bucket, &schema_key).await?,
+    }
+
+    let defaults = defaults
+        .cloned()
+        .unwrap_or_else(|| Value::Object(Map::new()));
+
+    write_document(
+        s3_client,
+        bucket,
+        &default_configuration_key(object_key),
+        &defaults,
+    )
+    .await?;
+
+    if !fits_schema {
+        write_document(s3_client, bucket, &current_key, &defaults).await?;
+    }
+
+    Ok(())
+}
@@ -1,21 +1,27 @@
 use crate::{
-    db::DbConnection,
-    models::{Definition, NewDefinition, UpdateDefinition},
+    db::{self, DbConnection},
+    errors::AppError,
+    events::LifecycleEvent,
+    models::{self, Definition, NewDefinition, UpdateDefinition, UpgradeOptions},
     s3,
     schema::definitions,
-    utils::{source_utils, stream_utils},
+    services::{configuration_services, documents_services, secrets_services},
+    utils::{digest_utils, source_utils, stream_utils},
 };
 use anyhow::{Context, Result};
 use aws_sdk_s3;
 use aws_smithy_types::byte_stream::ByteStream;
+use bytes::Bytes;
 use diesel::{associations::HasTable, prelude::*};
-use futures::stream::TryStreamExt;
-use http_body_util::StreamBody;
 use reqwest;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use serde_json::Value;
+use std::{future::Future, path::Path};
 use tokio::fs;
 
+/// Largest definition document that is read into memory for validation.
+const MAX_DOCUMENT_BYTES: usize = 16 * 1024 * 1024;
+
 #[derive(Debug, Deserialize)]
 pub enum SortBy {
     Id,
@@ -47,8 +53,17 @@ pub struct DefinitionPayload {
     pub r#type: String,
     pub description: String,
     pub file_url: String,
-    pub digest: String,
+    #[serde(default)]
+    pub digest: Option<String>,
+    /// Accepts the file without a `digest`; the server records the digest of
+    /// what it downloads instead.
+    #[serde(default)]
+    pub allow_unpinned: bool,
     pub source_url: Option<String>,
+    #[serde(default)]
+    pub default_configuration: Option<Value>,
+    #[serde(default)]
+    pub configuration_schema: Option<Value>,
 }
 
 async fn fetch_definition_from_path(path: &Path) -> Result<DefinitionPayload> {
@@ -90,6 +105,48 @@ async fn fetch_definition(
     }
 }
 
+/// Downloads the definition document at `file_url`, checks it against
+/// `digest` and validates its structure, so nothing malformed is stored.
+/// Documents over [`MAX_DOCUMENT_BYTES`] are refused while downloading.
+/// Returns the document with its digest, which is computed when the payload
+/// is unpinned.
+async fn fetch_document(
+    http_client: &reqwest::Client,
+    file_url: &str,
+    digest: Option<&str>,
+    definition_type: &str,
+) -> Result<(Bytes, String)> {
+    let document = match source_utils::Source::parse(file_url)? {
+        source_utils::Source::Http(url) => {
+            let response = stream_utils::stream_content_from_url(http_client, &url)
+                .await
+                .context("Failed to fetch definition file from URL")?;
+            stream_utils::read_response_limited(response, MAX_DOCUMENT_BYTES)
+                .await
+                .context("Failed to read definition file from URL")?
+        }
+        source_utils::Source::File(path) => {
+            let stream = stream_utils::stream_content_from_path(path)
+                .await
+                .context("Failed to read definition file from path")?;
+            stream_utils::read_stream_limited(stream, MAX_DOCUMENT_BYTES)
+                .await
+                .context("Failed to read definition file from path")?
+        }
+    };
+
+    let digest = match digest {
+        Some(digest) => {
+            digest_utils::verify_digest(digest, &document)?;
+            digest.to_string()
+        }
+        None => digest_utils::record_digest(&document),
+    };
+    documents_services::validate_document(definition_type, &document)?;
+
+    Ok((document, digest))
+}
+
 fn db_create_definition(
     conn: &mut DbConnection,
     new_definition: &NewDefinition,
@@ -118,16 +175,52 @@ pub fn get_definition(conn: &mut DbConnection, definition_id: &str) -> QueryResu
         .first(conn)
 }
 
-pub fn delete_definition(conn: &mut DbConnection, definition_id: &str) -> QueryResult<usize> {
-    diesel::delete(definitions::table.find(definition_id)).execute(conn)
+/// Deletes the definition row and returns it, so its stored objects can be
+/// removed afterwards.
+pub fn delete_definition(
+    conn: &mut DbConnection,
+    definition_id: &str,
+) -> QueryResult<Option<Definition>> {
+    diesel::delete(definitions::table.find(definition_id))
+        .returning(Definition::as_returning())
+        .get_result(conn)
+        .optional()
+}
+
+/// Removes the document, configuration and secrets stored for a deleted
+/// definition.
+pub async fn delete_objects(
+    s3_client: &aws_sdk_s3::Client,
+    definition: &Definition,
+) -> s3::DeletionReport {
+    let mut keys = vec![definition.definition_object_key.clone()];
+    keys.extend(configuration_services::stored_keys(
+        &definition.configuration_object_key,
+    ));
+    keys.push(secrets_services::secrets_key(
+        &definition.secrets_object_key,
+    ));
+
+    s3::delete_all(s3_client, "definitions", &keys).await
 }
 
+/// Applies `update_definition` and returns the definition as it was before and after
+/// the change, so callers can tell which fields actually changed.
 pub fn update_definition(
     conn: &mut DbConnection,
     definition_id: &str,
     update_definition: &UpdateDefinition,
-) -> QueryResult<Definition> {
-    db_update_definition(conn, definition_id, update_definition)
+) -> QueryResult<(Definition, Definition)> {
+    conn.transaction(|conn| {
+        let previous = definitions::table
+            .find(definition_id)
+            .for_update()
+            .select(Definition::as_select())
+            .first(conn)?;
+        let updated = db_update_definition(conn, definition_id, update_definition)?;
+
+        Ok((previous, updated))
+    })
 }
 
 pub fn list_definitions(
@@ -173,12 +266,22 @@ pub fn list_definitions(
     query.select(Definition::as_select()).load(conn)
 }
 
-pub async fn create_definition(
+/// Installs a definition. `approve` is asked about the inserted definition
+/// before it is committed; a rejected definition is rolled back and its
+/// stored objects removed.
+pub async fn create_definition<F, Fut>(
     conn: &mut DbConnection,
     http_client: &reqwest::Client,
     s3_client: &aws_sdk_s3::Client,
+    upload_options: &s3::UploadOptions,
     payload: &DefinitionPayload,
-) -> Result<Definition> {
+    approve: F,
+) -> Result<Definition>
+where
+    F: FnOnce(LifecycleEvent) -> Fut,
+    Fut: Future<Output = Result<(), AppError>>,
+{
+    models::ensure_valid_id(&payload.id)?;
     if get_definition(conn, &payload.id).is_ok() {
         anyhow::bail!(
             "Conflict: Definition with ID '{}' already exists",
@@ -186,35 +289,44 @@ pub async fn create_definition(
         );
     }
 
-    let definition_url = source_utils::Source::parse(&payload.file_url)?;
-    let obj_key = payload.id.clone();
+    configuration_services::validate_manifest_configuration(
+        payload.default_configuration.as_ref(),
+        payload.configuration_schema.as_ref(),
+    )?;
 
-    let body = match &definition_url {
-        source_utils::Source::Http(url) => {
-            let response = stream_utils::stream_content_from_url(http_client, url)
-                .await
-                .context("Failed to fetch definition file from URL")?;
+    let pinned_digest =
+        digest_utils::require_pin(payload.digest.as_deref(), payload.allow_unpinned)?;
 
-            let stream = response.bytes_stream();
-            let frames = stream.map_ok(hyper::body::Frame::data);
-            let body = StreamBody::new(frames);
-            ByteStream::from_body_1_x(body)
-        }
-        source_utils::Source::File(path) => stream_utils::stream_content_from_path(path)
-            .await
-            .context("Failed to read definition file from path")?,
-    };
+    let obj_key = payload.id.clone();
+    let (document, digest) = fetch_document(
+        http_client,
+        &payload.file_url,
+        pinned_digest,
+        &payload.r#type,
+    )
+    .await?;
 
     s3::put_stream(
         s3_client,
         "definitions",
         &obj_key,
-        body,
-        Some(&payload.digest),
+        ByteStream::from(document),
+        None,
+        upload_options,
     )
     .await
     .context("Failed to upload definition to S3")?;
 
+    configuration_services::store_manifest_configuration(
+        s3_client,
+        "definitions",
+        &obj_key,
+        payload.default_configuration.as_ref(),
+        payload.configuration_schema.as_ref(),
+        true,
+    )
+    .await?;
+
     let new_definition = NewDefinition {
         id: payload.id.clone(),
         type_: payload.r#type.clone(),
@@ -223,19 +335,46 @@ pub async fn create_definition(
         definition_object_key: obj_key.clone(),
         configuration_object_key: obj_key.clone(),
         secrets_object_key: obj_key.clone(),
-        digest: payload.digest.clone(),
+        digest,
         source_url: payload.source_url.clone(),
+        is_pinned: pinned_digest.is_some(),
     };
 
-    db_create_definition(conn, &new_definition).context("Failed to save definition to database")
+    db::transaction_checked(
+        conn,
+        |conn| {
+            db_create_definition(conn, &new_definition)
+                .context("Failed to save definition to database")
+        },
+        |definition| {
+            let definition = definition.clone();
+            async move {
+                let approval = approve(LifecycleEvent::DefinitionCreated {
+                    definition: definition.clone(),
+                })
+                .await;
+                if approval.is_err() {
+                    delete_objects(s3_client, &definition).await;
+                }
+                approval
+            }
+        },
+    )
+    .await
 }
 
-pub async fn create_definition_from_registry(
+pub async fn create_definition_from_registry<F, Fut>(
     conn: &mut DbConnection,
     http_client: &reqwest::Client,
     s3_client: &aws_sdk_s3::Client,
+    upload_options: &s3::UploadOptions,
     source_input: &str,
-) -> Result<Definition> {
+    approve: F,
+) -> Result<Definition>
+where
+    F: FnOnce(LifecycleEvent) -> Fut,
+    Fut: Future<Output = Result<(), AppError>>,
+{
     let source = source_utils::Source::parse(source_input)?;
     let mut payload = fetch_definition(http_client, &source)
         .await
@@ -245,15 +384,35 @@ pub async fn create_definition_from_registry(
         payload.source_url = Some(source_input.to_string());
     }
 
-    create_definition(conn, http_client, s3_client, &payload).await
+    create_definition(
+        conn,
+        http_client,
+        s3_client,
+        upload_options,
+        &payload,
+        approve,
+    )
+    .await
 }
 
-pub async fn update_definition_from_source(
+/// Upgrades the definition from its recorded source. Returns the definition and
+/// whether anything changed; a source still serving the installed digest
+/// leaves it untouched. A stored configuration the new schema rejects fails the
+/// upgrade unless `options.reset_configuration` allows replacing it, and
+/// `approve` is asked about the upgraded definition before anything is stored.
+pub async fn update_definition_from_source<F, Fut>(
     conn: &mut DbConnection,
     http_client: &reqwest::Client,
     s3_client: &aws_sdk_s3::Client,
+    upload_options: &s3::UploadOptions,
     definition_id: &str,
-) -> Result<Definition> {
+    options: UpgradeOptions,
+    approve: F,
+) -> Result<(Definition, bool)>
+where
+    F: FnOnce(LifecycleEvent) -> Fut,
+    Fut: Future<Output = Result<(), AppError>>,
+{
     let definition = get_definition(conn, definition_id)
         .context("Failed to fetch current definition from database")?;
     let source_url_str = definition
@@ -265,48 +424,88 @@ pub async fn update_definition_from_source(
         .await
         .context("Failed to fetch updated definition metadata from source")?;
 
-    if definition.digest == remote_payload.digest {
-        return Ok(definition);
+    let pinned_digest = digest_utils::require_upgrade_pin(
+        remote_payload.digest.as_deref(),
+        remote_payload.allow_unpinned,
+        definition.is_pinned,
+        options.allow_unpinned,
+    )?;
+    if pinned_digest == Some(definition.digest.as_str()) {
+        return Ok((definition, false));
+    }
+
+    configuration_services::validate_manifest_configuration(
+        remote_payload.default_configuration.as_ref(),
+        remote_payload.configuration_schema.as_ref(),
+    )?;
+    if !options.reset_configuration {
+        configuration_services::ensure_configuration_fits(
+            s3_client,
+            "definitions",
+            &definition.configuration_object_key,
+            remote_payload.configuration_schema.as_ref(),
+        )
+        .await?;
     }
 
-    let definition_file_source = source_utils::Source::parse(&remote_payload.file_url)?;
     let obj_key = definition.id.clone();
-    let body = match &definition_file_source {
-        source_utils::Source::Http(url) => {
-            let response = stream_utils::stream_content_from_url(http_client, url)
-                .await
-                .context("Failed to fetch updated definition file from URL")?;
-            let stream = response.bytes_stream();
-            let frames = stream.map_ok(hyper::body::Frame::data);
-            let body = StreamBody::new(frames);
+    let (document, digest) = fetch_document(
+        http_client,
+        &remote_payload.file_url,
+        pinned_digest,
+        &remote_payload.r#type,
+    )
+    .await?;
+    if digest == definition.digest {
+        return Ok((definition, false));
+    }
 
-            ByteStream::from_body_1_x(body)
-        }
-        source_utils::Source::File(path) => stream_utils::stream_content_from_path(path)
-            .await
-            .context("Failed to read updated definition file from path")?,
-    };
+    approve(LifecycleEvent::DefinitionUpgraded {
+        definition: Definition {
+            type_: remote_payload.r#type.clone(),
+            digest: digest.clone(),
+            is_pinned: pinned_digest.is_some(),
+            name: remote_payload.name.clone(),
+            description: remote_payload.description.clone(),
+            ..definition.clone()
+        },
+    })
+    .await?;
 
     s3::put_stream(
         s3_client,
         "definitions",
         &obj_key,
-        body,
-        Some(&remote_payload.digest),
+        ByteStream::from(document),
+        None,
+        upload_options,
     )
     .await
     .context("Failed to upload updated definition to S3")?;
 
+    configuration_services::store_manifest_configuration(
+        s3_client,
+        "definitions",
+        &definition.configuration_object_key,
+        remote_payload.default_configuration.as_ref(),
+        remote_payload.configuration_schema.as_ref(),
+        options.reset_configuration,
+    )
+    .await?;
+
     let update_data = UpdateDefinition {
         type_: Some(remote_payload.r#type),
-        digest: Some(remote_payload.digest),
+        digest: Some(digest),
+        is_pinned: Some(pinned_digest.is_some()),
         name: Some(remote_payload.name),
         description: Some(remote_payload.description),
         ..Default::default()
     };
 
-    db_update_definition(conn, definition_id, &update_data)
-        .context("Failed to update definition in database")
+    let definition = db_update_definition(conn, definition_id, &update_data)
+        .context("Failed to update definition in database")?;
+
+    Ok((definition, true))
 }
 
 #[cfg(test)]
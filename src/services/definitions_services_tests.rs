@@ -9,8 +9,11 @@ fn create_valid_payload() -> DefinitionPayload {
         r#type: "test-type".to_string(),
         description: "test description".to_string(),
         file_url: "".to_string(),
-        digest: "sha256:abc123".to_string(),
+        digest: Some("sha256:abc123".to_string()),
+        allow_unpinned: false,
         source_url: None,
+        default_configuration: None,
+        configuration_schema: None,
         id: "test-id".to_string(),
         name: "Test Definition".to_string(),
     }
@@ -0,0 +1,107 @@
+use crate::{
+    errors::{AppError, FieldViolation},
+    services::http_definitions_services::{self, RequestTemplate},
+    utils::schema_utils,
+};
+use serde_json::{json, Value};
+use std::sync::LazyLock;
+
+/// Revisions of the definition document format this server accepts.
+pub const FORMAT_VERSIONS: &[u64] = &[1];
+
+/// Structure of a version 1 definition document. Type-specific sections,
+/// such as the `request` of `http` definitions or the `source` of script
+/// definitions, are allowed alongside the common fields.
+static DOCUMENT_SCHEMA: LazyLock<Value> = LazyLock::new(|| {
+    json!({
+        "type": "object",
+        "required": ["format_version", "name", "description", "input_schema"],
+        "properties": {
+            "format_version": { "type": "integer" },
+            "name": { "type": "string", "minLength": 1 },
+            "description": { "type": "string" },
+            "input_schema": {
+                "type": "object",
+                "required": ["type"],
+                "properties": { "type": { "const": "object" } }
+            },
+            "output_schema": { "type": "object" },
+            "source": { "type": "string" },
+            "request": { "type": "object" }
+        }
+    })
+});
+
+fn violation(pointer: &str, message: impl Into<String>) -> FieldViolation {
+    FieldViolation {
+        pointer: pointer.to_string(),
+        message: message.into(),
+    }
+}
+
+/// Parses and validates the document of a definition of `definition_type`,
+/// reporting every problem found as a field-level violation.
+pub fn validate_document(definition_type: &str, bytes: &[u8]) -> Result<Value, AppError> {
+    let document: Value = serde_json::from_slice(bytes).map_err(|err| {
+        AppError::schema_validation(vec![violation(
+            "",
+            format!("Definition document is not valid JSON: {}", err),
+        )])
+    })?;
+
+    if let Some(version) = document.get("format_version").and_then(Value::as_u64) {
+        if !FORMAT_VERSIONS.contains(&version) {
+            return Err(AppError::schema_validation(vec![violation(
+                "/format_version",
+                format!(
+                    "Unsupported format version {}, expected one of {:?}",
+                    version, FORMAT_VERSIONS
+                ),
+            )]));
+        }
+    }
+
+    let mut violations = match schema_utils::validate_instance(&DOCUMENT_SCHEMA, &document) {
+        Ok(()) => Vec::new(),
+        Err(AppError::SchemaValidation(violations)) => violations,
+        Err(err) => return Err(err),
+    };
+
+    for field in ["input_schema", "output_schema"] {
+        if let Some(schema) = document.get(field).filter(|schema| schema.is_object()) {
+            if let Err(err) = jsonschema::meta::validate(schema) {
+                violations.push(violation(
+                    &format!("/{}", field),
+                    format!("Invalid JSON Schema: {}", err),
+                ));
+            }
+        }
+    }
+
+    if definition_type == http_definitions_services::HTTP_TYPE {
+        match document.get("request") {
+            None => violations.push(violation(
+                "/request",
+                format!(
+                    "A request template is required for definitions of type '{}'",
+                    definition_type
+                ),
+            )),
+            Some(request) => {
+                if let Err(err) = serde_json::from_value::<RequestTemplate>(request.clone()) {
+                    violations.push(violation("/request", err.to_string()));
+                }
+            }
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(document)
+    } else {
+        Err(AppError::schema_validation(violations))
+    }
+}
+
+#[cfg(test)]
+#[path = "documents_services_tests.rs"]
+mod tests;
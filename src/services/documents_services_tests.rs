@@ -0,0 +1,83 @@
+use super::*;
+
+fn document() -> Value {
+    json!({
+        "format_version": 1,
+        "name": "Search",
+        "description": "Searches the catalog",
+        "input_schema": {
+            "type": "object",
+            "properties": { "query": { "type": "string" } },
+            "required": ["query"]
+        },
+        "output_schema": { "type": "object" }
+    })
+}
+
+fn violations(definition_type: &str, document: &Value) -> Vec<FieldViolation> {
+    match validate_document(definition_type, document.to_string().as_bytes()) {
+        Err(AppError::SchemaValidation(violations)) => violations,
+        other => panic!("expected schema violations, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_validate_document() {
+    let validated = validate_document("remote", document().to_string().as_bytes()).unwrap();
+
+    assert_eq!(validated, document());
+}
+
+#[test]
+fn test_validate_document_rejects_invalid_json() {
+    let violations = match validate_document("remote", b"print(arguments)") {
+        Err(AppError::SchemaValidation(violations)) => violations,
+        other => panic!("expected schema violations, got {:?}", other),
+    };
+
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].pointer, "");
+}
+
+#[test]
+fn test_validate_document_reports_each_field() {
+    let mut document = document();
+    document["name"] = json!("");
+    document["input_schema"] = json!({ "type": "string" });
+    document["output_schema"] = json!({ "type": 12 });
+    document.as_object_mut().unwrap().remove("description");
+
+    let pointers: Vec<String> = violations("remote", &document)
+        .into_iter()
+        .map(|violation| violation.pointer)
+        .collect();
+
+    assert!(pointers.contains(&"".to_string()));
+    assert!(pointers.contains(&"/name".to_string()));
+    assert!(pointers.contains(&"/input_schema/type".to_string()));
+    assert!(pointers.contains(&"/output_schema".to_string()));
+}
+
+#[test]
+fn test_validate_document_rejects_unsupported_versions() {
+    let mut document = document();
+    document["format_version"] = json!(2);
+
+    let violations = violations("remote", &document);
+
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].pointer, "/format_version");
+}
+
+#[test]
+fn test_validate_document_checks_http_requests() {
+    let missing = violations("http", &document());
+    assert_eq!(missing[0].pointer, "/request");
+
+    let mut document = document();
+    document["request"] = json!({ "method": "GET" });
+    assert_eq!(violations("http", &document)[0].pointer, "/request");
+
+    document["request"] = json!({ "url": "https://api.example.com/search" });
+    assert!(validate_document("http", document.to_string().as_bytes()).is_ok());
+}
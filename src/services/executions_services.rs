@@ -0,0 +1,261 @@
+use crate::{
+    db::DbConnection,
+    errors::AppError,
+    models::{Execution, ExecutionStatus, FinishedExecution, Module, NewExecution},
+    runtime::{CommandInput, CommandOutput},
+    schema::executions,
+    AppState,
+};
+use chrono::Utc;
+use diesel::prelude::*;
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+/// How often a running execution renews its lease.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+/// How long a running execution may go without renewing its lease before it
+/// is presumed lost with the server that ran it.
+const EXECUTION_LEASE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Deserialize, Default)]
+pub struct ExecutionFilter {
+    pub status: Option<ExecutionStatus>,
+    pub limit: Option<i32>,
+    pub offset: Option<i32>,
+}
+
+fn db_create_execution(
+    conn: &mut DbConnection,
+    new_execution: &NewExecution,
+) -> QueryResult<Execution> {
+    diesel::insert_into(executions::table)
+        .values(new_execution)
+        .returning(Execution::as_returning())
+        .get_result(conn)
+}
+
+fn db_finish_execution(
+    conn: &mut DbConnection,
+    execution_id: Uuid,
+    finished: &FinishedExecution,
+) -> QueryResult<Execution> {
+    diesel::update(executions::table.find(execution_id))
+        .set(finished)
+        .returning(Execution::as_returning())
+        .get_result(conn)
+}
+
+pub fn get_execution(
+    conn: &mut DbConnection,
+    module_id: &str,
+    execution_id: Uuid,
+) -> QueryResult<Execution> {
+    executions::table
+        .find(execution_id)
+        .filter(executions::module_id.eq(module_id))
+        .select(Execution::as_select())
+        .first(conn)
+}
+
+/// Lists a module's executions, most recent first.
+pub fn list_executions(
+    conn: &mut DbConnection,
+    module_id: &str,
+    filter: &ExecutionFilter,
+) -> QueryResult<Vec<Execution>> {
+    let mut query = executions::table
+        .filter(executions::module_id.eq(module_id))
+        .into_boxed();
+
+    if let Some(status) = filter.status {
+        query = query.filter(executions::status.eq(status));
+    }
+    if let Some(limit) = filter.limit {
+        query = query.limit(limit as i64);
+    }
+    if let Some(offset) = filter.offset {
+        query = query.offset(offset as i64);
+    }
+
+    query
+        .order(executions::started_at.desc())
+        .select(Execution::as_select())
+        .load(conn)
+}
+
+fn db_renew_lease(conn: &mut DbConnection, execution_id: Uuid) -> QueryResult<usize> {
+    diesel::update(
+        executions::table
+            .find(execution_id)
+            .filter(executions::status.eq(ExecutionStatus::Running)),
+    )
+    .set(executions::heartbeat_at.eq(Utc::now()))
+    .execute(conn)
+}
+
+/// Marks running executions whose lease expired as failed. The server that
+/// ran them stopped renewing their leases, so they would otherwise stay
+/// running forever. Executions other servers are still running keep
+/// renewing theirs and are left alone.
+pub fn fail_abandoned(conn: &mut DbConnection) -> QueryResult<usize> {
+    let expired_before = Utc::now()
+        - chrono::Duration::from_std(EXECUTION_LEASE).expect("lease fits in a chrono duration");
+
+    diesel::update(
+        executions::table
+            .filter(executions::status.eq(ExecutionStatus::Running))
+            .filter(executions::heartbeat_at.lt(expired_before)),
+    )
+    .set((
+        executions::status.eq(ExecutionStatus::Failed),
+        executions::error.eq("Execution was abandoned by the server running it"),
+        executions::finished_at.eq(Utc::now()),
+    ))
+    .execute(conn)
+}
+
+/// Periodically fails executions whose lease expired, for as long as the
+/// server runs.
+pub async fn run_reaper(state: AppState) {
+    let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let reaped = async {
+            let mut conn = state.db_pool.get()?;
+            let reaped = tokio::task::spawn_blocking(move || fail_abandoned(&mut conn)).await??;
+
+            Ok::<_, AppError>(reaped)
+        }
+        .await;
+
+        match reaped {
+            Ok(0) => {}
+            Ok(reaped) => warn!("Marked {} abandoned executions as failed", reaped),
+            Err(err) => error!("Failed to fail abandoned executions: {}", err),
+        }
+    }
+}
+
+async fn renew_lease(state: &AppState, execution_id: Uuid) {
+    let renewed = async {
+        let mut conn = state.db_pool.get()?;
+        tokio::task::spawn_blocking(move || db_renew_lease(&mut conn, execution_id)).await??;
+
+        Ok::<_, AppError>(())
+    }
+    .await;
+
+    if let Err(err) = renewed {
+        warn!(execution = %execution_id, "Failed to renew execution lease: {}", err);
+    }
+}
+
+/// Replaces NUL characters, which Postgres `TEXT` columns cannot hold, so
+/// output containing them is still recorded.
+fn storable(output: String) -> String {
+    if output.contains('\0') {
+        output.replace('\0', "\u{FFFD}")
+    } else {
+        output
+    }
+}
+
+fn finished(result: Result<CommandOutput, AppError>) -> FinishedExecution {
+    let finished_at = Utc::now();
+    let output = match result {
+        Ok(output) => output,
+        Err(err) => CommandOutput {
+            exit_code: Err(err),
+            stdout: String::new(),
+            stderr: String::new(),
+            duration: Duration::ZERO,
+        },
+    };
+    let duration_ms = i64::try_from(output.duration.as_millis()).unwrap_or(i64::MAX);
+
+    match output.exit_code {
+        Ok(exit_code) => FinishedExecution {
+            status: ExecutionStatus::Completed,
+            exit_code: Some(exit_code),
+            stdout: storable(output.stdout),
+            stderr: storable(output.stderr),
+            error: None,
+            finished_at,
+            duration_ms,
+        },
+        Err(err) => FinishedExecution {
+            status: ExecutionStatus::Failed,
+            exit_code: None,
+            stdout: storable(output.stdout),
+            stderr: storable(output.stderr),
+            error: Some(err.to_string()),
+            finished_at,
+            duration_ms,
+        },
+    }
+}
+
+/// Records a new execution of the sandbox `module` and runs it in the
+/// background with the module's limits, renewing its lease while it runs.
+/// Returns the running record and a handle resolving to the finished one.
+pub async fn start_execution(
+    state: &AppState,
+    module: &Module,
+    input: CommandInput,
+) -> Result<(Execution, JoinHandle<Result<Execution, AppError>>), AppError> {
+    let loaded = state.runtime.load(&state.s3_client, module).await?;
+
+    let new_execution = NewExecution {
+        id: Uuid::new_v4(),
+        module_id: module.id.clone(),
+    };
+    let mut conn = state.db_pool.get()?;
+    let execution =
+        tokio::task::spawn_blocking(move || db_create_execution(&mut conn, &new_execution))
+            .await??;
+
+    let state = state.clone();
+    let execution_id = execution.id;
+    let handle = tokio::spawn(async move {
+        let run = state.runtime.run_command(&loaded, input);
+        tokio::pin!(run);
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        heartbeat.tick().await;
+
+        let result = loop {
+            tokio::select! {
+                result = &mut run => break result,
+                _ = heartbeat.tick() => renew_lease(&state, execution_id).await,
+            }
+        };
+        let finished = finished(result);
+
+        let recorded = async {
+            let mut conn = state.db_pool.get()?;
+            let execution = tokio::task::spawn_blocking(move || {
+                db_finish_execution(&mut conn, execution_id, &finished)
+            })
+            .await??;
+
+            Ok(execution)
+        }
+        .await;
+
+        if let Err(err) = &recorded {
+            error!(execution = %execution_id, "Failed to record execution result: {}", err);
+        }
+
+        recorded
+    });
+
+    Ok((execution, handle))
+}
+
+#[cfg(test)]
+#[path = "executions_services_tests.rs"]
+mod tests;
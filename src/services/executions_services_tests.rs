@@ -0,0 +1,68 @@
+use super::*;
+
+#[test]
+fn test_finished_records_command_output() {
+    let output = CommandOutput {
+        exit_code: Ok(3),
+        stdout: "hello".to_string(),
+        stderr: "warning".to_string(),
+        duration: Duration::from_millis(42),
+    };
+
+    let finished = finished(Ok(output));
+
+    assert_eq!(finished.status, ExecutionStatus::Completed);
+    assert_eq!(finished.exit_code, Some(3));
+    assert_eq!(finished.stdout, "hello");
+    assert_eq!(finished.stderr, "warning");
+    assert_eq!(finished.error, None);
+    assert_eq!(finished.duration_ms, 42);
+}
+
+#[test]
+fn test_finished_records_module_failures() {
+    let output = CommandOutput {
+        exit_code: Err(AppError::module_execution(
+            "Module 'runner' exceeded its timeout of 5000 ms",
+        )),
+        stdout: "partial".to_string(),
+        stderr: "still working".to_string(),
+        duration: Duration::from_millis(5000),
+    };
+
+    let finished = finished(Ok(output));
+
+    assert_eq!(finished.status, ExecutionStatus::Failed);
+    assert_eq!(finished.exit_code, None);
+    assert_eq!(finished.stdout, "partial");
+    assert_eq!(finished.stderr, "still working");
+    assert_eq!(
+        finished.error.as_deref(),
+        Some("Module execution failed: Module 'runner' exceeded its timeout of 5000 ms")
+    );
+    assert_eq!(finished.duration_ms, 5000);
+}
+
+#[test]
+fn test_finished_records_runs_that_never_started() {
+    let finished = finished(Err(AppError::conflict("Module 'runner' is disabled")));
+
+    assert_eq!(finished.status, ExecutionStatus::Failed);
+    assert_eq!(finished.stdout, "");
+    assert_eq!(finished.duration_ms, 0);
+}
+
+#[test]
+fn test_finished_replaces_nul_characters() {
+    let output = CommandOutput {
+        exit_code: Ok(0),
+        stdout: "he\0llo".to_string(),
+        stderr: "\0".to_string(),
+        duration: Duration::ZERO,
+    };
+
+    let finished = finished(Ok(output));
+
+    assert_eq!(finished.stdout, "he\u{FFFD}llo");
+    assert_eq!(finished.stderr, "\u{FFFD}");
+}
@@ -0,0 +1,167 @@
+use crate::{
+    db::DbConnection,
+    errors::AppError,
+    events::{EventType, LifecycleEvent},
+    models::{HookSubscription, Module, ModuleType},
+    schema::{hook_subscriptions, modules},
+    AppState,
+};
+use anyhow::Context;
+use diesel::prelude::*;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use tracing::{debug, error, warn};
+
+pub fn get_subscription(conn: &mut DbConnection, module_id: &str) -> QueryResult<HookSubscription> {
+    hook_subscriptions::table
+        .find(module_id)
+        .select(HookSubscription::as_select())
+        .first(conn)
+}
+
+pub fn set_subscription(
+    conn: &mut DbConnection,
+    subscription: &HookSubscription,
+) -> QueryResult<HookSubscription> {
+    diesel::insert_into(hook_subscriptions::table)
+        .values(subscription)
+        .on_conflict(hook_subscriptions::module_id)
+        .do_update()
+        .set(subscription)
+        .returning(HookSubscription::as_returning())
+        .get_result(conn)
+}
+
+pub fn delete_subscription(conn: &mut DbConnection, module_id: &str) -> QueryResult<usize> {
+    diesel::delete(hook_subscriptions::table.find(module_id)).execute(conn)
+}
+
+/// Returns the enabled hook modules subscribed to `event_type`.
+pub fn subscribers(conn: &mut DbConnection, event_type: EventType) -> QueryResult<Vec<Module>> {
+    hook_subscriptions::table
+        .inner_join(modules::table)
+        .filter(hook_subscriptions::events.contains(vec![event_type.as_str()]))
+        .filter(modules::is_enabled.eq(true))
+        .filter(modules::type_.eq(ModuleType::Hook))
+        .order(modules::id.asc())
+        .select(Module::as_select())
+        .load(conn)
+}
+
+/// Returns the enabled hook modules that must approve `event_type` changes.
+pub fn approvers(conn: &mut DbConnection, event_type: EventType) -> QueryResult<Vec<Module>> {
+    hook_subscriptions::table
+        .inner_join(modules::table)
+        .filter(hook_subscriptions::approvals.contains(vec![event_type.as_str()]))
+        .filter(modules::is_enabled.eq(true))
+        .filter(modules::type_.eq(ModuleType::Hook))
+        .order(modules::id.asc())
+        .select(Module::as_select())
+        .load(conn)
+}
+
+/// An approving hook's answer to a pending change.
+#[derive(Debug, Deserialize)]
+struct Verdict {
+    allow: bool,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// Asks every hook approving `event` whether the change it describes may be
+/// committed. Each hook gets the event with `"pending": true` and answers
+/// `{"allow": bool, "reason": "..."}`. The first rejection fails with
+/// `Forbidden`; a hook that fails or answers anything else fails the change
+/// too, so a broken approver cannot be bypassed.
+pub async fn approve(state: &AppState, event: LifecycleEvent) -> Result<(), AppError> {
+    let mut conn = state.db_pool.get()?;
+    let event_type = event.event_type();
+
+    let hooks = tokio::task::spawn_blocking(move || approvers(&mut conn, event_type)).await??;
+    if hooks.is_empty() {
+        return Ok(());
+    }
+
+    let mut payload =
+        serde_json::to_value(&event).context("Failed to serialize lifecycle event")?;
+    payload["pending"] = Value::Bool(true);
+
+    for module in &hooks {
+        let loaded = state.runtime.load(&state.s3_client, module).await?;
+        let output = state.runtime.hook_handle(&loaded, &payload).await?;
+        let verdict: Verdict = serde_json::from_value(output).map_err(|err| {
+            AppError::module_execution(format!(
+                "Hook '{}' returned an invalid verdict: {}",
+                module.id, err
+            ))
+        })?;
+
+        if !verdict.allow {
+            return Err(AppError::forbidden(format!(
+                "Hook '{}' rejected the {} event: {}",
+                module.id,
+                event_type.as_str(),
+                verdict.reason.as_deref().unwrap_or("no reason given")
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Invokes every hook subscribed to `event` and returns how many handled it.
+/// A failing hook is logged and does not stop the others from running. The
+/// change behind `event` is already committed, so a hook's output is only
+/// logged and cannot undo it; see [`approve`] for vetoing changes.
+pub async fn dispatch(state: &AppState, event: &LifecycleEvent) -> Result<usize, AppError> {
+    let mut conn = state.db_pool.get()?;
+    let event_type = event.event_type();
+
+    let hooks = tokio::task::spawn_blocking(move || subscribers(&mut conn, event_type)).await??;
+    if hooks.is_empty() {
+        return Ok(0);
+    }
+
+    let payload = serde_json::to_value(event).context("Failed to serialize lifecycle event")?;
+
+    let mut handled = 0;
+    for module in &hooks {
+        let result = match state.runtime.load(&state.s3_client, module).await {
+            Ok(loaded) => state.runtime.hook_handle(&loaded, &payload).await,
+            Err(err) => Err(err),
+        };
+
+        match result {
+            Ok(output) => {
+                debug!(module = %module.id, event = event_type.as_str(), %output, "Hook handled event");
+                handled += 1;
+            }
+            Err(err) => {
+                warn!(module = %module.id, event = event_type.as_str(), "Hook failed: {}", err);
+            }
+        }
+    }
+
+    Ok(handled)
+}
+
+/// Delivers events from `receiver` to subscribed hooks until the bus closes.
+pub async fn run_dispatcher(state: AppState, mut receiver: Receiver<LifecycleEvent>) {
+    loop {
+        match receiver.recv().await {
+            Ok(event) => {
+                if let Err(err) = dispatch(&state, &event).await {
+                    error!(
+                        event = event.event_type().as_str(),
+                        "Failed to dispatch event to hooks: {}", err
+                    );
+                }
+            }
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Hook dispatcher fell behind and skipped {} events", skipped);
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use crate::{
+    errors::AppError,
+    models::Definition,
+    s3,
+    services::{configuration_services, secrets_services},
+    utils::{stream_utils, template_utils},
+    AppState,
+};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Method, Url,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// Definitions of this type run without a module: their document describes
+/// an HTTP request that MCI performs itself.
+pub const HTTP_TYPE: &str = "http";
+
+/// Longest stretch of an upstream error body quoted back to the caller.
+const ERROR_BODY_LIMIT: usize = 512;
+
+/// Largest upstream response read into memory.
+const MAX_RESPONSE_BYTES: usize = 10 * 1024 * 1024;
+
+/// Everything but unreserved characters, so an argument cannot change the
+/// structure of the URL it is placed in.
+const ARGUMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// The `request` section of an `http` definition document. Every string may
+/// contain `{{ path }}` placeholders resolved against the call's
+/// `arguments`, `configuration` and `secrets`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RequestTemplate {
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub query: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: Option<Value>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+struct HttpDocument {
+    request: RequestTemplate,
+}
+
+/// A request template with all placeholders filled in.
+#[derive(Debug)]
+pub struct RenderedRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    pub query: Vec<(String, String)>,
+    pub body: Option<Value>,
+}
+
+pub fn parse_template(definition_id: &str, document: &[u8]) -> Result<RequestTemplate, AppError> {
+    let document: HttpDocument = serde_json::from_slice(document).map_err(|err| {
+        AppError::bad_request(format!(
+            "Definition '{}' does not describe an HTTP request: {}",
+            definition_id, err
+        ))
+    })?;
+
+    Ok(document.request)
+}
+
+/// Fills in `template` from `context`. Arguments placed in the URL are
+/// percent-encoded; configuration and secrets are trusted as is so they can
+/// carry whole base URLs. Query parameters and body fields that consist of a
+/// single placeholder are dropped when it is not set, which is how optional
+/// arguments are mapped.
+pub fn render(template: &RequestTemplate, context: &Value) -> Result<RenderedRequest, AppError> {
+    let method = Method::from_bytes(template.method.to_ascii_uppercase().as_bytes())
+        .map_err(|_| AppError::bad_request(format!("Invalid HTTP method '{}'", template.method)))?;
+
+    let url = template_utils::render_text(&template.url, context, |path, value| {
+        if path.starts_with("arguments.") {
+            utf8_percent_encode(&value, ARGUMENT).to_string()
+        } else {
+            value
+        }
+    })?;
+    let url = Url::parse(&url)
+        .map_err(|err| AppError::bad_request(format!("Invalid request URL: {}", err)))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(AppError::unsupported_scheme(url.scheme()));
+    }
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in &template.headers {
+        let value = template_utils::render_text(value, context, |_, value| value)?;
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| AppError::bad_request(format!("Invalid header name '{}'", name)))?;
+        let value = HeaderValue::from_str(&value)
+            .map_err(|_| AppError::bad_request(format!("Invalid value for header '{}'", name)))?;
+        headers.insert(name, value);
+    }
+
+    let mut query = Vec::new();
+    for (name, value) in &template.query {
+        let unset = template_utils::sole_placeholder(value)
+            .is_some_and(|path| template_utils::lookup(context, path).is_none());
+        if !unset {
+            let value = template_utils::render_text(value, context, |_, value| value)?;
+            query.push((name.clone(), value));
+        }
+    }
+
+    let body = template
+        .body
+        .as_ref()
+        .map(|body| template_utils::render_value(body, context))
+        .transpose()?;
+
+    Ok(RenderedRequest {
+        method,
+        url,
+        headers,
+        query,
+        body,
+    })
+}
+
+/// Performs the request described by the `http` definition `definition` with
+/// the shared HTTP client. JSON responses are returned as parsed values and
+/// anything else as text.
+pub async fn execute(
+    state: &AppState,
+    definition: &Definition,
+    arguments: &Value,
+) -> Result<Value, AppError> {
+    let document = s3::get_bytes(
+        &state.s3_client,
+        "definitions",
+        &definition.definition_object_key,
+    )
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found(format!(
+            "Document for definition '{}' is missing",
+            definition.id
+        ))
+    })?;
+    let template = parse_template(&definition.id, &document)?;
+
+    let configuration = configuration_services::get_configuration(
+        &state.s3_client,
+        "definitions",
+        &definition.configuration_object_key,
+    )
+    .await?;
+    let secrets = secrets_services::read_secrets(
+        &state.s3_client,
+        &state.keyring,
+        "definitions",
+        &definition.secrets_object_key,
+    )
+    .await?;
+
+    let context = json!({
+        "arguments": arguments,
+        "configuration": configuration,
+        "secrets": secrets,
+    });
+    let request = render(&template, &context)?;
+
+    let mut builder = state
+        .http_client
+        .request(request.method, request.url)
+        .headers(request.headers)
+        .query(&request.query);
+    if let Some(body) = &request.body {
+        builder = builder.json(body);
+    }
+
+    // Errors carry the URL, which may hold secrets, so it is stripped.
+    let response = builder.send().await.map_err(|err| {
+        AppError::upstream(format!(
+            "Request for definition '{}' failed: {}",
+            definition.id,
+            err.without_url()
+        ))
+    })?;
+    let status = response.status();
+    let body = stream_utils::read_response_limited(response, MAX_RESPONSE_BYTES)
+        .await
+        .map_err(|err| {
+            AppError::upstream(format!(
+                "Failed to read the response for definition '{}': {:#}",
+                definition.id, err
+            ))
+        })?;
+    let body = String::from_utf8_lossy(&body).into_owned();
+
+    if !status.is_success() {
+        return Err(AppError::upstream(format!(
+            "Definition '{}' received status {}: {}",
+            definition.id,
+            status,
+            body.chars().take(ERROR_BODY_LIMIT).collect::<String>()
+        )));
+    }
+
+    Ok(serde_json::from_str(&body).unwrap_or(Value::String(body)))
+}
+
+#[cfg(test)]
+#[path = "http_definitions_services_tests.rs"]
+mod tests;
@@ -0,0 +1,90 @@
+use super::*;
+
+fn context() -> Value {
+    json!({
+        "arguments": { "user": "jane doe/admin", "limit": 5 },
+        "configuration": { "base_url": "https://api.example.com/v1" },
+        "secrets": { "token": "s3cr3t" }
+    })
+}
+
+fn template() -> RequestTemplate {
+    parse_template(
+        "acme::users",
+        json!({
+            "name": "Users",
+            "request": {
+                "method": "post",
+                "url": "{{configuration.base_url}}/users/{{arguments.user}}",
+                "headers": { "Authorization": "Bearer {{secrets.token}}" },
+                "query": { "limit": "{{arguments.limit}}", "cursor": "{{arguments.cursor}}" },
+                "body": { "user": "{{arguments.user}}", "limit": "{{arguments.limit}}" }
+            }
+        })
+        .to_string()
+        .as_bytes(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_parse_template_defaults() {
+    let template = parse_template(
+        "acme::status",
+        br#"{"request":{"url":"https://status.example.com"}}"#,
+    )
+    .unwrap();
+
+    assert_eq!(template.method, "GET");
+    assert!(template.headers.is_empty());
+    assert!(template.query.is_empty());
+    assert_eq!(template.body, None);
+}
+
+#[test]
+fn test_parse_template_requires_request() {
+    let err = parse_template("acme::status", br#"{"name":"Status"}"#).unwrap_err();
+
+    assert!(matches!(err, AppError::BadRequest(msg) if msg.contains("'acme::status'")));
+}
+
+#[test]
+fn test_render() {
+    let request = render(&template(), &context()).unwrap();
+
+    assert_eq!(request.method, Method::POST);
+    assert_eq!(
+        request.url.as_str(),
+        "https://api.example.com/v1/users/jane%20doe%2Fadmin"
+    );
+    assert_eq!(request.headers["authorization"], "Bearer s3cr3t");
+    assert_eq!(request.query, vec![("limit".to_string(), "5".to_string())]);
+    assert_eq!(
+        request.body,
+        Some(json!({ "user": "jane doe/admin", "limit": 5 }))
+    );
+}
+
+#[test]
+fn test_render_rejects_invalid_requests() {
+    let mut invalid_method = template();
+    invalid_method.method = "GET NOW".to_string();
+    assert!(matches!(
+        render(&invalid_method, &context()),
+        Err(AppError::BadRequest(_))
+    ));
+
+    let mut unsupported = template();
+    unsupported.url = "file:///etc/passwd".to_string();
+    assert!(matches!(
+        render(&unsupported, &context()),
+        Err(AppError::UnsupportedScheme(_))
+    ));
+
+    let mut missing = template();
+    missing.url = "https://api.example.com/{{arguments.cursor}}".to_string();
+    assert!(matches!(
+        render(&missing, &context()),
+        Err(AppError::BadRequest(_))
+    ));
+}
@@ -0,0 +1,72 @@
+use crate::{
+    db::DbConnection,
+    models::{Definition, InterceptorBinding, Module, ModuleType},
+    schema::{interceptor_bindings, modules},
+};
+use diesel::prelude::*;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// What an interceptor decided about the request or response it was shown.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum Decision {
+    /// Pass the payload on, optionally replacing the arguments (request
+    /// phase) or the result (response phase).
+    Continue {
+        #[serde(default)]
+        arguments: Option<Value>,
+        #[serde(default)]
+        result: Option<Value>,
+    },
+    Reject {
+        #[serde(default)]
+        reason: Option<String>,
+    },
+}
+
+pub fn get_binding(conn: &mut DbConnection, module_id: &str) -> QueryResult<InterceptorBinding> {
+    interceptor_bindings::table
+        .find(module_id)
+        .select(InterceptorBinding::as_select())
+        .first(conn)
+}
+
+pub fn set_binding(
+    conn: &mut DbConnection,
+    binding: &InterceptorBinding,
+) -> QueryResult<InterceptorBinding> {
+    diesel::insert_into(interceptor_bindings::table)
+        .values(binding)
+        .on_conflict(interceptor_bindings::module_id)
+        .do_update()
+        .set(binding)
+        .returning(InterceptorBinding::as_returning())
+        .get_result(conn)
+}
+
+pub fn delete_binding(conn: &mut DbConnection, module_id: &str) -> QueryResult<usize> {
+    diesel::delete(interceptor_bindings::table.find(module_id)).execute(conn)
+}
+
+/// Returns the enabled interceptor modules that apply to `definition`, in the
+/// order their request hooks run.
+pub fn resolve_chain(conn: &mut DbConnection, definition: &Definition) -> QueryResult<Vec<Module>> {
+    let bound: Vec<(InterceptorBinding, Module)> = interceptor_bindings::table
+        .inner_join(modules::table)
+        .filter(modules::is_enabled.eq(true))
+        .filter(modules::type_.eq(ModuleType::Interceptor))
+        .order((interceptor_bindings::priority.asc(), modules::id.asc()))
+        .select((InterceptorBinding::as_select(), Module::as_select()))
+        .load(conn)?;
+
+    Ok(bound
+        .into_iter()
+        .filter(|(binding, _)| binding.applies_to(definition))
+        .map(|(_, module)| module)
+        .collect())
+}
+
+#[cfg(test)]
+#[path = "interceptors_services_tests.rs"]
+mod tests;
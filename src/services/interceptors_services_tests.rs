@@ -0,0 +1,54 @@
+use super::*;
+use serde_json::json;
+
+#[test]
+fn test_decision_continue_without_changes() {
+    let decision: Decision = serde_json::from_value(json!({ "action": "continue" })).unwrap();
+
+    assert_eq!(
+        decision,
+        Decision::Continue {
+            arguments: None,
+            result: None
+        }
+    );
+}
+
+#[test]
+fn test_decision_continue_with_rewritten_arguments() {
+    let decision: Decision = serde_json::from_value(json!({
+        "action": "continue",
+        "arguments": { "email": "[redacted]" }
+    }))
+    .unwrap();
+
+    assert_eq!(
+        decision,
+        Decision::Continue {
+            arguments: Some(json!({ "email": "[redacted]" })),
+            result: None
+        }
+    );
+}
+
+#[test]
+fn test_decision_reject() {
+    let decision: Decision = serde_json::from_value(json!({
+        "action": "reject",
+        "reason": "contains PII"
+    }))
+    .unwrap();
+
+    assert_eq!(
+        decision,
+        Decision::Reject {
+            reason: Some("contains PII".to_string())
+        }
+    );
+}
+
+#[test]
+fn test_decision_unknown_action() {
+    assert!(serde_json::from_value::<Decision>(json!({ "action": "retry" })).is_err());
+    assert!(serde_json::from_value::<Decision>(json!({})).is_err());
+}
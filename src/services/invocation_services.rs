@@ -0,0 +1,216 @@
+use crate::{
+    errors::AppError,
+    models::Definition,
+    runtime::LoadedModule,
+    services::{
+        definitions_services, http_definitions_services,
+        interceptors_services::{self, Decision},
+        languages_services, proxies_services,
+    },
+    AppState,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// What a proxy or language module reports back after running a call.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionResponse {
+    Result(Value),
+    Error(String),
+}
+
+/// A step of an invocation that is about to run. `step` counts from zero up
+/// to `total`, which is known once the interceptor chain is resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    pub step: u64,
+    pub total: u64,
+    pub message: String,
+}
+
+/// Invokes a definition with `arguments`, passing the request and the result
+/// through the interceptor chain that applies to it.
+///
+/// Request interceptors run in priority order and response interceptors in
+/// reverse, so the first interceptor to see a request is the last to see its
+/// result. Any interceptor can rewrite the payload or reject the call.
+pub async fn invoke_definition(
+    state: &AppState,
+    definition_id: &str,
+    arguments: Value,
+) -> Result<Value, AppError> {
+    invoke_definition_with_progress(state, definition_id, arguments, &|_| {}).await
+}
+
+/// Like [`invoke_definition`], reporting each step to `report` before it runs.
+pub async fn invoke_definition_with_progress(
+    state: &AppState,
+    definition_id: &str,
+    arguments: Value,
+    report: &(dyn Fn(Progress) + Send + Sync),
+) -> Result<Value, AppError> {
+    let mut conn = state.db_pool.get()?;
+    let definition_id = definition_id.to_string();
+
+    let (definition, chain) = tokio::task::spawn_blocking(move || {
+        let definition = definitions_services::get_definition(&mut conn, &definition_id)?;
+        let chain = interceptors_services::resolve_chain(&mut conn, &definition)?;
+        Ok::<_, AppError>((definition, chain))
+    })
+    .await??;
+
+    if !definition.is_enabled {
+        return Err(AppError::conflict(format!(
+            "Definition '{}' is disabled",
+            definition.id
+        )));
+    }
+
+    let mut interceptors = Vec::with_capacity(chain.len());
+    for module in &chain {
+        interceptors.push(state.runtime.load(&state.s3_client, module).await?);
+    }
+
+    let context = definition_context(&definition);
+    let total = interceptors.len() as u64 * 2 + 1;
+    let mut step = 0;
+    let mut advance = |message: String| {
+        report(Progress {
+            step,
+            total,
+            message,
+        });
+        step += 1;
+    };
+
+    let mut arguments = arguments;
+    for interceptor in &interceptors {
+        advance(format!("Running request interceptor '{}'", interceptor.id));
+        let request = json!({ "definition": context, "arguments": arguments });
+        let output = state
+            .runtime
+            .intercept_request(interceptor, &request)
+            .await?;
+
+        if let Some(rewritten) = decide(interceptor, output)?.arguments {
+            arguments = rewritten;
+        }
+    }
+
+    advance(format!("Invoking definition '{}'", definition.id));
+    let mut result = execute(state, &definition, &arguments).await?;
+
+    for interceptor in interceptors.iter().rev() {
+        advance(format!("Running response interceptor '{}'", interceptor.id));
+        let response = json!({ "definition": context, "arguments": arguments, "result": result });
+        let output = state
+            .runtime
+            .intercept_response(interceptor, &response)
+            .await?;
+
+        if let Some(rewritten) = decide(interceptor, output)?.result {
+            result = rewritten;
+        }
+    }
+
+    Ok(result)
+}
+
+/// Payload replacements requested by an interceptor that let the call
+/// continue.
+struct Continuation {
+    arguments: Option<Value>,
+    result: Option<Value>,
+}
+
+fn decide(interceptor: &LoadedModule, output: Value) -> Result<Continuation, AppError> {
+    let decision: Decision = serde_json::from_value(output).map_err(|err| {
+        AppError::module_execution(format!(
+            "Interceptor '{}' returned an invalid decision: {}",
+            interceptor.id, err
+        ))
+    })?;
+
+    match decision {
+        Decision::Continue { arguments, result } => Ok(Continuation { arguments, result }),
+        Decision::Reject { reason } => Err(AppError::forbidden(format!(
+            "Rejected by interceptor '{}': {}",
+            interceptor.id,
+            reason.as_deref().unwrap_or("no reason given")
+        ))),
+    }
+}
+
+/// Identifies the invoked definition to the modules taking part in a call.
+pub fn definition_context(definition: &Definition) -> Value {
+    json!({ "id": definition.id, "type": definition.type_ })
+}
+
+async fn execute(
+    state: &AppState,
+    definition: &Definition,
+    arguments: &Value,
+) -> Result<Value, AppError> {
+    let mut conn = state.db_pool.get()?;
+    let definition_id = definition.id.clone();
+
+    let proxy = tokio::task::spawn_blocking(move || {
+        proxies_services::find_proxy(&mut conn, &definition_id)
+    })
+    .await??;
+
+    if let Some((binding, module)) = proxy {
+        return proxies_services::forward(
+            state,
+            definition,
+            &module,
+            &binding.upstream_url,
+            arguments,
+        )
+        .await;
+    }
+
+    if definition.type_ == http_definitions_services::HTTP_TYPE {
+        return http_definitions_services::execute(state, definition, arguments).await;
+    }
+
+    let mut conn = state.db_pool.get()?;
+    let definition_type = definition.type_.clone();
+
+    let language = tokio::task::spawn_blocking(move || {
+        languages_services::find_language(&mut conn, &definition_type)
+    })
+    .await??;
+
+    if let Some(module) = language {
+        return languages_services::evaluate(state, definition, &module, arguments).await;
+    }
+
+    Err(AppError::bad_request(format!(
+        "Definitions of type '{}' cannot be invoked",
+        definition.type_
+    )))
+}
+
+/// Unwraps the [`ExecutionResponse`] returned by `module_id`.
+pub fn into_result(module_id: &str, output: Value) -> Result<Value, AppError> {
+    let response: ExecutionResponse = serde_json::from_value(output).map_err(|err| {
+        AppError::module_execution(format!(
+            "Module '{}' returned an invalid response: {}",
+            module_id, err
+        ))
+    })?;
+
+    match response {
+        ExecutionResponse::Result(result) => Ok(result),
+        ExecutionResponse::Error(message) => Err(AppError::module_execution(format!(
+            "Module '{}' reported an error: {}",
+            module_id, message
+        ))),
+    }
+}
+
+#[cfg(test)]
+#[path = "invocation_services_tests.rs"]
+mod tests;
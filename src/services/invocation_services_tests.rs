@@ -0,0 +1,28 @@
+use super::*;
+
+#[test]
+fn test_into_result_unwraps_result() {
+    let result = into_result("acme::gateway", json!({ "result": { "items": [] } })).unwrap();
+
+    assert_eq!(result, json!({ "items": [] }));
+}
+
+#[test]
+fn test_into_result_reports_module_errors() {
+    let err =
+        into_result("acme::gateway", json!({ "error": "upstream returned 502" })).unwrap_err();
+
+    assert!(matches!(
+        err,
+        AppError::ModuleExecution(msg)
+            if msg == "Module 'acme::gateway' reported an error: upstream returned 502"
+    ));
+}
+
+#[test]
+fn test_into_result_rejects_unknown_shapes() {
+    assert!(matches!(
+        into_result("acme::gateway", json!({ "items": [] })),
+        Err(AppError::ModuleExecution(_))
+    ));
+}
@@ -0,0 +1,113 @@
+use crate::{
+    db::DbConnection,
+    errors::AppError,
+    models::{Definition, LanguageBinding, Module},
+    s3,
+    schema::{language_bindings, modules},
+    services::{configuration_services, invocation_services, secrets_services},
+    AppState,
+};
+use diesel::prelude::*;
+use serde_json::{json, Value};
+
+pub fn list_bindings(conn: &mut DbConnection) -> QueryResult<Vec<LanguageBinding>> {
+    language_bindings::table
+        .order(language_bindings::definition_type.asc())
+        .select(LanguageBinding::as_select())
+        .load(conn)
+}
+
+pub fn get_binding(conn: &mut DbConnection, definition_type: &str) -> QueryResult<LanguageBinding> {
+    language_bindings::table
+        .find(definition_type)
+        .select(LanguageBinding::as_select())
+        .first(conn)
+}
+
+pub fn set_binding(
+    conn: &mut DbConnection,
+    binding: &LanguageBinding,
+) -> QueryResult<LanguageBinding> {
+    diesel::insert_into(language_bindings::table)
+        .values(binding)
+        .on_conflict(language_bindings::definition_type)
+        .do_update()
+        .set(binding)
+        .returning(LanguageBinding::as_returning())
+        .get_result(conn)
+}
+
+pub fn delete_binding(conn: &mut DbConnection, definition_type: &str) -> QueryResult<usize> {
+    diesel::delete(language_bindings::table.find(definition_type)).execute(conn)
+}
+
+/// Returns the language module bound to `definition_type`, if any.
+pub fn find_language(
+    conn: &mut DbConnection,
+    definition_type: &str,
+) -> QueryResult<Option<Module>> {
+    language_bindings::table
+        .inner_join(modules::table)
+        .filter(language_bindings::definition_type.eq(definition_type))
+        .select(Module::as_select())
+        .first(conn)
+        .optional()
+}
+
+/// Runs `definition` by feeding the `source` of its document, together with
+/// its configuration and secrets, to the language module `module`.
+pub async fn evaluate(
+    state: &AppState,
+    definition: &Definition,
+    module: &Module,
+    arguments: &Value,
+) -> Result<Value, AppError> {
+    let loaded = state.runtime.load(&state.s3_client, module).await?;
+
+    let document = s3::get_bytes(
+        &state.s3_client,
+        "definitions",
+        &definition.definition_object_key,
+    )
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found(format!(
+            "Document for definition '{}' is missing",
+            definition.id
+        ))
+    })?;
+    let source = serde_json::from_slice::<Value>(&document)
+        .ok()
+        .and_then(|document| document.get("source")?.as_str().map(str::to_string))
+        .ok_or_else(|| {
+            AppError::bad_request(format!(
+                "Definition '{}' has no source to evaluate",
+                definition.id
+            ))
+        })?;
+
+    let configuration = configuration_services::get_configuration(
+        &state.s3_client,
+        "definitions",
+        &definition.configuration_object_key,
+    )
+    .await?;
+    let secrets = secrets_services::read_secrets(
+        &state.s3_client,
+        &state.keyring,
+        "definitions",
+        &definition.secrets_object_key,
+    )
+    .await?;
+
+    let request = json!({
+        "definition": invocation_services::definition_context(definition),
+        "source": source,
+        "arguments": arguments,
+        "configuration": configuration,
+        "secrets": secrets,
+    });
+    let output = state.runtime.language_eval(&loaded, &request).await?;
+
+    invocation_services::into_result(&module.id, output)
+}
@@ -1,2 +1,12 @@
+pub mod configuration_services;
 pub mod definitions_services;
+pub mod documents_services;
+pub mod executions_services;
+pub mod hooks_services;
+pub mod http_definitions_services;
+pub mod interceptors_services;
+pub mod invocation_services;
+pub mod languages_services;
 pub mod modules_services;
+pub mod proxies_services;
+pub mod secrets_services;
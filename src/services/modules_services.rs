@@ -1,19 +1,21 @@
 use crate::{
-    db::DbConnection,
-    models::{Module, ModuleType, NewModule, UpdateModule},
+    db::{self, DbConnection},
+    errors::AppError,
+    events::LifecycleEvent,
+    models::{self, Capabilities, Module, ModuleType, NewModule, UpdateModule, UpgradeOptions},
     s3,
     schema::modules,
-    utils::{source_utils, stream_utils},
+    services::{configuration_services, secrets_services},
+    utils::{digest_utils, source_utils, stream_utils, wasm_utils},
 };
 use anyhow::{Context, Result};
 use aws_sdk_s3;
 use aws_smithy_types::byte_stream::ByteStream;
 use diesel::{associations::HasTable, prelude::*};
-use futures::stream::TryStreamExt;
-use http_body_util::StreamBody;
 use reqwest;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use serde_json::Value;
+use std::{collections::HashMap, future::Future, path::Path};
 use tokio::fs;
 
 fn ensure_wasm_file(file_url: &str) -> Result<()> {
@@ -23,6 +25,102 @@ fn ensure_wasm_file(file_url: &str) -> Result<()> {
     Ok(())
 }
 
+fn ensure_valid_capabilities(capabilities: &Capabilities) -> Result<()> {
+    for dir in &capabilities.preopened_dirs {
+        if dir.host_path.is_empty() || !dir.guest_path.starts_with('/') {
+            return Err(AppError::bad_request(format!(
+                "Preopened directory '{}' must map to an absolute guest path",
+                dir.host_path
+            ))
+            .into());
+        }
+    }
+    if capabilities
+        .allowed_hosts
+        .iter()
+        .any(|host| host.is_empty())
+    {
+        return Err(AppError::bad_request("Allowed hosts must not be empty").into());
+    }
+    if capabilities.environment.iter().any(|name| name.is_empty()) {
+        return Err(AppError::bad_request("Environment variable names must not be empty").into());
+    }
+    Ok(())
+}
+
+/// Fails unless every capability `requested` by a manifest has been approved.
+fn ensure_capabilities_approved(requested: &Capabilities, approved: &Capabilities) -> Result<()> {
+    let ungranted = requested.ungranted(approved);
+    if !ungranted.is_empty() {
+        return Err(AppError::bad_request(format!(
+            "Module requests capabilities that were not approved: {}",
+            ungranted.join(", ")
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+impl s3::UploadCheck for wasm_utils::ModuleValidator {
+    fn update(&mut self, chunk: &[u8]) -> Result<()> {
+        Ok(wasm_utils::ModuleValidator::update(self, chunk)?)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        Ok(wasm_utils::ModuleValidator::finish(*self)?)
+    }
+}
+
+/// Stores the module file once it matches `digest` and validates as a
+/// WebAssembly module of `module_type`. Both are checked while the file
+/// streams to S3, so it is never held in memory whole. Returns the digest of the
+/// stored file, which is computed on the way when `digest` is not given.
+async fn store_module_file(
+    s3_client: &aws_sdk_s3::Client,
+    upload_options: &s3::UploadOptions,
+    key: &str,
+    body: ByteStream,
+    digest: Option<&str>,
+    module_type: ModuleType,
+    capabilities: &Capabilities,
+) -> Result<String> {
+    let mut checks: Vec<Box<dyn s3::UploadCheck>> = Vec::new();
+    let recorded = match digest {
+        Some(digest) => {
+            checks.push(Box::new(digest_utils::DigestVerifier::new(digest)?));
+            None
+        }
+        None => {
+            let recorder = digest_utils::DigestRecorder::default();
+            let recorded = recorder.digest();
+            checks.push(Box::new(recorder));
+            Some(recorded)
+        }
+    };
+    checks.push(Box::new(wasm_utils::ModuleValidator::new(
+        module_type,
+        capabilities,
+    )));
+
+    s3::put_checked(
+        s3_client,
+        "modules",
+        key,
+        body,
+        checks,
+        HashMap::new(),
+        upload_options,
+    )
+    .await?;
+
+    match (digest, recorded) {
+        (Some(digest), _) => Ok(digest.to_string()),
+        (None, recorded) => recorded
+            .and_then(|recorded| recorded.get().cloned())
+            .context("Module digest was not recorded"),
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub enum SortBy {
     Id,
@@ -54,8 +152,19 @@ pub struct ModulePayload {
     pub r#type: ModuleType,
     pub description: String,
     pub file_url: String,
-    pub digest: String,
+    #[serde(default)]
+    pub digest: Option<String>,
+    /// Accepts the file without a `digest`; the server records the digest of
+    /// what it downloads instead.
+    #[serde(default)]
+    pub allow_unpinned: bool,
     pub source_url: Option<String>,
+    #[serde(default)]
+    pub default_configuration: Option<Value>,
+    #[serde(default)]
+    pub configuration_schema: Option<Value>,
+    #[serde(default)]
+    pub capabilities: Capabilities,
 }
 
 async fn fetch_module_from_path(path: &Path) -> Result<ModulePayload> {
@@ -119,16 +228,44 @@ pub fn get_module(conn: &mut DbConnection, module_id: &str) -> QueryResult<Modul
         .first(conn)
 }
 
-pub fn delete_module(conn: &mut DbConnection, module_id: &str) -> QueryResult<usize> {
-    diesel::delete(modules::table.find(module_id)).execute(conn)
+/// Deletes the module row and returns it, so its stored objects can be
+/// removed afterwards.
+pub fn delete_module(conn: &mut DbConnection, module_id: &str) -> QueryResult<Option<Module>> {
+    diesel::delete(modules::table.find(module_id))
+        .returning(Module::as_returning())
+        .get_result(conn)
+        .optional()
 }
 
+/// Removes the module file, configuration and secrets stored for a deleted
+/// module.
+pub async fn delete_objects(s3_client: &aws_sdk_s3::Client, module: &Module) -> s3::DeletionReport {
+    let mut keys = vec![module.module_object_key.clone()];
+    keys.extend(configuration_services::stored_keys(
+        &module.configuration_object_key,
+    ));
+    keys.push(secrets_services::secrets_key(&module.secrets_object_key));
+
+    s3::delete_all(s3_client, "modules", &keys).await
+}
+
+/// Applies `update_module` and returns the module as it was before and after
+/// the change, so callers can tell which fields actually changed.
 pub fn update_module(
     conn: &mut DbConnection,
     module_id: &str,
     update_module: &UpdateModule,
-) -> QueryResult<Module> {
-    db_update_module(conn, module_id, update_module)
+) -> QueryResult<(Module, Module)> {
+    conn.transaction(|conn| {
+        let previous = modules::table
+            .find(module_id)
+            .for_update()
+            .select(Module::as_select())
+            .first(conn)?;
+        let updated = db_update_module(conn, module_id, update_module)?;
+
+        Ok((previous, updated))
+    })
 }
 
 pub fn list_modules(conn: &mut DbConnection, filter: &ModuleFilter) -> QueryResult<Vec<Module>> {
@@ -171,39 +308,60 @@ pub fn list_modules(conn: &mut DbConnection, filter: &ModuleFilter) -> QueryResu
     query.select(Module::as_select()).load(conn)
 }
 
-pub async fn create_module(
+/// Installs a module. `approve` is asked about the inserted module before it
+/// is committed; a rejected module is rolled back and its stored objects
+/// removed.
+pub async fn create_module<F, Fut>(
     conn: &mut DbConnection,
     http_client: &reqwest::Client,
     s3_client: &aws_sdk_s3::Client,
+    upload_options: &s3::UploadOptions,
     payload: &ModulePayload,
-) -> Result<Module> {
+    approve: F,
+) -> Result<Module>
+where
+    F: FnOnce(LifecycleEvent) -> Fut,
+    Fut: Future<Output = Result<(), AppError>>,
+{
+    models::ensure_valid_id(&payload.id)?;
     if get_module(conn, &payload.id).is_ok() {
         anyhow::bail!("Conflict: Module with ID '{}' already exists", payload.id);
     }
 
     ensure_wasm_file(&payload.file_url)?;
+    ensure_valid_capabilities(&payload.capabilities)?;
+    let pinned_digest =
+        digest_utils::require_pin(payload.digest.as_deref(), payload.allow_unpinned)?;
+    configuration_services::validate_manifest_configuration(
+        payload.default_configuration.as_ref(),
+        payload.configuration_schema.as_ref(),
+    )?;
+
     let module_source = source_utils::Source::parse(&payload.file_url)?;
     let obj_key = format!("{}.wasm", payload.id);
 
-    let body = match &module_source {
-        source_utils::Source::Http(url) => {
-            let response = stream_utils::stream_content_from_url(http_client, url)
-                .await
-                .context("Failed to fetch module file from URL")?;
-
-            let stream = response.bytes_stream();
-            let frames = stream.map_ok(hyper::body::Frame::data);
-            let body = StreamBody::new(frames);
-            ByteStream::from_body_1_x(body)
-        }
-        source_utils::Source::File(path) => stream_utils::stream_content_from_path(path)
-            .await
-            .context("Failed to read module file from path")?,
-    };
+    let body = open_module_file(http_client, &module_source).await?;
+    let digest = store_module_file(
+        s3_client,
+        upload_options,
+        &obj_key,
+        body,
+        pinned_digest,
+        payload.r#type,
+        &payload.capabilities,
+    )
+    .await
+    .context("Failed to upload module to S3")?;
 
-    s3::put_stream(s3_client, "modules", &obj_key, body, Some(&payload.digest))
-        .await
-        .context("Failed to upload module to S3")?;
+    configuration_services::store_manifest_configuration(
+        s3_client,
+        "modules",
+        &payload.id,
+        payload.default_configuration.as_ref(),
+        payload.configuration_schema.as_ref(),
+        true,
+    )
+    .await?;
 
     let new_module = NewModule {
         id: payload.id.clone(),
@@ -213,37 +371,126 @@ pub async fn create_module(
         module_object_key: obj_key.clone(),
         configuration_object_key: payload.id.clone(),
         secrets_object_key: payload.id.clone(),
-        digest: payload.digest.clone(),
+        digest,
         source_url: payload.source_url.clone(),
+        is_pinned: pinned_digest.is_some(),
+        capabilities: payload.capabilities.clone(),
     };
 
-    db_create_module(conn, &new_module).context("Failed to save module to database")
+    db::transaction_checked(
+        conn,
+        |conn| db_create_module(conn, &new_module).context("Failed to save module to database"),
+        |module| {
+            let module = module.clone();
+            async move {
+                let approval = approve(LifecycleEvent::ModuleCreated {
+                    module: module.clone(),
+                })
+                .await;
+                if approval.is_err() {
+                    delete_objects(s3_client, &module).await;
+                }
+                approval
+            }
+        },
+    )
+    .await
 }
 
-pub async fn create_module_from_registry(
+/// Installs a module from a registry manifest. `approved_capabilities` is the
+/// operator's grant; the install fails if the manifest asks for more.
+pub async fn create_module_from_registry<F, Fut>(
     conn: &mut DbConnection,
     http_client: &reqwest::Client,
     s3_client: &aws_sdk_s3::Client,
+    upload_options: &s3::UploadOptions,
     source_input: &str,
-) -> Result<Module> {
+    approved_capabilities: &Capabilities,
+    approve: F,
+) -> Result<Module>
+where
+    F: FnOnce(LifecycleEvent) -> Fut,
+    Fut: Future<Output = Result<(), AppError>>,
+{
     let source = source_utils::Source::parse(source_input)?;
     let mut payload = fetch_module(http_client, &source)
         .await
         .context("Failed to load module metadata")?;
 
+    ensure_capabilities_approved(&payload.capabilities, approved_capabilities)?;
+
     if payload.source_url.is_none() {
         payload.source_url = Some(source_input.to_string());
     }
 
-    create_module(conn, http_client, s3_client, &payload).await
+    create_module(
+        conn,
+        http_client,
+        s3_client,
+        upload_options,
+        &payload,
+        approve,
+    )
+    .await
+}
+
+async fn open_module_file(
+    http_client: &reqwest::Client,
+    source: &source_utils::Source,
+) -> Result<ByteStream> {
+    match source {
+        source_utils::Source::Http(url) => {
+            let response = stream_utils::stream_content_from_url(http_client, url)
+                .await
+                .context("Failed to fetch module file from URL")?;
+
+            Ok(stream_utils::byte_stream_from_response(response))
+        }
+        source_utils::Source::File(path) => stream_utils::stream_content_from_path(path)
+            .await
+            .context("Failed to read module file from path"),
+    }
+}
+
+/// Reads the module file at `source` once to learn its digest without
+/// storing it.
+async fn source_digest(
+    http_client: &reqwest::Client,
+    source: &source_utils::Source,
+) -> Result<String> {
+    let mut body = open_module_file(http_client, source).await?;
+    let mut recorder = digest_utils::DigestRecorder::default();
+    while let Some(chunk) = body
+        .try_next()
+        .await
+        .context("Failed to read module file")?
+    {
+        recorder.update(&chunk);
+    }
+
+    Ok(recorder.finish())
 }
 
-pub async fn update_module_from_source(
+/// Upgrades the module from its recorded source. Returns the module and
+/// whether anything changed; a source still serving the installed digest
+/// leaves it untouched. A stored configuration the new schema rejects fails the
+/// upgrade unless `options.reset_configuration` allows replacing it, and
+/// `approve` is asked about the upgraded module before anything is stored.
+/// Without a published digest the file is read twice: once to learn the
+/// digest that is approved and once, pinned to it, to store it.
+pub async fn update_module_from_source<F, Fut>(
     conn: &mut DbConnection,
     http_client: &reqwest::Client,
     s3_client: &aws_sdk_s3::Client,
+    upload_options: &s3::UploadOptions,
     module_id: &str,
-) -> Result<Module> {
+    options: UpgradeOptions,
+    approve: F,
+) -> Result<(Module, bool)>
+where
+    F: FnOnce(LifecycleEvent) -> Fut,
+    Fut: Future<Output = Result<(), AppError>>,
+{
     let module =
         get_module(conn, module_id).context("Failed to fetch current module from database")?;
     let source_url_str = module
@@ -255,45 +502,90 @@ pub async fn update_module_from_source(
         .await
         .context("Failed to fetch updated module metadata from source")?;
 
-    if module.digest == remote_payload.digest {
-        return Ok(module);
+    let pinned_digest = digest_utils::require_upgrade_pin(
+        remote_payload.digest.as_deref(),
+        remote_payload.allow_unpinned,
+        module.is_pinned,
+        options.allow_unpinned,
+    )?;
+    if pinned_digest == Some(module.digest.as_str()) {
+        return Ok((module, false));
     }
 
     ensure_wasm_file(&remote_payload.file_url)?;
-    let module_file_source = source_utils::Source::parse(&remote_payload.file_url)?;
-    let obj_key = format!("{}.wasm", module.id);
-    let body = match &module_file_source {
-        source_utils::Source::Http(url) => {
-            let response = stream_utils::stream_content_from_url(http_client, url)
-                .await
-                .context("Failed to fetch updated module file from URL")?;
-            let stream = response.bytes_stream();
-            let frames = stream.map_ok(hyper::body::Frame::data);
-            let body = StreamBody::new(frames);
+    // Upgrades may narrow the grant but never widen it without a reinstall.
+    ensure_capabilities_approved(&remote_payload.capabilities, &module.capabilities)?;
+    configuration_services::validate_manifest_configuration(
+        remote_payload.default_configuration.as_ref(),
+        remote_payload.configuration_schema.as_ref(),
+    )?;
+    if !options.reset_configuration {
+        configuration_services::ensure_configuration_fits(
+            s3_client,
+            "modules",
+            &module.configuration_object_key,
+            remote_payload.configuration_schema.as_ref(),
+        )
+        .await?;
+    }
 
-            ByteStream::from_body_1_x(body)
-        }
-        source_utils::Source::File(path) => stream_utils::stream_content_from_path(path)
-            .await
-            .context("Failed to read updated module file from path")?,
+    let module_file_source = source_utils::Source::parse(&remote_payload.file_url)?;
+    let digest = match pinned_digest {
+        Some(digest) => digest.to_string(),
+        None => source_digest(http_client, &module_file_source).await?,
     };
+    // An unpinned source may still serve the file that is installed.
+    if digest == module.digest {
+        return Ok((module, false));
+    }
+
+    approve(LifecycleEvent::ModuleUpgraded {
+        module: Module {
+            digest: digest.clone(),
+            is_pinned: pinned_digest.is_some(),
+            name: remote_payload.name.clone(),
+            description: remote_payload.description.clone(),
+            capabilities: remote_payload.capabilities.clone(),
+            ..module.clone()
+        },
+    })
+    .await?;
 
-    s3::put_stream(
+    let obj_key = format!("{}.wasm", module.id);
+    let body = open_module_file(http_client, &module_file_source).await?;
+    store_module_file(
         s3_client,
-        "modules",
+        upload_options,
         &obj_key,
         body,
-        Some(&remote_payload.digest),
+        Some(&digest),
+        module.type_,
+        &remote_payload.capabilities,
     )
     .await
     .context("Failed to upload updated module to S3")?;
 
+    configuration_services::store_manifest_configuration(
+        s3_client,
+        "modules",
+        &module.configuration_object_key,
+        remote_payload.default_configuration.as_ref(),
+        remote_payload.configuration_schema.as_ref(),
+        options.reset_configuration,
+    )
+    .await?;
+
     let update_data = UpdateModule {
-        digest: Some(remote_payload.digest),
+        digest: Some(digest),
+        is_pinned: Some(pinned_digest.is_some()),
         name: Some(remote_payload.name),
         description: Some(remote_payload.description),
+        capabilities: Some(remote_payload.capabilities),
         ..Default::default()
     };
 
-    db_update_module(conn, module_id, &update_data).context("Failed to update module in database")
+    let module = db_update_module(conn, module_id, &update_data)
+        .context("Failed to update module in database")?;
+
+    Ok((module, true))
 }
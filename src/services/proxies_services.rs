@@ -0,0 +1,102 @@
+use crate::{
+    db::DbConnection,
+    errors::AppError,
+    models::{Definition, Module, ProxyBinding},
+    schema::{modules, proxy_bindings},
+    services::{configuration_services, invocation_services, secrets_services},
+    AppState,
+};
+use diesel::prelude::*;
+use serde_json::{json, Value};
+
+pub fn get_binding(conn: &mut DbConnection, definition_id: &str) -> QueryResult<ProxyBinding> {
+    proxy_bindings::table
+        .find(definition_id)
+        .select(ProxyBinding::as_select())
+        .first(conn)
+}
+
+pub fn set_binding(conn: &mut DbConnection, binding: &ProxyBinding) -> QueryResult<ProxyBinding> {
+    diesel::insert_into(proxy_bindings::table)
+        .values(binding)
+        .on_conflict(proxy_bindings::definition_id)
+        .do_update()
+        .set(binding)
+        .returning(ProxyBinding::as_returning())
+        .get_result(conn)
+}
+
+pub fn delete_binding(conn: &mut DbConnection, definition_id: &str) -> QueryResult<usize> {
+    diesel::delete(proxy_bindings::table.find(definition_id)).execute(conn)
+}
+
+/// Returns the binding for `definition_id` together with its proxy module.
+pub fn find_proxy(
+    conn: &mut DbConnection,
+    definition_id: &str,
+) -> QueryResult<Option<(ProxyBinding, Module)>> {
+    proxy_bindings::table
+        .inner_join(modules::table)
+        .filter(proxy_bindings::definition_id.eq(definition_id))
+        .select((ProxyBinding::as_select(), Module::as_select()))
+        .first(conn)
+        .optional()
+}
+
+/// Checks that `module` was granted access to the host of `upstream_url`, so
+/// a binding cannot point a proxy somewhere it is not allowed to reach.
+pub fn ensure_reachable(module: &Module, upstream_url: &str) -> Result<(), AppError> {
+    let url = url::Url::parse(upstream_url)
+        .map_err(|err| AppError::bad_request(format!("Invalid upstream URL: {}", err)))?;
+    let host = url.host_str().unwrap_or_default();
+
+    if !module.capabilities.allows_host(host) {
+        return Err(AppError::bad_request(format!(
+            "Module '{}' is not allowed to reach host '{}'",
+            module.id, host
+        )));
+    }
+
+    Ok(())
+}
+
+/// Forwards an invocation through `module` to `upstream_url`. The module gets
+/// its own configuration and secrets so it can authenticate upstream.
+pub async fn forward(
+    state: &AppState,
+    definition: &Definition,
+    module: &Module,
+    upstream_url: &str,
+    arguments: &Value,
+) -> Result<Value, AppError> {
+    let loaded = state.runtime.load(&state.s3_client, module).await?;
+
+    let configuration = configuration_services::get_configuration(
+        &state.s3_client,
+        "modules",
+        &module.configuration_object_key,
+    )
+    .await?;
+    let secrets = secrets_services::read_secrets(
+        &state.s3_client,
+        &state.keyring,
+        "modules",
+        &module.secrets_object_key,
+    )
+    .await?;
+
+    let request = json!({
+        "definition": invocation_services::definition_context(definition),
+        "upstream_url": upstream_url,
+        "arguments": arguments,
+        "configuration": configuration,
+        "secrets": secrets,
+    });
+    let output = state.runtime.proxy_forward(&loaded, &request).await?;
+
+    invocation_services::into_result(&module.id, output)
+}
+
+#[cfg(test)]
+#[path = "proxies_services_tests.rs"]
+mod tests;
@@ -0,0 +1,40 @@
+use super::*;
+use crate::models::{Capabilities, ModuleType};
+
+fn proxy_module(allowed_hosts: &[&str]) -> Module {
+    Module {
+        id: "acme::gateway".to_string(),
+        type_: ModuleType::Proxy,
+        is_enabled: true,
+        name: "Gateway".to_string(),
+        description: "Speaks the internal gateway's auth".to_string(),
+        module_object_key: "acme::gateway.wasm".to_string(),
+        configuration_object_key: "acme::gateway".to_string(),
+        secrets_object_key: "acme::gateway".to_string(),
+        digest: "sha256:abc123".to_string(),
+        source_url: None,
+        max_memory_bytes: 67_108_864,
+        fuel_limit: 1_000_000_000,
+        timeout_ms: 5000,
+        capabilities: Capabilities {
+            allowed_hosts: allowed_hosts.iter().map(|host| host.to_string()).collect(),
+            ..Default::default()
+        },
+        is_pinned: true,
+    }
+}
+
+#[test]
+fn test_ensure_reachable() {
+    let module = proxy_module(&["*.gateway.internal"]);
+
+    assert!(ensure_reachable(&module, "https://mcp.gateway.internal/rpc").is_ok());
+
+    let err = ensure_reachable(&module, "https://example.com/rpc").unwrap_err();
+    assert!(matches!(err, AppError::BadRequest(msg) if msg.contains("'example.com'")));
+
+    assert!(matches!(
+        ensure_reachable(&module, "not a url"),
+        Err(AppError::BadRequest(_))
+    ));
+}
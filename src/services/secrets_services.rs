@@ -0,0 +1,245 @@
+use crate::{
+    errors::AppError,
+    s3,
+    secrets::{Envelope, Keyring},
+    utils::regex_utils,
+};
+use anyhow::Context;
+use aws_sdk_s3;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use tracing::warn;
+
+pub const MASKED_VALUE: &str = "********";
+pub const SECRET_BUCKETS: [&str; 2] = ["definitions", "modules"];
+
+const KEY_VERSION_METADATA: &str = "key-version";
+const SECRETS_PREFIX: &str = "secrets/";
+/// Times a secrets object is re-read when it changes while being rotated.
+const ROTATION_ATTEMPTS: usize = 3;
+
+pub type Secrets = BTreeMap<String, String>;
+
+#[derive(Debug, Default, Serialize)]
+pub struct RotationReport {
+    pub key_version: u32,
+    pub rotated: usize,
+    pub unchanged: usize,
+    pub failed: Vec<String>,
+}
+
+pub fn secrets_key(object_key: &str) -> String {
+    format!("{}{}", SECRETS_PREFIX, object_key)
+}
+
+fn mask(secrets: &Secrets) -> Secrets {
+    secrets
+        .keys()
+        .map(|name| (name.clone(), MASKED_VALUE.to_string()))
+        .collect()
+}
+
+fn ensure_valid_names<'a>(names: impl IntoIterator<Item = &'a String>) -> Result<(), AppError> {
+    for name in names {
+        if name.len() > 128 || !regex_utils::NAMESPACE_ID.is_match(name) {
+            return Err(AppError::bad_request(format!(
+                "Invalid secret name '{}'",
+                name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Decrypts the stored secrets. Only the runtime should call this; API
+/// responses must go through the masked accessors.
+pub async fn read_secrets(
+    s3_client: &aws_sdk_s3::Client,
+    keyring: &Keyring,
+    bucket: &str,
+    object_key: &str,
+) -> Result<Secrets, AppError> {
+    let key = secrets_key(object_key);
+    let Some(bytes) = s3::get_bytes(s3_client, bucket, &key).await? else {
+        return Ok(Secrets::new());
+    };
+
+    let envelope = serde_json::from_slice(&bytes).context("Stored secrets are corrupted")?;
+    let plaintext = keyring.open(&envelope, &format!("{}/{}", bucket, key))?;
+    let secrets = serde_json::from_slice(&plaintext).context("Stored secrets are corrupted")?;
+
+    Ok(secrets)
+}
+
+async fn write_secrets(
+    s3_client: &aws_sdk_s3::Client,
+    keyring: &Keyring,
+    bucket: &str,
+    object_key: &str,
+    secrets: &Secrets,
+) -> Result<(), AppError> {
+    let key = secrets_key(object_key);
+    let plaintext = serde_json::to_vec(secrets).context("Failed to serialize secrets")?;
+    let envelope = keyring.seal(&plaintext, &format!("{}/{}", bucket, key))?;
+
+    write_envelope(s3_client, bucket, &key, &envelope).await
+}
+
+/// Serializes `envelope` together with the metadata recording its key
+/// version.
+fn envelope_object(envelope: &Envelope) -> anyhow::Result<(Vec<u8>, HashMap<String, String>)> {
+    let body = serde_json::to_vec(envelope).context("Failed to serialize secrets envelope")?;
+    let metadata = HashMap::from([(
+        KEY_VERSION_METADATA.to_string(),
+        envelope.key_version.to_string(),
+    )]);
+
+    Ok((body, metadata))
+}
+
+async fn write_envelope(
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    key: &str,
+    envelope: &Envelope,
+) -> Result<(), AppError> {
+    let (body, metadata) = envelope_object(envelope)?;
+
+    s3::put_object(s3_client, bucket, key, body.into(), metadata)
+        .await
+        .context("Failed to upload secrets to S3")?;
+
+    Ok(())
+}
+
+pub async fn get_secrets(
+    s3_client: &aws_sdk_s3::Client,
+    keyring: &Keyring,
+    bucket: &str,
+    object_key: &str,
+) -> Result<Secrets, AppError> {
+    let secrets = read_secrets(s3_client, keyring, bucket, object_key).await?;
+
+    Ok(mask(&secrets))
+}
+
+pub async fn set_secrets(
+    s3_client: &aws_sdk_s3::Client,
+    keyring: &Keyring,
+    bucket: &str,
+    object_key: &str,
+    secrets: Secrets,
+) -> Result<Secrets, AppError> {
+    ensure_valid_names(secrets.keys())?;
+    write_secrets(s3_client, keyring, bucket, object_key, &secrets).await?;
+
+    Ok(mask(&secrets))
+}
+
+/// Adds or replaces the given secrets; a `null` value removes that secret.
+pub async fn update_secrets(
+    s3_client: &aws_sdk_s3::Client,
+    keyring: &Keyring,
+    bucket: &str,
+    object_key: &str,
+    patch: BTreeMap<String, Option<String>>,
+) -> Result<Secrets, AppError> {
+    ensure_valid_names(patch.keys())?;
+
+    let mut secrets = read_secrets(s3_client, keyring, bucket, object_key).await?;
+    for (name, value) in patch {
+        match value {
+            Some(value) => secrets.insert(name, value),
+            None => secrets.remove(&name),
+        };
+    }
+
+    write_secrets(s3_client, keyring, bucket, object_key, &secrets).await?;
+
+    Ok(mask(&secrets))
+}
+
+pub async fn reset_secrets(
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    object_key: &str,
+) -> Result<(), AppError> {
+    s3::delete(s3_client, bucket, &secrets_key(object_key)).await?;
+
+    Ok(())
+}
+
+/// Re-wraps one secrets object. The write only goes through if the object
+/// is unchanged since it was read, so secrets saved while rotation runs are
+/// never replaced by the old values; the object is then read again.
+async fn rotate_object(
+    s3_client: &aws_sdk_s3::Client,
+    keyring: &Keyring,
+    bucket: &str,
+    key: &str,
+) -> anyhow::Result<bool> {
+    let active_version = keyring.active_version().to_string();
+
+    for _ in 0..ROTATION_ATTEMPTS {
+        let object = s3::get_object(s3_client, bucket, key)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Secrets object disappeared during rotation"))?;
+
+        if object.metadata.get(KEY_VERSION_METADATA) == Some(&active_version) {
+            return Ok(false);
+        }
+
+        let envelope: Envelope =
+            serde_json::from_slice(&object.bytes).context("Stored secrets are corrupted")?;
+        // Envelopes written before key versions were recorded in metadata
+        // may already use the active key; those only need tagging.
+        let rotated = envelope.key_version != keyring.active_version();
+        let envelope = if rotated {
+            keyring.rewrap(&envelope)?
+        } else {
+            envelope
+        };
+
+        let e_tag = object
+            .e_tag
+            .as_deref()
+            .context("S3 did not return an ETag for the secrets object")?;
+        let (body, metadata) = envelope_object(&envelope)?;
+        if s3::put_if_match(s3_client, bucket, key, body.into(), metadata, e_tag).await? {
+            return Ok(rotated);
+        }
+    }
+
+    anyhow::bail!(
+        "Secrets kept changing during rotation; gave up after {} attempts",
+        ROTATION_ATTEMPTS
+    )
+}
+
+/// Re-wraps every stored data key under the active master key version.
+/// Objects are processed independently so one failure does not stop the run.
+pub async fn rotate_secrets(
+    s3_client: &aws_sdk_s3::Client,
+    keyring: &Keyring,
+    buckets: &[&str],
+) -> Result<RotationReport, AppError> {
+    let mut report = RotationReport {
+        key_version: keyring.active_version(),
+        ..Default::default()
+    };
+
+    for bucket in buckets {
+        for key in s3::list_keys(s3_client, bucket, SECRETS_PREFIX).await? {
+            match rotate_object(s3_client, keyring, bucket, &key).await {
+                Ok(true) => report.rotated += 1,
+                Ok(false) => report.unchanged += 1,
+                Err(err) => {
+                    warn!("Failed to rotate secrets in {}/{}: {:?}", bucket, key, err);
+                    report.failed.push(format!("{}/{}", bucket, key));
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
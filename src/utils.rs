@@ -1,3 +1,8 @@
+pub mod digest_utils;
+pub mod json_utils;
 pub mod regex_utils;
+pub mod schema_utils;
 pub mod source_utils;
 pub mod stream_utils;
+pub mod template_utils;
+pub mod wasm_utils;
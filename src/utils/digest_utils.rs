@@ -0,0 +1,179 @@
+use crate::errors::AppError;
+use anyhow::Result;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::sync::{Arc, OnceLock};
+
+/// Algorithm of the digests the server computes for unpinned content.
+pub const DEFAULT_ALGORITHM: &str = "sha256";
+
+/// Splits a digest given as `algorithm:hash`.
+pub fn parse_digest(digest: &str) -> Result<(&str, &str)> {
+    digest
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Invalid digest format, expected 'algorithm:hash'"))
+}
+
+/// Incrementally hashes data with one of the supported algorithms.
+pub enum Hasher {
+    Sha256(Sha256),
+    Sha384(Sha384),
+    Sha512(Sha512),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    pub fn new(algorithm: &str) -> Result<Self> {
+        match algorithm {
+            "sha256" => Ok(Hasher::Sha256(Sha256::new())),
+            "sha384" => Ok(Hasher::Sha384(Sha384::new())),
+            "sha512" => Ok(Hasher::Sha512(Sha512::new())),
+            "blake3" => Ok(Hasher::Blake3(Box::default())),
+            _ => anyhow::bail!("Unsupported hash algorithm: {}", algorithm),
+        }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(bytes),
+            Hasher::Sha384(hasher) => hasher.update(bytes),
+            Hasher::Sha512(hasher) => hasher.update(bytes),
+            Hasher::Blake3(hasher) => {
+                hasher.update(bytes);
+            }
+        }
+    }
+
+    /// Returns the lowercase hex digest of everything hashed so far.
+    pub fn finalize(self) -> String {
+        match self {
+            Hasher::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            Hasher::Sha384(hasher) => format!("{:x}", hasher.finalize()),
+            Hasher::Sha512(hasher) => format!("{:x}", hasher.finalize()),
+            Hasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+/// Checks `bytes` against a digest given as `algorithm:hash`.
+pub fn verify_digest(expected_digest: &str, bytes: &[u8]) -> Result<()> {
+    let mut verifier = DigestVerifier::new(expected_digest)?;
+    verifier.update(bytes);
+
+    verifier.verify()
+}
+
+/// Checks data fed to it in pieces against a digest given as
+/// `algorithm:hash`, for content too large to hold in memory at once.
+pub struct DigestVerifier {
+    expected_digest: String,
+    algorithm: String,
+    hasher: Hasher,
+}
+
+impl DigestVerifier {
+    pub fn new(expected_digest: &str) -> Result<Self> {
+        let (algorithm, _) = parse_digest(expected_digest)?;
+
+        Ok(Self {
+            expected_digest: expected_digest.to_string(),
+            algorithm: algorithm.to_string(),
+            hasher: Hasher::new(algorithm)?,
+        })
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.hasher.update(bytes);
+    }
+
+    pub fn verify(self) -> Result<()> {
+        let (_, expected_hash) = parse_digest(&self.expected_digest)?;
+        let computed_hash = self.hasher.finalize();
+
+        if computed_hash != expected_hash {
+            anyhow::bail!(
+                "Digest mismatch: expected {}, got {}:{}",
+                self.expected_digest,
+                self.algorithm,
+                computed_hash
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the digest content must match. Content may only be accepted
+/// without one when its publisher explicitly opted out of pinning, in which
+/// case the server records the digest of whatever it receives.
+pub fn require_pin(digest: Option<&str>, allow_unpinned: bool) -> Result<Option<&str>, AppError> {
+    match digest {
+        None if !allow_unpinned => Err(AppError::bad_request(
+            "A digest is required unless allow_unpinned is set",
+        )),
+        digest => Ok(digest),
+    }
+}
+
+/// Returns the digest an upgrade must match. Content that was installed
+/// pinned stays pinned whatever its upstream manifest now says, unless the
+/// operator explicitly allows the upgrade to drop the pin.
+pub fn require_upgrade_pin(
+    digest: Option<&str>,
+    allow_unpinned: bool,
+    installed_pinned: bool,
+    operator_allows_unpinned: bool,
+) -> Result<Option<&str>, AppError> {
+    if digest.is_none() && installed_pinned && !operator_allows_unpinned {
+        return Err(AppError::bad_request(
+            "The installed version is pinned; the upstream no longer publishes a digest, \
+             so the upgrade must be requested with allow_unpinned",
+        ));
+    }
+
+    require_pin(digest, allow_unpinned)
+}
+
+/// Computes the digest of data fed to it in pieces, as `algorithm:hash`, for
+/// content published without one.
+pub struct DigestRecorder {
+    hasher: Hasher,
+    digest: Arc<OnceLock<String>>,
+}
+
+impl Default for DigestRecorder {
+    fn default() -> Self {
+        Self {
+            hasher: Hasher::Sha256(Sha256::new()),
+            digest: Arc::default(),
+        }
+    }
+}
+
+impl DigestRecorder {
+    /// Holds the digest once recording has finished, for callers that hand
+    /// the recorder off.
+    pub fn digest(&self) -> Arc<OnceLock<String>> {
+        self.digest.clone()
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.hasher.update(bytes);
+    }
+
+    pub fn finish(self) -> String {
+        let digest = format!("{}:{}", DEFAULT_ALGORITHM, self.hasher.finalize());
+        self.digest.get_or_init(|| digest).clone()
+    }
+}
+
+/// Computes the digest of `bytes` as `algorithm:hash`.
+pub fn record_digest(bytes: &[u8]) -> String {
+    let mut recorder = DigestRecorder::default();
+    recorder.update(bytes);
+
+    recorder.finish()
+}
+
+#[cfg(test)]
+#[path = "digest_utils_tests.rs"]
+mod tests;
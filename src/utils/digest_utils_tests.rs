@@ -0,0 +1,127 @@
+use super::*;
+
+const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+const HELLO_SHA384: &str = "59e1748777448c69de6b800d7a33bbfb9ff1b463e44354c3553bcdb9c666fa90125a3c79f90397bdf5f6a13de828684f";
+const HELLO_SHA512: &str = "9b71d224bd62f3785d96d46ad3ea3d73319bfbc2890caadae2dff72519673ca72323c3d99ba5c11d7c7acc6e14b8c5da0c4663475c2e5c3adef46f73bcdec043";
+const HELLO_BLAKE3: &str = "ea8f163db38682925e4491c5e58d4bb3506ef8c14eb78a86e908c5624a67200f";
+
+fn compute_digest(algorithm: &str, bytes: &[u8]) -> Result<String> {
+    let mut hasher = Hasher::new(algorithm)?;
+    hasher.update(bytes);
+
+    Ok(hasher.finalize())
+}
+
+#[test]
+fn test_compute_digest_sha256() {
+    assert_eq!(compute_digest("sha256", b"hello").unwrap(), HELLO_SHA256);
+}
+
+#[test]
+fn test_compute_digest_sha384() {
+    assert_eq!(compute_digest("sha384", b"hello").unwrap(), HELLO_SHA384);
+}
+
+#[test]
+fn test_compute_digest_sha512() {
+    assert_eq!(compute_digest("sha512", b"hello").unwrap(), HELLO_SHA512);
+}
+
+#[test]
+fn test_compute_digest_blake3() {
+    assert_eq!(compute_digest("blake3", b"hello").unwrap(), HELLO_BLAKE3);
+}
+
+#[test]
+fn test_compute_digest_rejects_unknown_algorithm() {
+    assert!(compute_digest("md5", b"hello").is_err());
+}
+
+#[test]
+fn test_verify_digest() {
+    assert!(verify_digest(&format!("sha256:{}", HELLO_SHA256), b"hello").is_ok());
+
+    let err = verify_digest(&format!("sha256:{}", HELLO_SHA256), b"other").unwrap_err();
+    assert!(err.to_string().contains("Digest mismatch"));
+
+    assert!(verify_digest(HELLO_SHA256, b"hello").is_err());
+}
+
+#[test]
+fn test_digest_verifier_accepts_chunks() {
+    let mut verifier = DigestVerifier::new(&format!("sha256:{}", HELLO_SHA256)).unwrap();
+    verifier.update(b"he");
+    verifier.update(b"");
+    verifier.update(b"llo");
+
+    assert!(verifier.verify().is_ok());
+}
+
+#[test]
+fn test_digest_verifier_supports_every_algorithm() {
+    for digest in [
+        format!("sha384:{}", HELLO_SHA384),
+        format!("sha512:{}", HELLO_SHA512),
+        format!("blake3:{}", HELLO_BLAKE3),
+    ] {
+        let mut verifier = DigestVerifier::new(&digest).unwrap();
+        verifier.update(b"hel");
+        verifier.update(b"lo");
+
+        assert!(verifier.verify().is_ok(), "{} should match", digest);
+    }
+}
+
+#[test]
+fn test_digest_verifier_rejects_invalid_digests() {
+    assert!(DigestVerifier::new("md5:abcd").is_err());
+    assert!(DigestVerifier::new(HELLO_SHA256).is_err());
+}
+
+#[test]
+fn test_require_pin() {
+    let digest = format!("sha256:{}", HELLO_SHA256);
+
+    assert_eq!(
+        require_pin(Some(&digest), false).unwrap(),
+        Some(&digest[..])
+    );
+    assert_eq!(require_pin(None, true).unwrap(), None);
+    assert!(matches!(
+        require_pin(None, false),
+        Err(AppError::BadRequest(_))
+    ));
+}
+
+#[test]
+fn test_require_upgrade_pin() {
+    let digest = format!("sha256:{}", HELLO_SHA256);
+
+    assert_eq!(
+        require_upgrade_pin(Some(&digest), false, true, false).unwrap(),
+        Some(&digest[..])
+    );
+    assert!(matches!(
+        require_upgrade_pin(None, true, true, false),
+        Err(AppError::BadRequest(_))
+    ));
+    assert_eq!(require_upgrade_pin(None, true, true, true).unwrap(), None);
+    assert_eq!(require_upgrade_pin(None, true, false, false).unwrap(), None);
+    assert!(matches!(
+        require_upgrade_pin(None, false, true, true),
+        Err(AppError::BadRequest(_))
+    ));
+}
+
+#[test]
+fn test_digest_recorder() {
+    let mut recorder = DigestRecorder::default();
+    let recorded = recorder.digest();
+    recorder.update(b"he");
+    recorder.update(b"llo");
+
+    let digest = recorder.finish();
+    assert_eq!(digest, format!("sha256:{}", HELLO_SHA256));
+    assert_eq!(recorded.get(), Some(&digest));
+    assert_eq!(record_digest(b"hello"), digest);
+}
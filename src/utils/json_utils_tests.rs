@@ -0,0 +1,45 @@
+use super::*;
+use serde_json::json;
+
+#[test]
+fn test_merge_patch_replaces_and_adds_members() {
+    let mut target = json!({ "a": "b", "c": { "d": "e", "f": "g" } });
+    merge_patch(
+        &mut target,
+        &json!({ "a": "z", "c": { "f": null }, "h": 1 }),
+    );
+
+    assert_eq!(target, json!({ "a": "z", "c": { "d": "e" }, "h": 1 }));
+}
+
+#[test]
+fn test_merge_patch_null_removes_member() {
+    let mut target = json!({ "a": "b", "c": "d" });
+    merge_patch(&mut target, &json!({ "a": null }));
+
+    assert_eq!(target, json!({ "c": "d" }));
+}
+
+#[test]
+fn test_merge_patch_replaces_arrays_wholesale() {
+    let mut target = json!({ "a": [1, 2, 3] });
+    merge_patch(&mut target, &json!({ "a": [4] }));
+
+    assert_eq!(target, json!({ "a": [4] }));
+}
+
+#[test]
+fn test_merge_patch_non_object_patch_replaces_target() {
+    let mut target = json!({ "a": "b" });
+    merge_patch(&mut target, &json!(["c"]));
+
+    assert_eq!(target, json!(["c"]));
+}
+
+#[test]
+fn test_merge_patch_object_patch_on_scalar_target() {
+    let mut target = json!("scalar");
+    merge_patch(&mut target, &json!({ "a": { "b": null, "c": 1 } }));
+
+    assert_eq!(target, json!({ "a": { "c": 1 } }));
+}
@@ -5,7 +5,12 @@ pub static NAMESPACE_ID: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"^[a-zA-Z0-9_.-]+$").unwrap());
 pub static TYPE_IDENTIFIER: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"^[a-zA-Z0-9_-]+$").unwrap());
+pub static TEMPLATE_PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*([a-zA-Z0-9_.-]+)\s*\}\}").unwrap());
 pub static SHA256: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[a-f0-9]{64}$").unwrap());
+pub static SHA384: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[a-f0-9]{96}$").unwrap());
+pub static SHA512: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[a-f0-9]{128}$").unwrap());
+pub static BLAKE3: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[a-f0-9]{64}$").unwrap());
 
 #[cfg(test)]
 #[path = "regex_utils_tests.rs"]
@@ -29,3 +29,40 @@ fn test_sha256() {
     assert!(!SHA256.is_match("g3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"));
     assert!(!SHA256.is_match("E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855"));
 }
+
+#[test]
+fn test_sha384() {
+    assert!(SHA384.is_match(&"a".repeat(96)));
+
+    assert!(!SHA384.is_match(&"a".repeat(64)));
+    assert!(!SHA384.is_match(&"a".repeat(128)));
+    assert!(!SHA384.is_match(&"A".repeat(96)));
+}
+
+#[test]
+fn test_sha512() {
+    assert!(SHA512.is_match(&"a".repeat(128)));
+
+    assert!(!SHA512.is_match(&"a".repeat(96)));
+    assert!(!SHA512.is_match(&"g".repeat(128)));
+}
+
+#[test]
+fn test_blake3() {
+    assert!(BLAKE3.is_match("ea8f163db38682925e4491c5e58d4bb3506ef8c14eb78a86e908c5624a67200f"));
+
+    assert!(!BLAKE3.is_match(&"a".repeat(128)));
+}
+
+#[test]
+fn test_template_placeholder() {
+    let captures = TEMPLATE_PLACEHOLDER
+        .captures("id={{ arguments.id }}")
+        .unwrap();
+    assert_eq!(&captures[1], "arguments.id");
+    assert!(TEMPLATE_PLACEHOLDER.is_match("{{secrets.api-key}}"));
+
+    assert!(!TEMPLATE_PLACEHOLDER.is_match("{{}}"));
+    assert!(!TEMPLATE_PLACEHOLDER.is_match("{{ arguments id }}"));
+    assert!(!TEMPLATE_PLACEHOLDER.is_match("{ arguments.id }"));
+}
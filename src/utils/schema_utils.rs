@@ -0,0 +1,30 @@
+use crate::errors::{AppError, FieldViolation};
+use serde_json::Value;
+
+pub fn ensure_valid_schema(schema: &Value) -> Result<(), AppError> {
+    jsonschema::meta::validate(schema)
+        .map_err(|err| AppError::bad_request(format!("Invalid JSON Schema: {}", err)))
+}
+
+pub fn validate_instance(schema: &Value, instance: &Value) -> Result<(), AppError> {
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|err| AppError::bad_request(format!("Invalid JSON Schema: {}", err)))?;
+
+    let violations: Vec<FieldViolation> = validator
+        .iter_errors(instance)
+        .map(|err| FieldViolation {
+            pointer: err.instance_path().to_string(),
+            message: err.to_string(),
+        })
+        .collect();
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::schema_validation(violations))
+    }
+}
+
+#[cfg(test)]
+#[path = "schema_utils_tests.rs"]
+mod tests;
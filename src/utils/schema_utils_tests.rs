@@ -0,0 +1,61 @@
+use super::*;
+use serde_json::json;
+
+fn timeout_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "timeout": { "type": "integer", "minimum": 1 },
+            "endpoint": {
+                "type": "object",
+                "properties": { "url": { "type": "string" } },
+                "required": ["url"]
+            }
+        },
+        "additionalProperties": false
+    })
+}
+
+#[test]
+fn test_ensure_valid_schema_accepts_schema() {
+    assert!(ensure_valid_schema(&timeout_schema()).is_ok());
+}
+
+#[test]
+fn test_ensure_valid_schema_rejects_invalid_schema() {
+    let result = ensure_valid_schema(&json!({ "type": "not-a-type" }));
+    assert!(matches!(result, Err(AppError::BadRequest(_))));
+}
+
+#[test]
+fn test_validate_instance_accepts_valid_document() {
+    let instance = json!({ "timeout": 5, "endpoint": { "url": "https://example.com" } });
+    assert!(validate_instance(&timeout_schema(), &instance).is_ok());
+}
+
+#[test]
+fn test_validate_instance_reports_json_pointers() {
+    let instance = json!({ "timeout": 0, "endpoint": { "url": 7 } });
+
+    match validate_instance(&timeout_schema(), &instance) {
+        Err(AppError::SchemaValidation(violations)) => {
+            let mut pointers: Vec<_> = violations.iter().map(|v| v.pointer.as_str()).collect();
+            pointers.sort();
+            assert_eq!(pointers, vec!["/endpoint/url", "/timeout"]);
+        }
+        _ => panic!("Expected SchemaValidation error"),
+    }
+}
+
+#[test]
+fn test_validate_instance_reports_root_pointer() {
+    let result = validate_instance(&timeout_schema(), &json!({ "unknown": true }));
+
+    match result {
+        Err(AppError::SchemaValidation(violations)) => {
+            assert_eq!(violations.len(), 1);
+            assert_eq!(violations[0].pointer, "");
+        }
+        _ => panic!("Expected SchemaValidation error"),
+    }
+}
@@ -1,5 +1,7 @@
+use crate::errors::AppError;
 use anyhow::Result;
 use aws_smithy_types::byte_stream::ByteStream;
+use bytes::{Bytes, BytesMut};
 use reqwest;
 use std::path::Path;
 
@@ -11,10 +13,65 @@ pub async fn stream_content_from_url(
     Ok(response)
 }
 
+/// Turns the body of `response` into a stream for S3, keeping its length so
+/// the upload does not have to buffer it.
+pub fn byte_stream_from_response(response: reqwest::Response) -> ByteStream {
+    ByteStream::from_body_1_x(reqwest::Body::from(response))
+}
+
 pub async fn stream_content_from_path(path: impl AsRef<Path>) -> Result<ByteStream> {
     Ok(ByteStream::from_path(path).await?)
 }
 
+/// Reads the body of `response` into memory, failing with an upstream error
+/// as soon as it is known to be larger than `max_bytes`. Errors leave out the
+/// URL, which may carry secrets.
+pub async fn read_response_limited(
+    mut response: reqwest::Response,
+    max_bytes: usize,
+) -> Result<Bytes> {
+    let too_large =
+        || AppError::upstream(format!("Response exceeds the limit of {} bytes", max_bytes));
+    if response
+        .content_length()
+        .is_some_and(|length| length > max_bytes as u64)
+    {
+        return Err(too_large().into());
+    }
+
+    let mut body = BytesMut::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(reqwest::Error::without_url)?
+    {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(too_large().into());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body.freeze())
+}
+
+/// Reads `stream` into memory, failing with a bad request as soon as it is
+/// larger than `max_bytes`.
+pub async fn read_stream_limited(mut stream: ByteStream, max_bytes: usize) -> Result<Bytes> {
+    let mut body = BytesMut::new();
+    while let Some(chunk) = stream.try_next().await? {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(AppError::bad_request(format!(
+                "Content exceeds the limit of {} bytes",
+                max_bytes
+            ))
+            .into());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body.freeze())
+}
+
 #[cfg(test)]
 #[path = "stream_utils_tests.rs"]
 mod tests;
@@ -35,6 +35,32 @@ mod http_tests {
         let res = stream_content_from_url(&client, &server.uri()).await;
         assert!(res.is_err());
     }
+
+    #[tokio::test]
+    async fn test_read_response_limited_rejects_large_body() {
+        let client = reqwest::Client::new();
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("0123456789"))
+            .mount(&server)
+            .await;
+
+        let response = stream_content_from_url(&client, &server.uri())
+            .await
+            .unwrap();
+        let err = read_response_limited(response, 4).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AppError>(),
+            Some(AppError::Upstream(_))
+        ));
+
+        let response = stream_content_from_url(&client, &server.uri())
+            .await
+            .unwrap();
+        let body = read_response_limited(response, 10).await.unwrap();
+        assert_eq!(&body[..], b"0123456789");
+    }
 }
 
 mod file_tests {
@@ -55,6 +81,17 @@ mod file_tests {
         assert_eq!(data, b"file content\n");
     }
 
+    #[tokio::test]
+    async fn test_read_stream_limited_rejects_large_content() {
+        let stream = ByteStream::from_static(b"0123456789");
+        let err = read_stream_limited(stream, 4).await.unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<AppError>(),
+            Some(AppError::BadRequest(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_stream_path_missing_file() {
         let res = stream_content_from_path("/NA.txt").await;
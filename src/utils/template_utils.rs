@@ -0,0 +1,91 @@
+use crate::{errors::AppError, utils::regex_utils::TEMPLATE_PLACEHOLDER};
+use serde_json::{Map, Value};
+
+/// Resolves a dotted `path` such as `arguments.user.id` in `context`. Numeric
+/// segments index into arrays.
+pub fn lookup<'a>(context: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(context, |value, segment| match value {
+            Value::Object(map) => map.get(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+}
+
+fn missing(path: &str) -> AppError {
+    AppError::bad_request(format!("Template references '{}', which is not set", path))
+}
+
+fn as_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Returns the path of `template` when it consists of a single placeholder.
+pub fn sole_placeholder(template: &str) -> Option<&str> {
+    let captures = TEMPLATE_PLACEHOLDER.captures(template)?;
+
+    (captures.get(0)?.as_str() == template).then(|| captures.get(1).unwrap().as_str())
+}
+
+/// Replaces every `{{ path }}` in `template` with the text of the value at
+/// `path` in `context`. `escape` receives the path and text of each value
+/// before it is inserted.
+pub fn render_text(
+    template: &str,
+    context: &Value,
+    escape: impl Fn(&str, String) -> String,
+) -> Result<String, AppError> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut last = 0;
+
+    for captures in TEMPLATE_PLACEHOLDER.captures_iter(template) {
+        let placeholder = captures.get(0).unwrap();
+        let path = &captures[1];
+        let value = lookup(context, path).ok_or_else(|| missing(path))?;
+
+        rendered.push_str(&template[last..placeholder.start()]);
+        rendered.push_str(&escape(path, as_text(value)));
+        last = placeholder.end();
+    }
+    rendered.push_str(&template[last..]);
+
+    Ok(rendered)
+}
+
+/// Renders a JSON template. A string made of a single placeholder takes the
+/// referenced value as is, keeping its type, and object fields whose sole
+/// placeholder is not set are left out. Other strings are rendered as text.
+pub fn render_value(template: &Value, context: &Value) -> Result<Value, AppError> {
+    match template {
+        Value::String(text) => match sole_placeholder(text) {
+            Some(path) => lookup(context, path).cloned().ok_or_else(|| missing(path)),
+            None => render_text(text, context, |_, value| value).map(Value::String),
+        },
+        Value::Array(items) => items
+            .iter()
+            .map(|item| render_value(item, context))
+            .collect::<Result<_, _>>()
+            .map(Value::Array),
+        Value::Object(fields) => {
+            let mut rendered = Map::new();
+            for (key, field) in fields {
+                let optional = field
+                    .as_str()
+                    .and_then(sole_placeholder)
+                    .is_some_and(|path| lookup(context, path).is_none());
+                if !optional {
+                    rendered.insert(key.clone(), render_value(field, context)?);
+                }
+            }
+            Ok(Value::Object(rendered))
+        }
+        other => Ok(other.clone()),
+    }
+}
+
+#[cfg(test)]
+#[path = "template_utils_tests.rs"]
+mod tests;
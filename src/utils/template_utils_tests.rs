@@ -0,0 +1,83 @@
+use super::*;
+use serde_json::json;
+
+fn context() -> Value {
+    json!({
+        "arguments": { "id": "a/b", "limit": 10, "tags": ["x", "y"] },
+        "secrets": { "token": "s3cr3t" }
+    })
+}
+
+#[test]
+fn test_lookup() {
+    let context = context();
+
+    assert_eq!(lookup(&context, "arguments.limit"), Some(&json!(10)));
+    assert_eq!(lookup(&context, "arguments.tags.1"), Some(&json!("y")));
+    assert_eq!(lookup(&context, "arguments.missing"), None);
+    assert_eq!(lookup(&context, "secrets.token.length"), None);
+}
+
+#[test]
+fn test_sole_placeholder() {
+    assert_eq!(sole_placeholder("{{ arguments.id }}"), Some("arguments.id"));
+    assert_eq!(sole_placeholder("id-{{ arguments.id }}"), None);
+    assert_eq!(sole_placeholder("plain"), None);
+}
+
+#[test]
+fn test_render_text() {
+    let rendered = render_text(
+        "Bearer {{secrets.token}} for {{ arguments.id }} ({{arguments.limit}})",
+        &context(),
+        |_, value| value,
+    )
+    .unwrap();
+
+    assert_eq!(rendered, "Bearer s3cr3t for a/b (10)");
+}
+
+#[test]
+fn test_render_text_escapes_values() {
+    let rendered = render_text("/items/{{arguments.id}}", &context(), |path, value| {
+        format!("<{}={}>", path, value)
+    })
+    .unwrap();
+
+    assert_eq!(rendered, "/items/<arguments.id=a/b>");
+}
+
+#[test]
+fn test_render_text_rejects_missing_values() {
+    let err = render_text("{{arguments.missing}}", &context(), |_, value| value).unwrap_err();
+
+    assert!(matches!(err, AppError::BadRequest(msg) if msg.contains("arguments.missing")));
+}
+
+#[test]
+fn test_render_value() {
+    let template = json!({
+        "id": "{{arguments.id}}",
+        "limit": "{{ arguments.limit }}",
+        "label": "item {{arguments.id}}",
+        "filters": ["{{arguments.tags}}", true],
+        "cursor": "{{arguments.cursor}}"
+    });
+
+    let rendered = render_value(&template, &context()).unwrap();
+
+    assert_eq!(
+        rendered,
+        json!({
+            "id": "a/b",
+            "limit": 10,
+            "label": "item a/b",
+            "filters": [["x", "y"], true]
+        })
+    );
+}
+
+#[test]
+fn test_render_value_rejects_missing_array_items() {
+    assert!(render_value(&json!(["{{arguments.cursor}}"]), &context()).is_err());
+}
@@ -0,0 +1,285 @@
+use crate::{
+    errors::AppError,
+    models::{Capabilities, ModuleType},
+};
+use std::collections::HashMap;
+use wasmparser::{
+    types::{EntityType, Types, TypesRef},
+    Chunk, FuncValidatorAllocations, Parser, ValType, ValidPayload, Validator,
+};
+
+pub const HOST_MODULE: &str = "mci";
+pub const WASI_MODULE: &str = "wasi_snapshot_preview1";
+
+pub const MEMORY_EXPORT: &str = "memory";
+pub const ALLOC_EXPORT: &str = "mci_alloc";
+pub const START_EXPORT: &str = "_start";
+pub const LANGUAGE_EVAL_EXPORT: &str = "mci_language_eval";
+pub const INTERCEPT_REQUEST_EXPORT: &str = "mci_intercept_request";
+pub const INTERCEPT_RESPONSE_EXPORT: &str = "mci_intercept_response";
+pub const PROXY_FORWARD_EXPORT: &str = "mci_proxy_forward";
+pub const HOOK_HANDLE_EXPORT: &str = "mci_hook_handle";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequiredExport {
+    Memory,
+    Function {
+        params: &'static [ValType],
+        results: &'static [ValType],
+    },
+}
+
+const ALLOC: RequiredExport = RequiredExport::Function {
+    params: &[ValType::I32],
+    results: &[ValType::I32],
+};
+
+/// Entrypoints receive a `(ptr, len)` input buffer and return the output
+/// buffer packed as `ptr << 32 | len`.
+const ENTRYPOINT: RequiredExport = RequiredExport::Function {
+    params: &[ValType::I32, ValType::I32],
+    results: &[ValType::I64],
+};
+
+const COMMAND: RequiredExport = RequiredExport::Function {
+    params: &[],
+    results: &[],
+};
+
+/// The exports a module of the given type must provide for the runtime to
+/// call it.
+pub fn required_exports(module_type: ModuleType) -> &'static [(&'static str, RequiredExport)] {
+    match module_type {
+        ModuleType::Language => &[
+            (MEMORY_EXPORT, RequiredExport::Memory),
+            (ALLOC_EXPORT, ALLOC),
+            (LANGUAGE_EVAL_EXPORT, ENTRYPOINT),
+        ],
+        ModuleType::Sandbox => &[
+            (MEMORY_EXPORT, RequiredExport::Memory),
+            (START_EXPORT, COMMAND),
+        ],
+        ModuleType::Interceptor => &[
+            (MEMORY_EXPORT, RequiredExport::Memory),
+            (ALLOC_EXPORT, ALLOC),
+            (INTERCEPT_REQUEST_EXPORT, ENTRYPOINT),
+            (INTERCEPT_RESPONSE_EXPORT, ENTRYPOINT),
+        ],
+        ModuleType::Proxy => &[
+            (MEMORY_EXPORT, RequiredExport::Memory),
+            (ALLOC_EXPORT, ALLOC),
+            (PROXY_FORWARD_EXPORT, ENTRYPOINT),
+        ],
+        ModuleType::Hook => &[
+            (MEMORY_EXPORT, RequiredExport::Memory),
+            (ALLOC_EXPORT, ALLOC),
+            (HOOK_HANDLE_EXPORT, ENTRYPOINT),
+        ],
+    }
+}
+
+/// Returns why importing `module.name` is not permitted under `capabilities`.
+/// WASI calls that only touch stdio, clocks, randomness, arguments or the
+/// (filtered) environment are always allowed.
+fn denied_import(module: &str, name: &str, capabilities: &Capabilities) -> Option<&'static str> {
+    let lacks_network = capabilities.allowed_hosts.is_empty();
+    let lacks_filesystem = capabilities.preopened_dirs.is_empty();
+
+    match (module, name) {
+        (HOST_MODULE, "log") => None,
+        (HOST_MODULE, "http_request") if lacks_network => Some("requires allowed hosts"),
+        (HOST_MODULE, "http_request") => None,
+        (HOST_MODULE, _) => Some("is not provided by MCI"),
+        (WASI_MODULE, name) if name.starts_with("path_") && lacks_filesystem => {
+            Some("requires a preopened directory")
+        }
+        (WASI_MODULE, name) if name.starts_with("sock_") && lacks_network => {
+            Some("requires allowed hosts")
+        }
+        (WASI_MODULE, _) => None,
+        _ => Some("is from an unknown import module"),
+    }
+}
+
+fn invalid_module(err: wasmparser::BinaryReaderError) -> AppError {
+    AppError::bad_request(format!("Invalid WebAssembly module: {}", err))
+}
+
+/// Fully validates `bytes` as a core WebAssembly module, checks that it
+/// provides the exports required for `module_type` and that it only imports
+/// host functions covered by `capabilities`.
+pub fn validate_module(
+    bytes: &[u8],
+    module_type: ModuleType,
+    capabilities: &Capabilities,
+) -> Result<(), AppError> {
+    let mut validator = ModuleValidator::new(module_type, capabilities);
+    validator.update(bytes)?;
+
+    validator.finish()
+}
+
+/// Upper bound on a single buffered section, or function body within the
+/// code section. Data and custom sections are buffered whole.
+const MAX_SECTION_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Performs [`validate_module`] on a module whose bytes arrive in pieces.
+/// Only the section being parsed is buffered, and within the code section a
+/// single function body. Modules with a larger section than
+/// [`MAX_SECTION_BYTES`] are rejected, which bounds memory use regardless of
+/// the module's size.
+pub struct ModuleValidator {
+    module_type: ModuleType,
+    capabilities: Capabilities,
+    parser: Parser,
+    validator: Validator,
+    allocations: FuncValidatorAllocations,
+    buffer: Vec<u8>,
+    header_checked: bool,
+    types: Option<Types>,
+}
+
+impl ModuleValidator {
+    pub fn new(module_type: ModuleType, capabilities: &Capabilities) -> Self {
+        Self {
+            module_type,
+            capabilities: capabilities.clone(),
+            parser: Parser::new(0),
+            validator: Validator::new(),
+            allocations: FuncValidatorAllocations::default(),
+            buffer: Vec::new(),
+            header_checked: false,
+            types: None,
+        }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) -> Result<(), AppError> {
+        self.buffer.extend_from_slice(bytes);
+
+        self.parse(false)
+    }
+
+    pub fn finish(mut self) -> Result<(), AppError> {
+        self.parse(true)?;
+
+        let types = self
+            .types
+            .ok_or_else(|| AppError::bad_request("Invalid WebAssembly module: unexpected end"))?;
+
+        check_interface(types.as_ref(), self.module_type, &self.capabilities)
+    }
+
+    fn parse(&mut self, eof: bool) -> Result<(), AppError> {
+        if !self.header_checked {
+            if self.buffer.len() < 8 && !eof {
+                return Ok(());
+            }
+            if !Parser::is_core_wasm(&self.buffer) {
+                return Err(AppError::bad_request(
+                    "Module file is not a WebAssembly binary",
+                ));
+            }
+            self.header_checked = true;
+        }
+
+        let mut offset = 0;
+        while self.types.is_none() {
+            let chunk = self
+                .parser
+                .parse(&self.buffer[offset..], eof)
+                .map_err(invalid_module)?;
+            let (consumed, payload) = match chunk {
+                Chunk::Parsed { consumed, payload } => (consumed, payload),
+                Chunk::NeedMoreData(hint) => {
+                    let buffered = (self.buffer.len() - offset) as u64;
+                    if buffered.saturating_add(hint) > MAX_SECTION_BYTES {
+                        return Err(AppError::bad_request(format!(
+                            "Invalid WebAssembly module: sections may be at most {} MiB",
+                            MAX_SECTION_BYTES / (1024 * 1024)
+                        )));
+                    }
+                    break;
+                }
+            };
+
+            match self.validator.payload(&payload).map_err(invalid_module)? {
+                ValidPayload::Func(func, body) => {
+                    let mut func_validator =
+                        func.into_validator(std::mem::take(&mut self.allocations));
+                    func_validator.validate(&body).map_err(invalid_module)?;
+                    self.allocations = func_validator.into_allocations();
+                }
+                ValidPayload::End(types) => self.types = Some(types),
+                ValidPayload::Ok | ValidPayload::Parser(_) => {}
+            }
+            offset += consumed;
+        }
+        self.buffer.drain(..offset);
+
+        Ok(())
+    }
+}
+
+fn check_interface(
+    types: TypesRef<'_>,
+    module_type: ModuleType,
+    capabilities: &Capabilities,
+) -> Result<(), AppError> {
+    let exports: HashMap<&str, EntityType> = types
+        .core_exports()
+        .map(|exports| exports.collect())
+        .unwrap_or_default();
+
+    let mut problems = Vec::new();
+    for (name, required) in required_exports(module_type) {
+        let matches = match (required, exports.get(name)) {
+            (RequiredExport::Memory, Some(EntityType::Memory(_))) => true,
+            (
+                RequiredExport::Function { params, results },
+                Some(EntityType::Func(id) | EntityType::FuncExact(id)),
+            ) => {
+                let func = types[*id].unwrap_func();
+                func.params() == *params && func.results() == *results
+            }
+            (_, None) => {
+                problems.push(format!("missing export '{}'", name));
+                continue;
+            }
+            _ => false,
+        };
+
+        if !matches {
+            problems.push(format!("export '{}' has the wrong type", name));
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(AppError::bad_request(format!(
+            "Module does not provide the exports required for its type: {}",
+            problems.join(", ")
+        )));
+    }
+
+    let denied: Vec<String> = types
+        .core_imports()
+        .into_iter()
+        .flatten()
+        .filter_map(|(module, name, _)| {
+            denied_import(module, name, capabilities)
+                .map(|reason| format!("'{}.{}' {}", module, name, reason))
+        })
+        .collect();
+
+    if !denied.is_empty() {
+        return Err(AppError::bad_request(format!(
+            "Module imports host functions it was not granted: {}",
+            denied.join(", ")
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "wasm_utils_tests.rs"]
+mod tests;
@@ -0,0 +1,167 @@
+use super::*;
+
+fn interceptor_module() -> Vec<u8> {
+    wat::parse_str(
+        r#"(module
+            (memory (export "memory") 1)
+            (func (export "mci_alloc") (param i32) (result i32) i32.const 0)
+            (func (export "mci_intercept_request") (param i32 i32) (result i64) i64.const 0)
+            (func (export "mci_intercept_response") (param i32 i32) (result i64) i64.const 0))"#,
+    )
+    .unwrap()
+}
+
+fn assert_bad_request(result: Result<(), AppError>, expected: &str) {
+    match result {
+        Err(AppError::BadRequest(msg)) => assert!(msg.contains(expected), "{}", msg),
+        other => panic!("Expected BadRequest, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_validate_module_accepts_matching_exports() {
+    assert!(validate_module(
+        &interceptor_module(),
+        ModuleType::Interceptor,
+        &Capabilities::default()
+    )
+    .is_ok());
+}
+
+#[test]
+fn test_validate_module_accepts_sandbox_command() {
+    let bytes = wat::parse_str(r#"(module (memory (export "memory") 1) (func (export "_start")))"#)
+        .unwrap();
+
+    assert!(validate_module(&bytes, ModuleType::Sandbox, &Capabilities::default()).is_ok());
+}
+
+#[test]
+fn test_validate_module_rejects_non_wasm() {
+    let result = validate_module(
+        b"<html>Not Found</html>",
+        ModuleType::Language,
+        &Capabilities::default(),
+    );
+    assert_bad_request(result, "not a WebAssembly binary");
+}
+
+#[test]
+fn test_validate_module_rejects_malformed_sections() {
+    let result = validate_module(
+        b"\0asm\x01\0\0\0\x01\xff",
+        ModuleType::Language,
+        &Capabilities::default(),
+    );
+    assert_bad_request(result, "Invalid WebAssembly module");
+}
+
+#[test]
+fn test_validate_module_reports_missing_exports() {
+    let result = validate_module(
+        &interceptor_module(),
+        ModuleType::Proxy,
+        &Capabilities::default(),
+    );
+    assert_bad_request(result, "missing export 'mci_proxy_forward'");
+}
+
+#[test]
+fn test_validate_module_rejects_wrong_signature() {
+    let bytes = wat::parse_str(
+        r#"(module
+            (memory (export "memory") 1)
+            (func (export "mci_alloc") (param i32) (result i32) i32.const 0)
+            (func (export "mci_hook_handle") (param i32) (result i32) i32.const 0))"#,
+    )
+    .unwrap();
+
+    let result = validate_module(&bytes, ModuleType::Hook, &Capabilities::default());
+    assert_bad_request(result, "export 'mci_hook_handle' has the wrong type");
+}
+
+fn hook_importing(module: &str, name: &str, params: &str) -> Vec<u8> {
+    wat::parse_str(format!(
+        r#"(module
+            (import "{}" "{}" (func {}))
+            (memory (export "memory") 1)
+            (func (export "mci_alloc") (param i32) (result i32) i32.const 0)
+            (func (export "mci_hook_handle") (param i32 i32) (result i64) i64.const 0))"#,
+        module, name, params
+    ))
+    .unwrap()
+}
+
+#[test]
+fn test_validate_module_allows_ungated_imports() {
+    let logging = hook_importing("mci", "log", "(param i32 i32 i32)");
+    let stdio = hook_importing(
+        "wasi_snapshot_preview1",
+        "fd_write",
+        "(param i32 i32 i32 i32) (result i32)",
+    );
+
+    assert!(validate_module(&logging, ModuleType::Hook, &Capabilities::default()).is_ok());
+    assert!(validate_module(&stdio, ModuleType::Hook, &Capabilities::default()).is_ok());
+}
+
+#[test]
+fn test_validate_module_requires_network_grant() {
+    let bytes = hook_importing("mci", "http_request", "(param i32 i32) (result i64)");
+    let result = validate_module(&bytes, ModuleType::Hook, &Capabilities::default());
+    assert_bad_request(result, "'mci.http_request' requires allowed hosts");
+
+    let granted = Capabilities {
+        allowed_hosts: vec!["api.example.com".to_string()],
+        ..Default::default()
+    };
+    assert!(validate_module(&bytes, ModuleType::Hook, &granted).is_ok());
+}
+
+#[test]
+fn test_validate_module_requires_filesystem_grant() {
+    let bytes = hook_importing(
+        "wasi_snapshot_preview1",
+        "path_open",
+        "(param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)",
+    );
+    let result = validate_module(&bytes, ModuleType::Hook, &Capabilities::default());
+    assert_bad_request(result, "requires a preopened directory");
+}
+
+#[test]
+fn test_validate_module_rejects_unknown_imports() {
+    let bytes = hook_importing("env", "system", "(param i32)");
+    let result = validate_module(&bytes, ModuleType::Hook, &Capabilities::default());
+    assert_bad_request(result, "'env.system' is from an unknown import module");
+}
+
+#[test]
+fn test_module_validator_accepts_modules_in_pieces() {
+    let bytes = interceptor_module();
+    let mut validator = ModuleValidator::new(ModuleType::Interceptor, &Capabilities::default());
+    for byte in bytes.chunks(3) {
+        validator.update(byte).unwrap();
+    }
+
+    assert!(validator.finish().is_ok());
+}
+
+#[test]
+fn test_module_validator_rejects_truncated_modules() {
+    let bytes = interceptor_module();
+    let mut validator = ModuleValidator::new(ModuleType::Interceptor, &Capabilities::default());
+    validator.update(&bytes[..bytes.len() - 4]).unwrap();
+
+    assert_bad_request(validator.finish(), "Invalid WebAssembly module");
+}
+
+#[test]
+fn test_module_validator_rejects_oversized_sections() {
+    let mut validator = ModuleValidator::new(ModuleType::Interceptor, &Capabilities::default());
+    validator.update(b"\0asm\x01\0\0\0").unwrap();
+    // A custom section declaring a 64 MiB payload is rejected from its header.
+    let result = validator.update(&[0x00, 0x80, 0x80, 0x80, 0x20, 0x04, b'n', b'a', b'm', b'e']);
+
+    assert_bad_request(result, "sections may be at most 32 MiB");
+}
@@ -8,22 +8,30 @@ use bytes::Bytes;
 use http_body_util::BodyExt as _;
 use mci::{
     app,
-    models::{Definition, Module, ModuleType},
+    errors::AppError,
+    events::{EventBus, LifecycleEvent},
+    mcp::sessions::Sessions,
+    models::{Definition, ExecutionStatus, Module, ModuleType},
+    runtime::Runtime,
+    s3::{self, UploadOptions},
+    secrets::Keyring,
+    services::{configuration_services, executions_services, hooks_services},
     AppState,
 };
 use serde_json::json;
 use sha2::{Digest, Sha256};
+use std::sync::Arc;
 use testcontainers_modules::{minio, postgres, testcontainers::ContainerAsync};
 use tower::ServiceExt;
-use wiremock::matchers::{method, path};
+use wiremock::matchers::{header, method, path, query_param};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 mod common;
 
-async fn setup_app() -> Result<(
+async fn setup_state() -> Result<(
     ContainerAsync<postgres::Postgres>,
     ContainerAsync<minio::MinIO>,
-    Router,
+    AppState,
 )> {
     let (pg_container, pool) = common::initialize_pg().await?;
     let (s3_container, s3_client) = common::initialize_s3().await?;
@@ -39,10 +47,24 @@ async fn setup_app() -> Result<(
         db_pool: pool,
         http_client: reqwest::Client::new(),
         s3_client,
+        upload_options: UploadOptions::default(),
+        keyring: Arc::new(Keyring::new(Some(&common::master_key()))?),
+        runtime: Runtime::new()?,
+        events: EventBus::new(),
+        mcp_sessions: Sessions::new(),
     };
-    let router = app(state);
 
-    Ok((pg_container, s3_container, router))
+    Ok((pg_container, s3_container, state))
+}
+
+async fn setup_app() -> Result<(
+    ContainerAsync<postgres::Postgres>,
+    ContainerAsync<minio::MinIO>,
+    Router,
+)> {
+    let (pg_container, s3_container, state) = setup_state().await?;
+
+    Ok((pg_container, s3_container, app(state)))
 }
 
 async fn read_body(response: axum::response::Response) -> Result<Bytes> {
@@ -84,7 +106,7 @@ async fn create_get_update_delete_definition_flow() -> Result<()> {
 
     let temp_dir = tempfile::TempDir::new()?;
     let file_path = temp_dir.path().join("def.json");
-    let file_body = br#"{\"hello\":\"world\"}"#;
+    let file_body = &common::definition_document("API Name")[..];
 
     std::fs::write(&file_path, file_body)?;
 
@@ -195,12 +217,294 @@ async fn create_get_update_delete_definition_flow() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn create_rejects_ids_that_would_reach_other_objects() -> Result<()> {
+    let (pg_container, s3_container, app) = setup_app().await?;
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let file_path = temp_dir.path().join("def.json");
+    let file_body = common::definition_document("Sneaky");
+    std::fs::write(&file_path, &file_body)?;
+
+    for uri in ["/definitions", "/modules"] {
+        let response = send_json(
+            &app,
+            "POST",
+            uri,
+            json!({
+                "id": "secrets/victim",
+                "name": "Sneaky",
+                "type": "sandbox",
+                "description": "Overwrites another resource's secrets",
+                "file_url": file_path.to_string_lossy(),
+                "digest": format!("sha256:{:x}", Sha256::digest(&file_body)),
+            }),
+        )
+        .await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+    }
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn create_definition_rejects_malformed_documents() -> Result<()> {
+    let (pg_container, s3_container, app) = setup_app().await?;
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let file_path = temp_dir.path().join("def.json");
+    let file_body = br#"{"format_version":1,"name":"","input_schema":{"type":"array"}}"#;
+    std::fs::write(&file_path, file_body)?;
+
+    let response = send_json(
+        &app,
+        "POST",
+        "/definitions",
+        json!({
+            "id": "malformed",
+            "name": "Malformed",
+            "type": "remote",
+            "description": "Missing most of its document",
+            "file_url": file_path.to_string_lossy(),
+            "digest": format!("sha256:{:x}", Sha256::digest(file_body)),
+        }),
+    )
+    .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = serde_json::from_slice(&read_body(response).await?)?;
+    assert_eq!(body["error"]["type"], "validation_error");
+    let pointers: Vec<&str> = body["error"]["details"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|violation| violation["pointer"].as_str())
+        .collect();
+    assert!(pointers.contains(&"/name"));
+    assert!(pointers.contains(&"/input_schema/type"));
+
+    let missing = send_json(&app, "GET", "/definitions/malformed", json!({})).await?;
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn deleting_removes_stored_objects() -> Result<()> {
+    let (pg_container, s3_container, state) = setup_state().await?;
+    let s3_client = state.s3_client.clone();
+    let app = app(state);
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let definition_path = temp_dir.path().join("def.json");
+    let definition_body = common::definition_document("Short Lived");
+    std::fs::write(&definition_path, &definition_body)?;
+    let module_path = temp_dir.path().join("sandbox.wasm");
+    let module_body = common::wasm_module(ModuleType::Sandbox, "short-lived");
+    std::fs::write(&module_path, &module_body)?;
+
+    let cases = [
+        (
+            "definitions",
+            "secrets",
+            json!({
+                "id": "short-lived",
+                "name": "Short Lived",
+                "type": "remote",
+                "description": "Deleted right away",
+                "file_url": definition_path.to_string_lossy(),
+                "digest": format!("sha256:{:x}", Sha256::digest(&definition_body)),
+                "default_configuration": { "region": "eu" },
+            }),
+        ),
+        (
+            "modules",
+            "secret",
+            json!({
+                "id": "short-lived-mod",
+                "name": "Short Lived Module",
+                "type": "sandbox",
+                "description": "Deleted right away",
+                "file_url": module_path.to_string_lossy(),
+                "digest": format!("sha256:{:x}", Sha256::digest(&module_body)),
+                "default_configuration": { "region": "eu" },
+            }),
+        ),
+    ];
+
+    for (bucket, secrets_path, payload) in cases {
+        let uri = format!("/{}/{}", bucket, payload["id"].as_str().unwrap());
+
+        let response = send_json(&app, "POST", &format!("/{}", bucket), payload).await?;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let response = send_json(
+            &app,
+            "PUT",
+            &format!("{}/{}", uri, secrets_path),
+            json!({ "token": "hunter2" }),
+        )
+        .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!s3::list_keys(&s3_client, bucket, "").await?.is_empty());
+
+        let response = send_json(&app, "DELETE", &uri, json!({})).await?;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let remaining = s3::list_keys(&s3_client, bucket, "").await?;
+        assert!(
+            remaining.is_empty(),
+            "left behind in {}: {:?}",
+            bucket,
+            remaining
+        );
+    }
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unpinned_installs_require_opt_in_and_report_computed_digest() -> Result<()> {
+    let (pg_container, s3_container, app) = setup_app().await?;
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let definition_path = temp_dir.path().join("def.json");
+    let definition_body = common::definition_document("Unpinned");
+    std::fs::write(&definition_path, &definition_body)?;
+    let module_path = temp_dir.path().join("sandbox.wasm");
+    let module_body = common::wasm_module(ModuleType::Sandbox, "unpinned");
+    std::fs::write(&module_path, &module_body)?;
+
+    let definition = json!({
+        "id": "unpinned",
+        "name": "Unpinned",
+        "type": "remote",
+        "description": "Built in CI",
+        "file_url": definition_path.to_string_lossy(),
+    });
+    let module = json!({
+        "id": "unpinned-mod",
+        "name": "Unpinned Module",
+        "type": "sandbox",
+        "description": "Built in CI",
+        "file_url": module_path.to_string_lossy(),
+    });
+
+    let cases = [
+        ("/definitions", definition, definition_body),
+        ("/modules", module, module_body),
+    ];
+    for (uri, mut payload, body) in cases {
+        let response = send_json(&app, "POST", uri, payload.clone()).await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+
+        payload["allow_unpinned"] = json!(true);
+        let response = send_json(&app, "POST", uri, payload).await?;
+        assert_eq!(response.status(), StatusCode::CREATED, "{}", uri);
+
+        let created: serde_json::Value = serde_json::from_slice(&read_body(response).await?)?;
+        assert_eq!(
+            created["digest"],
+            format!("sha256:{:x}", Sha256::digest(&body)),
+            "{}",
+            uri
+        );
+    }
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn upgrades_keep_the_installed_pin_unless_the_operator_drops_it() -> Result<()> {
+    let (pg_container, s3_container, app) = setup_app().await?;
+
+    let mock = MockServer::start().await;
+    let v1_body = &common::definition_document("Pinned")[..];
+    let v2_body = &common::definition_document("Pinned v2")[..];
+
+    Mock::given(method("GET"))
+        .and(path("/v1.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(v1_body, "application/json"))
+        .mount(&mock)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/registry.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "pinned",
+            "name": "Pinned",
+            "type": "reg-type",
+            "description": "From registry",
+            "file_url": format!("{}/v1.json", mock.uri()),
+            "digest": format!("sha256:{:x}", Sha256::digest(v1_body)),
+        })))
+        .mount(&mock)
+        .await;
+
+    let install = json!({ "source": format!("{}/registry.json", mock.uri()) });
+    let response = send_json(&app, "POST", "/definitions/install", install).await?;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    mock.reset().await;
+    Mock::given(method("GET"))
+        .and(path("/v2.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(v2_body, "application/json"))
+        .mount(&mock)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/registry.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "pinned",
+            "name": "Pinned v2",
+            "type": "reg-type",
+            "description": "From registry",
+            "file_url": format!("{}/v2.json", mock.uri()),
+            "allow_unpinned": true,
+        })))
+        .mount(&mock)
+        .await;
+
+    let response = send_json(&app, "POST", "/definitions/pinned/update", json!({})).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = send_json(
+        &app,
+        "POST",
+        "/definitions/pinned/update?allow_unpinned=true",
+        json!({}),
+    )
+    .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let upgraded: Definition = serde_json::from_slice(&read_body(response).await?)?;
+    assert_eq!(
+        upgraded.digest,
+        format!("sha256:{:x}", Sha256::digest(v2_body))
+    );
+    assert!(!upgraded.is_pinned);
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn update_definition_rejects_digest_without_file_url() -> Result<()> {
     let (pg_container, s3_container, app) = setup_app().await?;
 
     let mock = MockServer::start().await;
-    let file_body = b"some-content";
+    let file_body = &common::definition_document("Update Test")[..];
     let digest = format!("sha256:{:x}", Sha256::digest(file_body));
 
     Mock::given(method("GET"))
@@ -250,7 +554,7 @@ async fn update_definition_rejects_digest_without_file_url() -> Result<()> {
 
     assert_eq!(bad_resp.status(), StatusCode::BAD_REQUEST);
 
-    let new_body = b"new-content";
+    let new_body = &common::definition_document("Update Test v2")[..];
     let new_digest = format!("sha256:{:x}", Sha256::digest(new_body));
 
     Mock::given(method("GET"))
@@ -289,7 +593,7 @@ async fn install_and_upgrade_definition_from_http_registry() -> Result<()> {
     let (pg_container, s3_container, app) = setup_app().await?;
 
     let mock = MockServer::start().await;
-    let def_v1_body = b"definition-v1-content";
+    let def_v1_body = &common::definition_document("Registry Name")[..];
     let digest_v1 = format!("sha256:{:x}", Sha256::digest(def_v1_body));
 
     Mock::given(method("GET"))
@@ -335,7 +639,7 @@ async fn install_and_upgrade_definition_from_http_registry() -> Result<()> {
     assert_eq!(installed.id, "api-def-2");
     assert_eq!(installed.source_url.as_deref(), Some(registry_url.as_str()));
 
-    let def_v2_body = b"definition-v2-content";
+    let def_v2_body = &common::definition_document("Registry Name v2")[..];
     let digest_v2 = format!("sha256:{:x}", Sha256::digest(def_v2_body));
 
     mock.reset().await;
@@ -420,11 +724,11 @@ async fn create_get_update_delete_module_flow() -> Result<()> {
 
     let temp_dir = tempfile::TempDir::new()?;
     let file_path = temp_dir.path().join("module.wasm");
-    let file_body = b"\0asm\x01\0\0\0";
+    let file_body = common::wasm_module(ModuleType::Language, "api-mod-1");
 
-    std::fs::write(&file_path, file_body)?;
+    std::fs::write(&file_path, &file_body)?;
 
-    let digest = format!("sha256:{:x}", Sha256::digest(file_body));
+    let digest = format!("sha256:{:x}", Sha256::digest(&file_body));
 
     let payload = json!({
         "id": "api-mod-1",
@@ -473,10 +777,16 @@ async fn create_get_update_delete_module_flow() -> Result<()> {
     let fetched: Module = serde_json::from_slice(&get_body)?;
 
     assert_eq!(fetched.description, "Module via API test");
+    assert_eq!(fetched.max_memory_bytes, 64 * 1024 * 1024);
+    assert_eq!(fetched.fuel_limit, 1_000_000_000);
+    assert_eq!(fetched.timeout_ms, 5000);
 
     let update_payload = json!({
         "name": "API Module Updated",
         "description": "Updated module description",
+        "max_memory_bytes": 16 * 1024 * 1024,
+        "fuel_limit": 5_000_000,
+        "timeout_ms": 250,
     });
 
     let patch_resp = app
@@ -498,6 +808,23 @@ async fn create_get_update_delete_module_flow() -> Result<()> {
 
     assert_eq!(updated.name, "API Module Updated");
     assert_eq!(updated.description, "Updated module description");
+    assert_eq!(updated.max_memory_bytes, 16 * 1024 * 1024);
+    assert_eq!(updated.fuel_limit, 5_000_000);
+    assert_eq!(updated.timeout_ms, 250);
+
+    let bad_limits_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PATCH")
+                .uri("/modules/api-mod-1")
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec(&json!({ "timeout_ms": 0 }))?))
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(bad_limits_resp.status(), StatusCode::BAD_REQUEST);
 
     let delete_resp = app
         .clone()
@@ -536,12 +863,14 @@ async fn update_module_rejects_digest_without_file_url() -> Result<()> {
     let (pg_container, s3_container, app) = setup_app().await?;
 
     let mock = MockServer::start().await;
-    let file_body = b"\0asm\x01\0\0\0module";
-    let digest = format!("sha256:{:x}", Sha256::digest(file_body));
+    let file_body = common::wasm_module(ModuleType::Sandbox, "module");
+    let digest = format!("sha256:{:x}", Sha256::digest(&file_body));
 
     Mock::given(method("GET"))
         .and(path("/module.wasm"))
-        .respond_with(ResponseTemplate::new(200).set_body_raw(file_body, "application/json"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_raw(file_body.clone(), "application/json"),
+        )
         .mount(&mock)
         .await;
 
@@ -586,8 +915,8 @@ async fn update_module_rejects_digest_without_file_url() -> Result<()> {
 
     assert_eq!(bad_resp.status(), StatusCode::BAD_REQUEST);
 
-    let new_body = b"\0asm\x01\0\0\0module2";
-    let new_digest = format!("sha256:{:x}", Sha256::digest(new_body));
+    let new_body = common::wasm_module(ModuleType::Sandbox, "module2");
+    let new_digest = format!("sha256:{:x}", Sha256::digest(&new_body));
 
     Mock::given(method("GET"))
         .and(path("/module2.wasm"))
@@ -625,8 +954,8 @@ async fn install_and_upgrade_module_from_http_registry() -> Result<()> {
     let (pg_container, s3_container, app) = setup_app().await?;
 
     let mock = MockServer::start().await;
-    let mod_v1_body = b"\0asm\x01\0\0\0v1";
-    let digest_v1 = format!("sha256:{:x}", Sha256::digest(mod_v1_body));
+    let mod_v1_body = common::wasm_module(ModuleType::Interceptor, "v1");
+    let digest_v1 = format!("sha256:{:x}", Sha256::digest(&mod_v1_body));
 
     Mock::given(method("GET"))
         .and(path("/mod_v1.wasm"))
@@ -671,8 +1000,8 @@ async fn install_and_upgrade_module_from_http_registry() -> Result<()> {
     assert_eq!(installed.id, "api-mod-2");
     assert_eq!(installed.source_url.as_deref(), Some(registry_url.as_str()));
 
-    let mod_v2_body = b"\0asm\x01\0\0\0v2";
-    let digest_v2 = format!("sha256:{:x}", Sha256::digest(mod_v2_body));
+    let mod_v2_body = common::wasm_module(ModuleType::Interceptor, "v2");
+    let digest_v2 = format!("sha256:{:x}", Sha256::digest(&mod_v2_body));
 
     mock.reset().await;
 
@@ -722,3 +1051,2027 @@ async fn install_and_upgrade_module_from_http_registry() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn install_module_requires_approved_capabilities() -> Result<()> {
+    let (pg_container, s3_container, app) = setup_app().await?;
+
+    let mock = MockServer::start().await;
+    let module_body = common::wasm_module(ModuleType::Hook, "caps");
+    let requested = json!({
+        "allowed_hosts": ["api.example.com"],
+        "environment": ["LANG"],
+    });
+
+    Mock::given(method("GET"))
+        .and(path("/caps.wasm"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_raw(module_body.clone(), "application/wasm"),
+        )
+        .mount(&mock)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/registry.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "caps-mod",
+            "name": "Capability Module",
+            "type": "hook",
+            "description": "Module requesting capabilities",
+            "file_url": format!("{}/caps.wasm", mock.uri()),
+            "digest": format!("sha256:{:x}", Sha256::digest(&module_body)),
+            "capabilities": requested.clone(),
+        })))
+        .mount(&mock)
+        .await;
+
+    let registry_url = format!("{}/registry.json", mock.uri());
+
+    let install = |capabilities: serde_json::Value| {
+        let payload = json!({ "source": registry_url, "capabilities": capabilities });
+        Request::builder()
+            .method("POST")
+            .uri("/modules/install")
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+            .unwrap()
+    };
+
+    let denied_resp = app
+        .clone()
+        .oneshot(install(json!({ "environment": ["LANG"] })))
+        .await?;
+
+    assert_eq!(denied_resp.status(), StatusCode::BAD_REQUEST);
+
+    let denied_body: serde_json::Value = serde_json::from_slice(&read_body(denied_resp).await?)?;
+    assert!(denied_body["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("host 'api.example.com'"));
+
+    let approved_resp = app.clone().oneshot(install(requested.clone())).await?;
+
+    assert_eq!(approved_resp.status(), StatusCode::CREATED);
+
+    let installed: Module = serde_json::from_slice(&read_body(approved_resp).await?)?;
+    assert_eq!(
+        installed.capabilities.allowed_hosts,
+        vec!["api.example.com"]
+    );
+    assert_eq!(installed.capabilities.environment, vec!["LANG"]);
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn create_module_rejects_invalid_wasm() -> Result<()> {
+    let (pg_container, s3_container, app) = setup_app().await?;
+
+    let mock = MockServer::start().await;
+    let html_body = b"<html><body>Not Found</body></html>";
+    let sandbox_body = common::wasm_module(ModuleType::Sandbox, "sandbox");
+
+    Mock::given(method("GET"))
+        .and(path("/error.wasm"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(html_body, "text/html"))
+        .mount(&mock)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/sandbox.wasm"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_raw(sandbox_body.clone(), "application/wasm"),
+        )
+        .mount(&mock)
+        .await;
+
+    let cases = [
+        (
+            "error.wasm",
+            format!("sha256:{:x}", Sha256::digest(html_body)),
+        ),
+        (
+            "sandbox.wasm",
+            format!("sha256:{:x}", Sha256::digest(&sandbox_body)),
+        ),
+    ];
+
+    for (file, digest) in cases {
+        let payload = json!({
+            "id": "bad-mod",
+            "name": "Bad Module",
+            "type": "language",
+            "description": "Not a language module",
+            "file_url": format!("{}/{}", mock.uri(), file),
+            "digest": digest,
+        });
+
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/modules")
+                    .header(http::header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(serde_json::to_vec(&payload)?))
+                    .unwrap(),
+            )
+            .await?;
+
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    let get_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/modules/bad-mod")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(get_resp.status(), StatusCode::NOT_FOUND);
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn definition_configuration_flow() -> Result<()> {
+    let (pg_container, s3_container, app) = setup_app().await?;
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let file_path = temp_dir.path().join("def.json");
+    let file_body = &common::definition_document("Configured")[..];
+
+    std::fs::write(&file_path, file_body)?;
+
+    let payload = json!({
+        "id": "cfg-def",
+        "name": "Config Definition",
+        "type": "api-type",
+        "description": "Configuration test",
+        "file_url": file_path.to_string_lossy(),
+        "digest": format!("sha256:{:x}", Sha256::digest(file_body)),
+        "default_configuration": { "timeout": 30, "region": "eu" },
+    });
+
+    let create_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/definitions")
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec(&payload)?))
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(create_resp.status(), StatusCode::CREATED);
+
+    let get_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/definitions/cfg-def/configuration")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(get_resp.status(), StatusCode::OK);
+
+    let configuration: serde_json::Value = serde_json::from_slice(&read_body(get_resp).await?)?;
+    assert_eq!(configuration, json!({ "timeout": 30, "region": "eu" }));
+
+    let patch_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PATCH")
+                .uri("/definitions/cfg-def/configuration")
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec(
+                    &json!({ "timeout": 60, "region": null }),
+                )?))
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(patch_resp.status(), StatusCode::OK);
+
+    let patched: serde_json::Value = serde_json::from_slice(&read_body(patch_resp).await?)?;
+    assert_eq!(patched, json!({ "timeout": 60 }));
+
+    let put_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/definitions/cfg-def/configuration")
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec(&json!([
+                    "not", "an", "object"
+                ]))?))
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(put_resp.status(), StatusCode::BAD_REQUEST);
+
+    let reset_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/definitions/cfg-def/configuration")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(reset_resp.status(), StatusCode::OK);
+
+    let reset: serde_json::Value = serde_json::from_slice(&read_body(reset_resp).await?)?;
+    assert_eq!(reset, json!({ "timeout": 30, "region": "eu" }));
+
+    let missing_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/definitions/missing/configuration")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(missing_resp.status(), StatusCode::NOT_FOUND);
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn definition_configuration_is_validated_against_schema() -> Result<()> {
+    let (pg_container, s3_container, app) = setup_app().await?;
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let file_path = temp_dir.path().join("def.json");
+    let file_body = &common::definition_document("Configured")[..];
+
+    std::fs::write(&file_path, file_body)?;
+
+    let schema = json!({
+        "type": "object",
+        "properties": { "timeout": { "type": "integer", "minimum": 1 } },
+        "additionalProperties": false
+    });
+
+    let payload = json!({
+        "id": "schema-def",
+        "name": "Schema Definition",
+        "type": "api-type",
+        "description": "Configuration schema test",
+        "file_url": file_path.to_string_lossy(),
+        "digest": format!("sha256:{:x}", Sha256::digest(file_body)),
+        "default_configuration": { "timeout": 30 },
+        "configuration_schema": schema,
+    });
+
+    let create_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/definitions")
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec(&payload)?))
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(create_resp.status(), StatusCode::CREATED);
+
+    let schema_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/definitions/schema-def/configuration/schema")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(schema_resp.status(), StatusCode::OK);
+
+    let stored_schema: serde_json::Value = serde_json::from_slice(&read_body(schema_resp).await?)?;
+    assert_eq!(stored_schema, schema);
+
+    let bad_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PATCH")
+                .uri("/definitions/schema-def/configuration")
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec(
+                    &json!({ "timeout": "soon" }),
+                )?))
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(bad_resp.status(), StatusCode::BAD_REQUEST);
+
+    let error: serde_json::Value = serde_json::from_slice(&read_body(bad_resp).await?)?;
+    assert_eq!(error["error"]["type"], "validation_error");
+    assert_eq!(error["error"]["details"][0]["pointer"], "/timeout");
+
+    let good_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/definitions/schema-def/configuration")
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec(&json!({ "timeout": 5 }))?))
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(good_resp.status(), StatusCode::OK);
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn manifest_schema_change_rejects_stored_configuration_unless_reset() -> Result<()> {
+    let (pg_container, s3_container, state) = setup_state().await?;
+    let s3_client = &state.s3_client;
+
+    let old_schema = json!({
+        "type": "object",
+        "properties": { "timeout": { "type": "integer" } }
+    });
+    let new_schema = json!({
+        "type": "object",
+        "properties": { "timeout": { "type": "string" } }
+    });
+
+    configuration_services::store_manifest_configuration(
+        s3_client,
+        "definitions",
+        "upgraded",
+        Some(&json!({ "timeout": 30 })),
+        Some(&old_schema),
+        false,
+    )
+    .await?;
+    configuration_services::set_configuration(
+        s3_client,
+        "definitions",
+        "upgraded",
+        json!({ "timeout": 5 }),
+    )
+    .await?;
+
+    let rejected = configuration_services::store_manifest_configuration(
+        s3_client,
+        "definitions",
+        "upgraded",
+        Some(&json!({ "timeout": "30s" })),
+        Some(&new_schema),
+        false,
+    )
+    .await;
+    assert!(matches!(rejected, Err(AppError::SchemaValidation(_))));
+
+    let current =
+        configuration_services::get_configuration(s3_client, "definitions", "upgraded").await?;
+    assert_eq!(current, json!({ "timeout": 5 }));
+    let schema =
+        configuration_services::get_configuration_schema(s3_client, "definitions", "upgraded")
+            .await?;
+    assert_eq!(schema, Some(old_schema));
+
+    configuration_services::store_manifest_configuration(
+        s3_client,
+        "definitions",
+        "upgraded",
+        Some(&json!({ "timeout": "30s" })),
+        Some(&new_schema),
+        true,
+    )
+    .await?;
+
+    let current =
+        configuration_services::get_configuration(s3_client, "definitions", "upgraded").await?;
+    assert_eq!(current, json!({ "timeout": "30s" }));
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn definition_secrets_are_write_only() -> Result<()> {
+    let (pg_container, s3_container, app) = setup_app().await?;
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let file_path = temp_dir.path().join("def.json");
+    let file_body = &common::definition_document("Configured")[..];
+
+    std::fs::write(&file_path, file_body)?;
+
+    let payload = json!({
+        "id": "secret-def",
+        "name": "Secret Definition",
+        "type": "api-type",
+        "description": "Secrets test",
+        "file_url": file_path.to_string_lossy(),
+        "digest": format!("sha256:{:x}", Sha256::digest(file_body)),
+    });
+
+    let create_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/definitions")
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec(&payload)?))
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(create_resp.status(), StatusCode::CREATED);
+
+    let put_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/definitions/secret-def/secret")
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec(
+                    &json!({ "API_KEY": "plaintext-key", "TOKEN": "plaintext-token" }),
+                )?))
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(put_resp.status(), StatusCode::OK);
+
+    let put_body = read_body(put_resp).await?;
+    assert!(!String::from_utf8_lossy(&put_body).contains("plaintext"));
+
+    let patch_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PATCH")
+                .uri("/definitions/secret-def/secret")
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec(&json!({ "TOKEN": null }))?))
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(patch_resp.status(), StatusCode::OK);
+
+    let get_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/definitions/secret-def/secret")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(get_resp.status(), StatusCode::OK);
+
+    let masked: serde_json::Value = serde_json::from_slice(&read_body(get_resp).await?)?;
+    assert_eq!(masked, json!({ "API_KEY": "********" }));
+
+    let reset_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/definitions/secret-def/secret")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(reset_resp.status(), StatusCode::NO_CONTENT);
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn module_configuration_and_secrets_flow() -> Result<()> {
+    let (pg_container, s3_container, app) = setup_app().await?;
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let file_path = temp_dir.path().join("module.wasm");
+    let file_body = common::wasm_module(ModuleType::Proxy, "cfg-mod");
+
+    std::fs::write(&file_path, &file_body)?;
+
+    let payload = json!({
+        "id": "cfg-mod",
+        "name": "Config Module",
+        "type": "proxy",
+        "description": "Module configuration test",
+        "file_url": file_path.to_string_lossy(),
+        "digest": format!("sha256:{:x}", Sha256::digest(&file_body)),
+        "default_configuration": { "retries": 3 },
+        "configuration_schema": {
+            "type": "object",
+            "properties": { "retries": { "type": "integer" } }
+        },
+    });
+
+    let create_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/modules")
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec(&payload)?))
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(create_resp.status(), StatusCode::CREATED);
+
+    let get_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/modules/cfg-mod/configuration")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(get_resp.status(), StatusCode::OK);
+
+    let configuration: serde_json::Value = serde_json::from_slice(&read_body(get_resp).await?)?;
+    assert_eq!(configuration, json!({ "retries": 3 }));
+
+    let bad_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/modules/cfg-mod/configuration")
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec(
+                    &json!({ "retries": "many" }),
+                )?))
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(bad_resp.status(), StatusCode::BAD_REQUEST);
+
+    let schema_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/modules/cfg-mod/configuration/schema")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(schema_resp.status(), StatusCode::OK);
+
+    let secret_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/modules/cfg-mod/secret")
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec(
+                    &json!({ "UPSTREAM_TOKEN": "plaintext-token" }),
+                )?))
+                .unwrap(),
+        )
+        .await?;
+
+    assert_eq!(secret_resp.status(), StatusCode::OK);
+
+    let masked: serde_json::Value = serde_json::from_slice(&read_body(secret_resp).await?)?;
+    assert_eq!(masked, json!({ "UPSTREAM_TOKEN": "********" }));
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+async fn send_json(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    body: serde_json::Value,
+) -> Result<axum::response::Response> {
+    Ok(app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec(&body)?))
+                .unwrap(),
+        )
+        .await?)
+}
+
+async fn send_mcp(
+    app: &Router,
+    session_id: &str,
+    body: serde_json::Value,
+) -> Result<axum::response::Response> {
+    Ok(app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/mcp")
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .header("mcp-session-id", session_id)
+                .body(Body::from(serde_json::to_vec(&body)?))
+                .unwrap(),
+        )
+        .await?)
+}
+
+#[tokio::test]
+async fn interceptor_chain_can_reject_invocations() -> Result<()> {
+    let (pg_container, s3_container, app) = setup_app().await?;
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let definition_path = temp_dir.path().join("def.json");
+    let definition_body = &common::definition_document("Invoked")[..];
+    std::fs::write(&definition_path, definition_body)?;
+
+    let create_def = send_json(
+        &app,
+        "POST",
+        "/definitions",
+        json!({
+            "id": "guarded-def",
+            "name": "Guarded",
+            "type": "api-type",
+            "description": "Definition behind an interceptor",
+            "file_url": definition_path.to_string_lossy(),
+            "digest": format!("sha256:{:x}", Sha256::digest(definition_body)),
+        }),
+    )
+    .await?;
+    assert_eq!(create_def.status(), StatusCode::CREATED);
+
+    let invoke_disabled =
+        send_json(&app, "POST", "/definitions/guarded-def/invoke", json!({})).await?;
+    assert_eq!(invoke_disabled.status(), StatusCode::CONFLICT);
+
+    let enable_def = send_json(
+        &app,
+        "PATCH",
+        "/definitions/guarded-def",
+        json!({ "is_enabled": true }),
+    )
+    .await?;
+    assert_eq!(enable_def.status(), StatusCode::OK);
+
+    let module_path = temp_dir.path().join("policy.wasm");
+    let module_body = common::wasm_responder(
+        ModuleType::Interceptor,
+        r#"{"action":"reject","reason":"contains PII"}"#,
+    );
+    std::fs::write(&module_path, &module_body)?;
+
+    let create_mod = send_json(
+        &app,
+        "POST",
+        "/modules",
+        json!({
+            "id": "policy",
+            "name": "Policy",
+            "type": "interceptor",
+            "description": "Rejects everything",
+            "file_url": module_path.to_string_lossy(),
+            "digest": format!("sha256:{:x}", Sha256::digest(&module_body)),
+        }),
+    )
+    .await?;
+    assert_eq!(create_mod.status(), StatusCode::CREATED);
+
+    let enable_mod = send_json(
+        &app,
+        "PATCH",
+        "/modules/policy",
+        json!({ "is_enabled": true }),
+    )
+    .await?;
+    assert_eq!(enable_mod.status(), StatusCode::OK);
+
+    let bind_resp = send_json(
+        &app,
+        "PUT",
+        "/modules/policy/interceptor",
+        json!({ "priority": 10, "definition_types": ["api-type"] }),
+    )
+    .await?;
+    assert_eq!(bind_resp.status(), StatusCode::OK);
+
+    let binding: serde_json::Value = serde_json::from_slice(&read_body(bind_resp).await?)?;
+    assert_eq!(binding["priority"], 10);
+    assert_eq!(binding["definition_types"], json!(["api-type"]));
+
+    let invoke_resp = send_json(
+        &app,
+        "POST",
+        "/definitions/guarded-def/invoke",
+        json!({ "arguments": { "email": "someone@example.com" } }),
+    )
+    .await?;
+    assert_eq!(invoke_resp.status(), StatusCode::FORBIDDEN);
+
+    let error: serde_json::Value = serde_json::from_slice(&read_body(invoke_resp).await?)?;
+    assert_eq!(error["error"]["type"], "forbidden");
+    assert!(error["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("contains PII"));
+
+    let unbind_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/modules/policy/interceptor")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await?;
+    assert_eq!(unbind_resp.status(), StatusCode::NO_CONTENT);
+
+    // With the interceptor gone the call reaches the definition, which has no
+    // executor for its type.
+    let invoke_resp = send_json(&app, "POST", "/definitions/guarded-def/invoke", json!({})).await?;
+    assert_eq!(invoke_resp.status(), StatusCode::BAD_REQUEST);
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn hooks_receive_subscribed_lifecycle_events() -> Result<()> {
+    let (pg_container, s3_container, state) = setup_state().await?;
+    let app = app(state.clone());
+    let mut events = state.events.subscribe();
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let module_path = temp_dir.path().join("notify.wasm");
+    let module_body = common::wasm_responder(ModuleType::Hook, r#"{"ok":true}"#);
+    std::fs::write(&module_path, &module_body)?;
+
+    let create_resp = send_json(
+        &app,
+        "POST",
+        "/modules",
+        json!({
+            "id": "notify",
+            "name": "Notify",
+            "type": "hook",
+            "description": "Notifies chat",
+            "file_url": module_path.to_string_lossy(),
+            "digest": format!("sha256:{:x}", Sha256::digest(&module_body)),
+        }),
+    )
+    .await?;
+    assert_eq!(create_resp.status(), StatusCode::CREATED);
+
+    let created = match events.recv().await? {
+        LifecycleEvent::ModuleCreated { module } => module,
+        other => panic!("unexpected event {:?}", other),
+    };
+    assert_eq!(created.id, "notify");
+
+    let invalid_resp = send_json(
+        &app,
+        "PUT",
+        "/modules/notify/hook",
+        json!({ "events": ["module_renamed"] }),
+    )
+    .await?;
+    assert_eq!(invalid_resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let subscribe_resp = send_json(
+        &app,
+        "PUT",
+        "/modules/notify/hook",
+        json!({ "events": ["definition_deleted", "module_disabled", "definition_deleted"] }),
+    )
+    .await?;
+    assert_eq!(subscribe_resp.status(), StatusCode::OK);
+
+    let subscription: serde_json::Value =
+        serde_json::from_slice(&read_body(subscribe_resp).await?)?;
+    assert_eq!(
+        subscription["events"],
+        json!(["definition_deleted", "module_disabled"])
+    );
+
+    let deleted = LifecycleEvent::DefinitionDeleted {
+        id: "some-def".to_string(),
+    };
+    // Hooks only run while enabled.
+    assert_eq!(hooks_services::dispatch(&state, &deleted).await?, 0);
+
+    let enable_resp = send_json(
+        &app,
+        "PATCH",
+        "/modules/notify",
+        json!({ "is_enabled": true }),
+    )
+    .await?;
+    assert_eq!(enable_resp.status(), StatusCode::OK);
+
+    assert!(matches!(
+        events.recv().await?,
+        LifecycleEvent::ModuleUpdated { .. }
+    ));
+    assert!(matches!(
+        events.recv().await?,
+        LifecycleEvent::ModuleEnabled { .. }
+    ));
+
+    let unchanged_resp = send_json(
+        &app,
+        "PATCH",
+        "/modules/notify",
+        json!({ "is_enabled": true }),
+    )
+    .await?;
+    assert_eq!(unchanged_resp.status(), StatusCode::OK);
+
+    assert!(matches!(
+        events.recv().await?,
+        LifecycleEvent::ModuleUpdated { .. }
+    ));
+    // Already enabled, so no second `module_enabled`.
+    assert!(events.try_recv().is_err());
+
+    assert_eq!(hooks_services::dispatch(&state, &deleted).await?, 1);
+    assert_eq!(
+        hooks_services::dispatch(
+            &state,
+            &LifecycleEvent::ModuleDeleted {
+                id: "other".to_string()
+            }
+        )
+        .await?,
+        0
+    );
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn approving_hooks_can_reject_changes() -> Result<()> {
+    let (pg_container, s3_container, app) = setup_app().await?;
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let module_path = temp_dir.path().join("gate.wasm");
+    let module_body = common::wasm_responder(
+        ModuleType::Hook,
+        r#"{"allow":false,"reason":"change freeze"}"#,
+    );
+    std::fs::write(&module_path, &module_body)?;
+
+    let module_payload = |id: &str| {
+        json!({
+            "id": id,
+            "name": "Gate",
+            "type": "hook",
+            "description": "Freezes changes",
+            "file_url": module_path.to_string_lossy(),
+            "digest": format!("sha256:{:x}", Sha256::digest(&module_body)),
+        })
+    };
+    let create_resp = send_json(&app, "POST", "/modules", module_payload("gate")).await?;
+    assert_eq!(create_resp.status(), StatusCode::CREATED);
+    let enable_resp = send_json(
+        &app,
+        "PATCH",
+        "/modules/gate",
+        json!({ "is_enabled": true }),
+    )
+    .await?;
+    assert_eq!(enable_resp.status(), StatusCode::OK);
+
+    let unapprovable_resp = send_json(
+        &app,
+        "PUT",
+        "/modules/gate/hook",
+        json!({ "approvals": ["definition_deleted"] }),
+    )
+    .await?;
+    assert_eq!(unapprovable_resp.status(), StatusCode::BAD_REQUEST);
+
+    let subscribe_resp = send_json(
+        &app,
+        "PUT",
+        "/modules/gate/hook",
+        json!({ "approvals": ["definition_enabled", "module_created"] }),
+    )
+    .await?;
+    assert_eq!(subscribe_resp.status(), StatusCode::OK);
+
+    let definition_path = temp_dir.path().join("def.json");
+    let definition_body = &common::definition_document("Frozen")[..];
+    std::fs::write(&definition_path, definition_body)?;
+    let create_def = send_json(
+        &app,
+        "POST",
+        "/definitions",
+        json!({
+            "id": "frozen-def",
+            "name": "Frozen",
+            "type": "http",
+            "description": "Waits for the freeze to end",
+            "file_url": definition_path.to_string_lossy(),
+            "digest": format!("sha256:{:x}", Sha256::digest(definition_body)),
+        }),
+    )
+    .await?;
+    assert_eq!(create_def.status(), StatusCode::CREATED);
+
+    let enable_def = send_json(
+        &app,
+        "PATCH",
+        "/definitions/frozen-def",
+        json!({ "is_enabled": true }),
+    )
+    .await?;
+    assert_eq!(enable_def.status(), StatusCode::FORBIDDEN);
+    let rejection: serde_json::Value = serde_json::from_slice(&read_body(enable_def).await?)?;
+    assert!(rejection["error"]["message"]
+        .as_str()
+        .is_some_and(|message| message.contains("change freeze")));
+
+    let get_def = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/definitions/frozen-def")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await?;
+    let definition: Definition = serde_json::from_slice(&read_body(get_def).await?)?;
+    assert!(!definition.is_enabled);
+
+    let rejected_module = send_json(&app, "POST", "/modules", module_payload("late")).await?;
+    assert_eq!(rejected_module.status(), StatusCode::FORBIDDEN);
+    let get_module = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/modules/late")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await?;
+    assert_eq!(get_module.status(), StatusCode::NOT_FOUND);
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn proxy_binding_forwards_invocations() -> Result<()> {
+    let (pg_container, s3_container, app) = setup_app().await?;
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let definition_path = temp_dir.path().join("def.json");
+    let definition_body = &common::definition_document("Invoked")[..];
+    std::fs::write(&definition_path, definition_body)?;
+
+    let create_def = send_json(
+        &app,
+        "POST",
+        "/definitions",
+        json!({
+            "id": "remote-def",
+            "name": "Remote",
+            "type": "remote",
+            "description": "Lives behind a gateway",
+            "file_url": definition_path.to_string_lossy(),
+            "digest": format!("sha256:{:x}", Sha256::digest(definition_body)),
+        }),
+    )
+    .await?;
+    assert_eq!(create_def.status(), StatusCode::CREATED);
+
+    let enable_def = send_json(
+        &app,
+        "PATCH",
+        "/definitions/remote-def",
+        json!({ "is_enabled": true }),
+    )
+    .await?;
+    assert_eq!(enable_def.status(), StatusCode::OK);
+
+    let module_path = temp_dir.path().join("gateway.wasm");
+    let module_body = common::wasm_responder(ModuleType::Proxy, r#"{"result":{"status":"ok"}}"#);
+    std::fs::write(&module_path, &module_body)?;
+
+    let create_mod = send_json(
+        &app,
+        "POST",
+        "/modules",
+        json!({
+            "id": "gateway",
+            "name": "Gateway",
+            "type": "proxy",
+            "description": "Speaks the gateway's auth",
+            "file_url": module_path.to_string_lossy(),
+            "digest": format!("sha256:{:x}", Sha256::digest(&module_body)),
+            "capabilities": { "allowed_hosts": ["gateway.internal"] },
+        }),
+    )
+    .await?;
+    assert_eq!(create_mod.status(), StatusCode::CREATED);
+
+    let enable_mod = send_json(
+        &app,
+        "PATCH",
+        "/modules/gateway",
+        json!({ "is_enabled": true }),
+    )
+    .await?;
+    assert_eq!(enable_mod.status(), StatusCode::OK);
+
+    let unreachable = send_json(
+        &app,
+        "PUT",
+        "/definitions/remote-def/proxy",
+        json!({ "module_id": "gateway", "upstream_url": "https://example.com/mcp" }),
+    )
+    .await?;
+    assert_eq!(unreachable.status(), StatusCode::BAD_REQUEST);
+
+    let bind_resp = send_json(
+        &app,
+        "PUT",
+        "/definitions/remote-def/proxy",
+        json!({ "module_id": "gateway", "upstream_url": "https://gateway.internal/mcp" }),
+    )
+    .await?;
+    assert_eq!(bind_resp.status(), StatusCode::OK);
+
+    let invoke_resp = send_json(
+        &app,
+        "POST",
+        "/definitions/remote-def/invoke",
+        json!({ "arguments": { "query": "status" } }),
+    )
+    .await?;
+    assert_eq!(invoke_resp.status(), StatusCode::OK);
+
+    let body: serde_json::Value = serde_json::from_slice(&read_body(invoke_resp).await?)?;
+    assert_eq!(body, json!({ "result": { "status": "ok" } }));
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+/// Answers every evaluation with `{"result": <request>}`.
+const ECHO_LANGUAGE: &str = r#"(module
+    (memory (export "memory") 1)
+    (data (i32.const 0) "{\"result\":")
+    (func (export "mci_alloc") (param i32) (result i32) i32.const 1024)
+    (func (export "mci_language_eval") (param $ptr i32) (param $len i32) (result i64)
+        (memory.copy (i32.const 10) (local.get $ptr) (local.get $len))
+        (i32.store8 (i32.add (i32.const 10) (local.get $len)) (i32.const 125))
+        (i64.extend_i32_u (i32.add (local.get $len) (i32.const 11)))))"#;
+
+#[tokio::test]
+async fn language_binding_evaluates_definition_source() -> Result<()> {
+    let (pg_container, s3_container, app) = setup_app().await?;
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let definition_path = temp_dir.path().join("script.echo");
+    let definition_body = &serde_json::to_vec(&json!({
+        "format_version": 1,
+        "name": "Echo Script",
+        "description": "Evaluated by the echo language",
+        "input_schema": { "type": "object" },
+        "source": "print(arguments)"
+    }))?[..];
+    std::fs::write(&definition_path, definition_body)?;
+
+    let create_def = send_json(
+        &app,
+        "POST",
+        "/definitions",
+        json!({
+            "id": "echo-script",
+            "name": "Echo Script",
+            "type": "echo-lang",
+            "description": "Evaluated by the echo language",
+            "file_url": definition_path.to_string_lossy(),
+            "digest": format!("sha256:{:x}", Sha256::digest(definition_body)),
+        }),
+    )
+    .await?;
+    assert_eq!(create_def.status(), StatusCode::CREATED);
+
+    let enable_def = send_json(
+        &app,
+        "PATCH",
+        "/definitions/echo-script",
+        json!({ "is_enabled": true }),
+    )
+    .await?;
+    assert_eq!(enable_def.status(), StatusCode::OK);
+
+    let unbound = send_json(&app, "POST", "/definitions/echo-script/invoke", json!({})).await?;
+    assert_eq!(unbound.status(), StatusCode::BAD_REQUEST);
+
+    let module_path = temp_dir.path().join("echo.wasm");
+    let module_body = wat::parse_str(ECHO_LANGUAGE)?;
+    std::fs::write(&module_path, &module_body)?;
+
+    let create_mod = send_json(
+        &app,
+        "POST",
+        "/modules",
+        json!({
+            "id": "echo",
+            "name": "Echo",
+            "type": "language",
+            "description": "Echoes its input",
+            "file_url": module_path.to_string_lossy(),
+            "digest": format!("sha256:{:x}", Sha256::digest(&module_body)),
+        }),
+    )
+    .await?;
+    assert_eq!(create_mod.status(), StatusCode::CREATED);
+
+    let enable_mod = send_json(
+        &app,
+        "PATCH",
+        "/modules/echo",
+        json!({ "is_enabled": true }),
+    )
+    .await?;
+    assert_eq!(enable_mod.status(), StatusCode::OK);
+
+    let bind_resp = send_json(
+        &app,
+        "PUT",
+        "/languages/echo-lang",
+        json!({ "module_id": "echo" }),
+    )
+    .await?;
+    assert_eq!(bind_resp.status(), StatusCode::OK);
+
+    let list_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/languages")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await?;
+    let bindings: serde_json::Value = serde_json::from_slice(&read_body(list_resp).await?)?;
+    assert_eq!(
+        bindings,
+        json!([{ "definition_type": "echo-lang", "module_id": "echo" }])
+    );
+
+    let invoke_resp = send_json(
+        &app,
+        "POST",
+        "/definitions/echo-script/invoke",
+        json!({ "arguments": { "x": 1 } }),
+    )
+    .await?;
+    assert_eq!(invoke_resp.status(), StatusCode::OK);
+
+    let body: serde_json::Value = serde_json::from_slice(&read_body(invoke_resp).await?)?;
+    assert_eq!(body["result"]["source"], "print(arguments)");
+    assert_eq!(body["result"]["arguments"], json!({ "x": 1 }));
+    assert_eq!(body["result"]["definition"]["type"], "echo-lang");
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+/// Prints "hello" and exits with status 3.
+const HELLO_SANDBOX: &str = r#"(module
+    (import "wasi_snapshot_preview1" "fd_write"
+        (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+    (memory (export "memory") 1)
+    (data (i32.const 8) "hello")
+    (func (export "_start")
+        (i32.store (i32.const 0) (i32.const 8))
+        (i32.store (i32.const 4) (i32.const 5))
+        (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 16)))
+        (call $proc_exit (i32.const 3))))"#;
+
+#[tokio::test]
+async fn sandbox_executions_are_recorded() -> Result<()> {
+    let (pg_container, s3_container, app) = setup_app().await?;
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let module_path = temp_dir.path().join("hello.wasm");
+    let module_body = wat::parse_str(HELLO_SANDBOX)?;
+    std::fs::write(&module_path, &module_body)?;
+
+    let create_mod = send_json(
+        &app,
+        "POST",
+        "/modules",
+        json!({
+            "id": "hello",
+            "name": "Hello",
+            "type": "sandbox",
+            "description": "Says hello",
+            "file_url": module_path.to_string_lossy(),
+            "digest": format!("sha256:{:x}", Sha256::digest(&module_body)),
+        }),
+    )
+    .await?;
+    assert_eq!(create_mod.status(), StatusCode::CREATED);
+
+    let disabled = send_json(&app, "POST", "/modules/hello/executions", json!({})).await?;
+    assert_eq!(disabled.status(), StatusCode::CONFLICT);
+
+    let enable_mod = send_json(
+        &app,
+        "PATCH",
+        "/modules/hello",
+        json!({ "is_enabled": true }),
+    )
+    .await?;
+    assert_eq!(enable_mod.status(), StatusCode::OK);
+
+    let run_resp = send_json(
+        &app,
+        "POST",
+        "/modules/hello/executions",
+        json!({ "args": ["--greet"], "stdin": "print('hi')" }),
+    )
+    .await?;
+    assert_eq!(run_resp.status(), StatusCode::CREATED);
+
+    let execution: serde_json::Value = serde_json::from_slice(&read_body(run_resp).await?)?;
+    assert_eq!(execution["status"], "completed");
+    assert_eq!(execution["exit_code"], 3);
+    assert_eq!(execution["stdout"], "hello");
+    assert!(execution["duration_ms"].is_i64());
+
+    let get_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!(
+                    "/modules/hello/executions/{}",
+                    execution["id"].as_str().unwrap()
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await?;
+    assert_eq!(get_resp.status(), StatusCode::OK);
+
+    let fetched: serde_json::Value = serde_json::from_slice(&read_body(get_resp).await?)?;
+    assert_eq!(fetched, execution);
+
+    let list_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/modules/hello/executions?status=completed")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await?;
+    let listed: Vec<serde_json::Value> = serde_json::from_slice(&read_body(list_resp).await?)?;
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0]["id"], execution["id"]);
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+/// Prints "he", a NUL byte and "llo".
+const NUL_SANDBOX: &str = r#"(module
+    (import "wasi_snapshot_preview1" "fd_write"
+        (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (memory (export "memory") 1)
+    (data (i32.const 8) "he\00llo")
+    (func (export "_start")
+        (i32.store (i32.const 0) (i32.const 8))
+        (i32.store (i32.const 4) (i32.const 6))
+        (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 16)))))"#;
+
+#[tokio::test]
+async fn sandbox_output_with_nul_bytes_is_recorded() -> Result<()> {
+    let (pg_container, s3_container, app) = setup_app().await?;
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let module_path = temp_dir.path().join("nul.wasm");
+    let module_body = wat::parse_str(NUL_SANDBOX)?;
+    std::fs::write(&module_path, &module_body)?;
+
+    let create_mod = send_json(
+        &app,
+        "POST",
+        "/modules",
+        json!({
+            "id": "nul",
+            "name": "Nul",
+            "type": "sandbox",
+            "description": "Prints a NUL byte",
+            "file_url": module_path.to_string_lossy(),
+            "digest": format!("sha256:{:x}", Sha256::digest(&module_body)),
+        }),
+    )
+    .await?;
+    assert_eq!(create_mod.status(), StatusCode::CREATED);
+    let enable_mod =
+        send_json(&app, "PATCH", "/modules/nul", json!({ "is_enabled": true })).await?;
+    assert_eq!(enable_mod.status(), StatusCode::OK);
+
+    let run_resp = send_json(&app, "POST", "/modules/nul/executions", json!({})).await?;
+    assert_eq!(run_resp.status(), StatusCode::CREATED);
+
+    let execution: serde_json::Value = serde_json::from_slice(&read_body(run_resp).await?)?;
+    assert_eq!(execution["status"], "completed");
+    assert_eq!(execution["stdout"], "he\u{FFFD}llo");
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn abandoned_executions_fail_once_their_lease_expires() -> Result<()> {
+    use diesel::RunQueryDsl;
+
+    let (pg_container, s3_container, state) = setup_state().await?;
+    let app = app(state.clone());
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let module_path = temp_dir.path().join("hello.wasm");
+    let module_body = wat::parse_str(HELLO_SANDBOX)?;
+    std::fs::write(&module_path, &module_body)?;
+
+    let create_mod = send_json(
+        &app,
+        "POST",
+        "/modules",
+        json!({
+            "id": "hello",
+            "name": "Hello",
+            "type": "sandbox",
+            "description": "Says hello",
+            "file_url": module_path.to_string_lossy(),
+            "digest": format!("sha256:{:x}", Sha256::digest(&module_body)),
+        }),
+    )
+    .await?;
+    assert_eq!(create_mod.status(), StatusCode::CREATED);
+
+    let abandoned = uuid::Uuid::new_v4();
+    let alive = uuid::Uuid::new_v4();
+    let mut conn = state.db_pool.get()?;
+    diesel::sql_query(format!(
+        "INSERT INTO executions (id, module_id, heartbeat_at) VALUES \
+         ('{}', 'hello', NOW() - INTERVAL '5 minutes'), ('{}', 'hello', NOW())",
+        abandoned, alive
+    ))
+    .execute(&mut conn)?;
+
+    assert_eq!(executions_services::fail_abandoned(&mut conn)?, 1);
+    assert_eq!(
+        executions_services::get_execution(&mut conn, "hello", abandoned)?.status,
+        ExecutionStatus::Failed
+    );
+    assert_eq!(
+        executions_services::get_execution(&mut conn, "hello", alive)?.status,
+        ExecutionStatus::Running
+    );
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn http_definitions_perform_their_request_template() -> Result<()> {
+    let (pg_container, s3_container, app) = setup_app().await?;
+
+    let mock = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/users/jane%20doe"))
+        .and(header("authorization", "Bearer s3cr3t"))
+        .and(query_param("limit", "5"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "name": "Jane Doe" })))
+        .expect(1)
+        .mount(&mock)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/users/huge"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("x".repeat(10 * 1024 * 1024 + 1)))
+        .mount(&mock)
+        .await;
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let document = serde_json::to_vec(&json!({
+        "format_version": 1,
+        "name": "Get user",
+        "description": "Looks a user up",
+        "input_schema": { "type": "object" },
+        "request": {
+            "url": "{{configuration.base_url}}/users/{{arguments.user}}",
+            "headers": { "Authorization": "Bearer {{secrets.token}}" },
+            "query": { "limit": "{{arguments.limit}}", "cursor": "{{arguments.cursor}}" }
+        }
+    }))?;
+    let document_path = temp_dir.path().join("get-user.json");
+    std::fs::write(&document_path, &document)?;
+
+    let create_def = send_json(
+        &app,
+        "POST",
+        "/definitions",
+        json!({
+            "id": "get-user",
+            "name": "Get user",
+            "type": "http",
+            "description": "Looks a user up",
+            "file_url": document_path.to_string_lossy(),
+            "digest": format!("sha256:{:x}", Sha256::digest(&document)),
+        }),
+    )
+    .await?;
+    assert_eq!(create_def.status(), StatusCode::CREATED);
+
+    for (method, uri, body) in [
+        (
+            "PUT",
+            "/definitions/get-user/configuration",
+            json!({ "base_url": format!("{}/v1", mock.uri()) }),
+        ),
+        (
+            "PUT",
+            "/definitions/get-user/secret",
+            json!({ "token": "s3cr3t" }),
+        ),
+        (
+            "PATCH",
+            "/definitions/get-user",
+            json!({ "is_enabled": true }),
+        ),
+    ] {
+        let response = send_json(&app, method, uri, body).await?;
+        assert_eq!(response.status(), StatusCode::OK, "{} {}", method, uri);
+    }
+
+    let invoke = send_json(
+        &app,
+        "POST",
+        "/definitions/get-user/invoke",
+        json!({ "arguments": { "user": "jane doe", "limit": 5 } }),
+    )
+    .await?;
+    assert_eq!(invoke.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_slice(&read_body(invoke).await?)?;
+    assert_eq!(body["result"], json!({ "name": "Jane Doe" }));
+
+    let oversized = send_json(
+        &app,
+        "POST",
+        "/definitions/get-user/invoke",
+        json!({ "arguments": { "user": "huge" } }),
+    )
+    .await?;
+    assert_eq!(oversized.status(), StatusCode::BAD_GATEWAY);
+
+    let language = send_json(
+        &app,
+        "PUT",
+        "/languages/http",
+        json!({ "module_id": "anything" }),
+    )
+    .await?;
+    assert_eq!(language.status(), StatusCode::CONFLICT);
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn mcp_endpoint_lists_and_calls_enabled_definitions() -> Result<()> {
+    let (pg_container, s3_container, app) = setup_app().await?;
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let document = serde_json::to_vec(&json!({
+        "format_version": 1,
+        "name": "Lookup",
+        "description": "Looks a record up",
+        "input_schema": {
+            "type": "object",
+            "properties": { "key": { "type": "string" } },
+            "required": ["key"]
+        }
+    }))?;
+    let document_path = temp_dir.path().join("lookup.json");
+    std::fs::write(&document_path, &document)?;
+
+    for (id, enabled) in [("lookup", true), ("hidden", false)] {
+        let create_def = send_json(
+            &app,
+            "POST",
+            "/definitions",
+            json!({
+                "id": id,
+                "name": "Registry Name",
+                "type": "remote",
+                "description": "Registry description",
+                "file_url": document_path.to_string_lossy(),
+                "digest": format!("sha256:{:x}", Sha256::digest(&document)),
+            }),
+        )
+        .await?;
+        assert_eq!(create_def.status(), StatusCode::CREATED);
+
+        let update = send_json(
+            &app,
+            "PATCH",
+            &format!("/definitions/{}", id),
+            json!({ "is_enabled": enabled }),
+        )
+        .await?;
+        assert_eq!(update.status(), StatusCode::OK);
+    }
+
+    let module_path = temp_dir.path().join("backend.wasm");
+    let module_body = common::wasm_responder(ModuleType::Proxy, r#"{"result":{"value":42}}"#);
+    std::fs::write(&module_path, &module_body)?;
+
+    let create_mod = send_json(
+        &app,
+        "POST",
+        "/modules",
+        json!({
+            "id": "backend",
+            "name": "Backend",
+            "type": "proxy",
+            "description": "Answers lookups",
+            "file_url": module_path.to_string_lossy(),
+            "digest": format!("sha256:{:x}", Sha256::digest(&module_body)),
+            "capabilities": { "allowed_hosts": ["backend.internal"] },
+        }),
+    )
+    .await?;
+    assert_eq!(create_mod.status(), StatusCode::CREATED);
+
+    send_json(
+        &app,
+        "PATCH",
+        "/modules/backend",
+        json!({ "is_enabled": true }),
+    )
+    .await?;
+    let bind_resp = send_json(
+        &app,
+        "PUT",
+        "/definitions/lookup/proxy",
+        json!({ "module_id": "backend", "upstream_url": "https://backend.internal/" }),
+    )
+    .await?;
+    assert_eq!(bind_resp.status(), StatusCode::OK);
+
+    let rpc = |id: i64, method: &str, params: serde_json::Value| json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+
+    let init_resp = send_json(
+        &app,
+        "POST",
+        "/mcp",
+        rpc(
+            1,
+            "initialize",
+            json!({
+                "protocolVersion": "2025-03-26",
+                "capabilities": {},
+                "clientInfo": { "name": "test", "version": "1.0" }
+            }),
+        ),
+    )
+    .await?;
+    assert_eq!(init_resp.status(), StatusCode::OK);
+    let session_id = init_resp
+        .headers()
+        .get("mcp-session-id")
+        .expect("initialize should start a session")
+        .to_str()?
+        .to_string();
+    let init: serde_json::Value = serde_json::from_slice(&read_body(init_resp).await?)?;
+    assert_eq!(init["id"], 1);
+    assert_eq!(init["result"]["protocolVersion"], "2025-03-26");
+
+    let initialized = send_mcp(
+        &app,
+        &session_id,
+        json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+    )
+    .await?;
+    assert_eq!(initialized.status(), StatusCode::ACCEPTED);
+
+    let list_resp = send_mcp(&app, &session_id, rpc(2, "tools/list", json!({}))).await?;
+    let list: serde_json::Value = serde_json::from_slice(&read_body(list_resp).await?)?;
+    let tools = list["result"]["tools"].as_array().unwrap();
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0]["name"], "lookup");
+    assert_eq!(tools[0]["title"], "Lookup");
+    assert_eq!(tools[0]["description"], "Looks a record up");
+    assert_eq!(tools[0]["inputSchema"]["required"], json!(["key"]));
+    assert!(list["result"].get("nextCursor").is_none());
+
+    let bad_cursor_resp = send_mcp(
+        &app,
+        &session_id,
+        rpc(2, "tools/list", json!({ "cursor": "not-a-cursor" })),
+    )
+    .await?;
+    let bad_cursor: serde_json::Value = serde_json::from_slice(&read_body(bad_cursor_resp).await?)?;
+    assert_eq!(bad_cursor["error"]["code"], -32602);
+
+    let invalid_resp = send_mcp(
+        &app,
+        &session_id,
+        rpc(
+            3,
+            "tools/call",
+            json!({ "name": "lookup", "arguments": {} }),
+        ),
+    )
+    .await?;
+    let invalid: serde_json::Value = serde_json::from_slice(&read_body(invalid_resp).await?)?;
+    assert_eq!(invalid["result"]["isError"], true);
+
+    let call_resp = send_mcp(
+        &app,
+        &session_id,
+        rpc(
+            4,
+            "tools/call",
+            json!({ "name": "lookup", "arguments": { "key": "answer" } }),
+        ),
+    )
+    .await?;
+    let call: serde_json::Value = serde_json::from_slice(&read_body(call_resp).await?)?;
+    assert_eq!(call["result"]["isError"], false);
+    assert_eq!(call["result"]["structuredContent"], json!({ "value": 42 }));
+
+    let hidden_resp = send_mcp(
+        &app,
+        &session_id,
+        rpc(5, "tools/call", json!({ "name": "hidden" })),
+    )
+    .await?;
+    let hidden: serde_json::Value = serde_json::from_slice(&read_body(hidden_resp).await?)?;
+    assert_eq!(hidden["error"]["code"], -32602);
+
+    let unknown_resp = send_mcp(&app, &session_id, rpc(6, "resources/list", json!({}))).await?;
+    let unknown: serde_json::Value = serde_json::from_slice(&read_body(unknown_resp).await?)?;
+    assert_eq!(unknown["error"]["code"], -32601);
+
+    let missing_session = send_json(&app, "POST", "/mcp", rpc(7, "ping", json!({}))).await?;
+    assert_eq!(missing_session.status(), StatusCode::BAD_REQUEST);
+    let unknown_session = send_mcp(&app, "unknown", rpc(8, "ping", json!({}))).await?;
+    assert_eq!(unknown_session.status(), StatusCode::NOT_FOUND);
+
+    let ping_with = |name: &'static str, value: &'static str| {
+        Request::builder()
+            .method("POST")
+            .uri("/mcp")
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .header("mcp-session-id", &session_id)
+            .header(name, value)
+            .body(Body::from(
+                serde_json::to_vec(&rpc(8, "ping", json!({}))).unwrap(),
+            ))
+            .unwrap()
+    };
+    let current_version = app
+        .clone()
+        .oneshot(ping_with("mcp-protocol-version", "2025-03-26"))
+        .await?;
+    assert_eq!(current_version.status(), StatusCode::OK);
+    let other_version = app
+        .clone()
+        .oneshot(ping_with("mcp-protocol-version", "2025-06-18"))
+        .await?;
+    assert_eq!(other_version.status(), StatusCode::BAD_REQUEST);
+    let foreign_origin = app
+        .clone()
+        .oneshot(ping_with("origin", "http://attacker.example"))
+        .await?;
+    assert_eq!(foreign_origin.status(), StatusCode::FORBIDDEN);
+
+    let stream_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/mcp")
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .header(http::header::ACCEPT, "application/json, text/event-stream")
+                .header("mcp-session-id", &session_id)
+                .body(Body::from(serde_json::to_vec(&rpc(
+                    9,
+                    "tools/call",
+                    json!({
+                        "name": "lookup",
+                        "arguments": { "key": "answer" },
+                        "_meta": { "progressToken": "call-9" }
+                    }),
+                ))?))
+                .unwrap(),
+        )
+        .await?;
+    assert_eq!(stream_resp.status(), StatusCode::OK);
+    assert_eq!(
+        stream_resp.headers()[http::header::CONTENT_TYPE],
+        "text/event-stream"
+    );
+    let events = String::from_utf8(read_body(stream_resp).await?.to_vec())?;
+    let messages: Vec<serde_json::Value> = events
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    let (progress, response) = messages.split_at(messages.len() - 1);
+    assert!(!progress.is_empty());
+    assert!(progress.iter().all(|message| {
+        message["method"] == "notifications/progress"
+            && message["params"]["progressToken"] == "call-9"
+    }));
+    assert_eq!(response[0]["id"], 9);
+    assert_eq!(
+        response[0]["result"]["structuredContent"],
+        json!({ "value": 42 })
+    );
+
+    let close = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/mcp")
+                .header("mcp-session-id", &session_id)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await?;
+    assert_eq!(close.status(), StatusCode::NO_CONTENT);
+    let closed_session = send_mcp(&app, &session_id, rpc(10, "ping", json!({}))).await?;
+    assert_eq!(closed_session.status(), StatusCode::NOT_FOUND);
+
+    let malformed = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/mcp")
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(Body::from("{"))
+                .unwrap(),
+        )
+        .await?;
+    assert_eq!(malformed.status(), StatusCode::BAD_REQUEST);
+    let parse_error: serde_json::Value = serde_json::from_slice(&read_body(malformed).await?)?;
+    assert_eq!(parse_error["error"]["code"], -32700);
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn mcp_stdio_transport_answers_each_line() -> Result<()> {
+    let (pg_container, s3_container, state) = setup_state().await?;
+    let app = app(state.clone());
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let document = serde_json::to_vec(&json!({
+        "format_version": 1,
+        "name": "Echo",
+        "description": "Echoes its input",
+        "input_schema": { "type": "object" }
+    }))?;
+    let document_path = temp_dir.path().join("echo.json");
+    std::fs::write(&document_path, &document)?;
+
+    let create_def = send_json(
+        &app,
+        "POST",
+        "/definitions",
+        json!({
+            "id": "echo",
+            "name": "Echo",
+            "type": "remote",
+            "description": "Echoes its input",
+            "file_url": document_path.to_string_lossy(),
+            "digest": format!("sha256:{:x}", Sha256::digest(&document)),
+        }),
+    )
+    .await?;
+    assert_eq!(create_def.status(), StatusCode::CREATED);
+    send_json(
+        &app,
+        "PATCH",
+        "/definitions/echo",
+        json!({ "is_enabled": true }),
+    )
+    .await?;
+
+    let input = [
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "protocolVersion": "2025-06-18" } }),
+        json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }),
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "ping" }),
+    ]
+    .iter()
+    .map(|message| format!("{}\n", message))
+    .collect::<String>()
+        + "not json\n";
+
+    let mut output = Vec::new();
+    mci::mcp::stdio::serve(state, input.as_bytes(), &mut output).await?;
+
+    let mut responses: Vec<serde_json::Value> = String::from_utf8(output)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    responses.sort_by_key(|response| response["id"].as_i64());
+
+    assert_eq!(responses.len(), 4);
+    assert_eq!(responses[0]["error"]["code"], -32700);
+    assert_eq!(responses[1]["result"]["protocolVersion"], "2025-06-18");
+    assert_eq!(
+        responses[1]["result"]["capabilities"]["tools"]["listChanged"],
+        false
+    );
+    assert_eq!(responses[2]["result"]["tools"][0]["name"], "echo");
+    assert_eq!(responses[3]["result"], json!({}));
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn mcp_sessions_are_notified_when_tool_list_changes() -> Result<()> {
+    let (pg_container, s3_container, state) = setup_state().await?;
+    tokio::spawn(mci::mcp::run_list_change_notifier(
+        state.mcp_sessions.clone(),
+        state.events.subscribe(),
+    ));
+    let app = app(state);
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let document_path = temp_dir.path().join("def.json");
+    let document = &common::definition_document("Notify")[..];
+    std::fs::write(&document_path, document)?;
+
+    let create_def = send_json(
+        &app,
+        "POST",
+        "/definitions",
+        json!({
+            "id": "notify",
+            "name": "Notify",
+            "type": "remote",
+            "description": "Toggled during the test",
+            "file_url": document_path.to_string_lossy(),
+            "digest": format!("sha256:{:x}", Sha256::digest(document)),
+        }),
+    )
+    .await?;
+    assert_eq!(create_def.status(), StatusCode::CREATED);
+
+    let init_resp = send_json(
+        &app,
+        "POST",
+        "/mcp",
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": { "protocolVersion": "2025-06-18" }
+        }),
+    )
+    .await?;
+    let session_id = init_resp.headers()["mcp-session-id"].to_str()?.to_string();
+
+    let stream_resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/mcp")
+                .header(http::header::ACCEPT, "text/event-stream")
+                .header("mcp-session-id", &session_id)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await?;
+    assert_eq!(stream_resp.status(), StatusCode::OK);
+    let mut stream = stream_resp.into_body();
+
+    let update = send_json(
+        &app,
+        "PATCH",
+        "/definitions/notify",
+        json!({ "is_enabled": true }),
+    )
+    .await?;
+    assert_eq!(update.status(), StatusCode::OK);
+
+    let frame = tokio::time::timeout(std::time::Duration::from_secs(5), stream.frame())
+        .await?
+        .expect("stream should stay open")?;
+    let event = String::from_utf8(frame.into_data().unwrap().to_vec())?;
+    assert!(event.contains("notifications/tools/list_changed"));
+
+    pg_container.stop().await.ok();
+    s3_container.stop().await.ok();
+
+    Ok(())
+}
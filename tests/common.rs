@@ -1,6 +1,8 @@
+use aes_gcm::{aead::OsRng, Aes256Gcm, KeyInit};
 use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD, Engine};
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
-use mci::{db, s3};
+use mci::{db, models::ModuleType, s3};
 use testcontainers_modules::{
     minio, postgres,
     testcontainers::{runners::AsyncRunner, ContainerAsync},
@@ -22,6 +24,24 @@ pub async fn initialize_s3() -> Result<(ContainerAsync<minio::MinIO>, aws_sdk_s3
     Ok((container, client))
 }
 
+/// A fresh base64 encoded secrets master key.
+#[allow(dead_code)]
+pub fn master_key() -> String {
+    STANDARD.encode(Aes256Gcm::generate_key(OsRng))
+}
+
+/// A minimal definition document in the current format.
+#[allow(dead_code)]
+pub fn definition_document(name: &str) -> Vec<u8> {
+    serde_json::to_vec(&serde_json::json!({
+        "format_version": 1,
+        "name": name,
+        "description": format!("{} definition", name),
+        "input_schema": { "type": "object" },
+    }))
+    .unwrap()
+}
+
 #[allow(dead_code)]
 pub async fn initialize_pg() -> Result<(ContainerAsync<postgres::Postgres>, db::PgPool)> {
     let container = postgres::Postgres::default().start().await?;
@@ -43,3 +63,67 @@ pub async fn initialize_pg() -> Result<(ContainerAsync<postgres::Postgres>, db::
 
     Ok((container, pool))
 }
+
+/// Builds a minimal module exposing the exports required for `module_type`.
+/// `marker` is embedded in a data segment so callers can produce distinct
+/// binaries (and digests) for the same type.
+#[allow(dead_code)]
+pub fn wasm_module(module_type: ModuleType, marker: &str) -> Vec<u8> {
+    let entrypoints: &[&str] = match module_type {
+        ModuleType::Language => &["mci_language_eval"],
+        ModuleType::Sandbox => &[],
+        ModuleType::Interceptor => &["mci_intercept_request", "mci_intercept_response"],
+        ModuleType::Proxy => &["mci_proxy_forward"],
+        ModuleType::Hook => &["mci_hook_handle"],
+    };
+
+    let mut funcs = String::new();
+    if module_type == ModuleType::Sandbox {
+        funcs.push_str(r#"(func (export "_start"))"#);
+    } else {
+        funcs.push_str(r#"(func (export "mci_alloc") (param i32) (result i32) i32.const 0)"#);
+    }
+    for name in entrypoints {
+        funcs.push_str(&format!(
+            r#"(func (export "{}") (param i32 i32) (result i64) i64.const 0)"#,
+            name
+        ));
+    }
+
+    wat::parse_str(format!(
+        r#"(module (memory (export "memory") 1) {} (data (i32.const 0) "{}"))"#,
+        funcs, marker
+    ))
+    .expect("fixture module should be valid")
+}
+
+/// Builds a module of `module_type` whose entrypoints all ignore their input
+/// and return `output`.
+#[allow(dead_code)]
+pub fn wasm_responder(module_type: ModuleType, output: &str) -> Vec<u8> {
+    let entrypoints: &[&str] = match module_type {
+        ModuleType::Language => &["mci_language_eval"],
+        ModuleType::Sandbox => panic!("sandbox modules have no entrypoints"),
+        ModuleType::Interceptor => &["mci_intercept_request", "mci_intercept_response"],
+        ModuleType::Proxy => &["mci_proxy_forward"],
+        ModuleType::Hook => &["mci_hook_handle"],
+    };
+
+    // Inputs are written past the output so they cannot overwrite it.
+    let mut funcs =
+        String::from(r#"(func (export "mci_alloc") (param i32) (result i32) i32.const 4096)"#);
+    for name in entrypoints {
+        funcs.push_str(&format!(
+            r#"(func (export "{}") (param i32 i32) (result i64) i64.const {})"#,
+            name,
+            output.len()
+        ));
+    }
+
+    wat::parse_str(format!(
+        r#"(module (memory (export "memory") 1) {} (data (i32.const 0) "{}"))"#,
+        funcs,
+        output.replace('\\', "\\\\").replace('"', "\\\"")
+    ))
+    .expect("fixture module should be valid")
+}
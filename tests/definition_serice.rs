@@ -1,7 +1,8 @@
 use anyhow::Result;
 use diesel::prelude::*;
 use mci::{
-    models::{Definition, NewDefinition},
+    models::{Definition, NewDefinition, UpgradeOptions},
+    s3::UploadOptions,
     schema::definitions::dsl::*,
     services::definitions_services::{
         create_definition, create_definition_from_registry, list_definitions,
@@ -26,7 +27,7 @@ async fn create_definition_from_http_source() -> Result<()> {
         .await?;
 
     let mock = MockServer::start().await;
-    let file_body = b"hello-def";
+    let file_body = &common::definition_document("hello-def")[..];
     let digest_str = format!("sha256:{:x}", Sha256::digest(file_body));
 
     Mock::given(method("GET"))
@@ -45,8 +46,11 @@ async fn create_definition_from_http_source() -> Result<()> {
                 r#type: "example-type".into(),
                 description: "Example description".into(),
                 file_url: format!("{}/file.json", mock.uri()),
-                digest: digest_str.clone(),
+                digest: Some(digest_str.clone()),
+                allow_unpinned: false,
                 source_url: Some(format!("{}/meta.json", mock.uri())),
+                default_configuration: None,
+                configuration_schema: None,
             }),
         )
         .mount(&mock)
@@ -68,12 +72,23 @@ async fn create_definition_from_http_source() -> Result<()> {
                 r#type: "example-type".into(),
                 description: "Example description".into(),
                 file_url: format!("{}/file.json", mock.uri()),
-                digest: digest_for_task,
+                digest: Some(digest_for_task),
+                allow_unpinned: false,
                 source_url: Some(meta_url.clone()),
+                default_configuration: None,
+                configuration_schema: None,
             };
 
             tokio::runtime::Handle::current().block_on(async {
-                create_definition(&mut conn, &http_client, &s3_client, &payload).await
+                create_definition(
+                    &mut conn,
+                    &http_client,
+                    &s3_client,
+                    &UploadOptions::default(),
+                    &payload,
+                    |_| async { Ok(()) },
+                )
+                .await
             })
         }
     })
@@ -114,7 +129,7 @@ async fn create_definition_conflict_errors() -> Result<()> {
     let mock = MockServer::start().await;
     let mock_uri = mock.uri();
 
-    let file_body = b"hello-conflict";
+    let file_body = &common::definition_document("hello-conflict")[..];
     let digest_str = format!("sha256:{:x}", Sha256::digest(file_body));
 
     Mock::given(method("GET"))
@@ -132,8 +147,11 @@ async fn create_definition_conflict_errors() -> Result<()> {
                 r#type: "t".into(),
                 description: "d".into(),
                 file_url: format!("{}/file.json", mock_uri.clone()),
-                digest: digest_str.clone(),
+                digest: Some(digest_str.clone()),
+                allow_unpinned: false,
                 source_url: Some(format!("{}/meta.json", mock_uri.clone())),
+                default_configuration: None,
+                configuration_schema: None,
             }),
         )
         .mount(&mock)
@@ -159,12 +177,23 @@ async fn create_definition_conflict_errors() -> Result<()> {
                 r#type: "t".into(),
                 description: "d".into(),
                 file_url: file_url.clone(),
-                digest: digest_for_task.clone(),
+                digest: Some(digest_for_task.clone()),
+                allow_unpinned: false,
                 source_url: None,
+                default_configuration: None,
+                configuration_schema: None,
             };
             tokio::runtime::Handle::current()
                 .block_on(async {
-                    create_definition(&mut conn, &http_client, &s3_client, &payload).await
+                    create_definition(
+                        &mut conn,
+                        &http_client,
+                        &s3_client,
+                        &UploadOptions::default(),
+                        &payload,
+                        |_| async { Ok(()) },
+                    )
+                    .await
                 })
                 .map(|_| ())
         }
@@ -189,11 +218,22 @@ async fn create_definition_conflict_errors() -> Result<()> {
                 r#type: "t".into(),
                 description: "d".into(),
                 file_url,
-                digest: digest_for_task,
+                digest: Some(digest_for_task),
+                allow_unpinned: false,
                 source_url: Some(meta_url),
+                default_configuration: None,
+                configuration_schema: None,
             };
             tokio::runtime::Handle::current().block_on(async {
-                create_definition(&mut conn, &http_client, &s3_client, &payload).await
+                create_definition(
+                    &mut conn,
+                    &http_client,
+                    &s3_client,
+                    &UploadOptions::default(),
+                    &payload,
+                    |_| async { Ok(()) },
+                )
+                .await
             })?;
             Ok(())
         }
@@ -226,7 +266,7 @@ async fn create_definition_from_registry_sets_source_url() -> Result<()> {
         .await?;
 
     let mock = MockServer::start().await;
-    let file_body = b"registry-body";
+    let file_body = &common::definition_document("registry-body")[..];
     let digest_str = format!("sha256:{:x}", Sha256::digest(file_body));
     let registry_url = format!("{}/registry.json", mock.uri());
 
@@ -245,8 +285,11 @@ async fn create_definition_from_registry_sets_source_url() -> Result<()> {
                 r#type: "reg-type".into(),
                 description: "reg-desc".into(),
                 file_url: format!("{}/file.json", mock.uri()),
-                digest: digest_str.clone(),
+                digest: Some(digest_str.clone()),
+                allow_unpinned: false,
                 source_url: None,
+                default_configuration: None,
+                configuration_schema: None,
             }),
         )
         .mount(&mock)
@@ -263,8 +306,15 @@ async fn create_definition_from_registry_sets_source_url() -> Result<()> {
         move || -> Result<Definition> {
             let mut conn = pool.get()?;
             tokio::runtime::Handle::current().block_on(async {
-                create_definition_from_registry(&mut conn, &http_client, &s3_client, &registry_url)
-                    .await
+                create_definition_from_registry(
+                    &mut conn,
+                    &http_client,
+                    &s3_client,
+                    &UploadOptions::default(),
+                    &registry_url,
+                    |_| async { Ok(()) },
+                )
+                .await
             })
         }
     })
@@ -303,10 +353,10 @@ async fn update_definition_from_source_updates_when_digest_changes() -> Result<(
 
     let mock = MockServer::start().await;
 
-    let old_body = b"old-body";
+    let old_body = &common::definition_document("old-body")[..];
     let old_digest = format!("sha256:{:x}", Sha256::digest(old_body));
 
-    let new_body = b"new-body";
+    let new_body = &common::definition_document("new-body")[..];
     let new_digest = format!("sha256:{:x}", Sha256::digest(new_body));
 
     Mock::given(method("GET"))
@@ -324,8 +374,11 @@ async fn update_definition_from_source_updates_when_digest_changes() -> Result<(
                 r#type: "new-type".into(),
                 description: "New Desc".into(),
                 file_url: format!("{}/file-new.json", mock.uri()),
-                digest: new_digest.clone(),
+                digest: Some(new_digest.clone()),
+                allow_unpinned: false,
                 source_url: Some(format!("{}/meta.json", mock.uri())),
+                default_configuration: None,
+                configuration_schema: None,
             }),
         )
         .mount(&mock)
@@ -348,6 +401,7 @@ async fn update_definition_from_source_updates_when_digest_changes() -> Result<(
                     secrets_object_key: "def-4".into(),
                     digest: old_digest,
                     source_url: Some(format!("{}/meta.json", mock.uri())),
+                    is_pinned: true,
                 })
                 .execute(&mut conn)?;
             Ok(())
@@ -357,19 +411,32 @@ async fn update_definition_from_source_updates_when_digest_changes() -> Result<(
 
     let http_client = reqwest::Client::new();
 
-    tokio::task::spawn_blocking({
+    let upgrade = || {
         let pool = pool.clone();
         let http_client = http_client.clone();
         let s3_client = s3_client.clone();
 
-        move || -> Result<Definition> {
+        tokio::task::spawn_blocking(move || -> Result<(Definition, bool)> {
             let mut conn = pool.get()?;
             tokio::runtime::Handle::current().block_on(async {
-                update_definition_from_source(&mut conn, &http_client, &s3_client, "def-4").await
+                update_definition_from_source(
+                    &mut conn,
+                    &http_client,
+                    &s3_client,
+                    &UploadOptions::default(),
+                    "def-4",
+                    UpgradeOptions::default(),
+                    |_| async { Ok(()) },
+                )
+                .await
             })
-        }
-    })
-    .await??;
+        })
+    };
+
+    let (_, upgraded) = upgrade().await??;
+    assert!(upgraded);
+    let (_, upgraded) = upgrade().await??;
+    assert!(!upgraded);
 
     let updated = tokio::task::spawn_blocking({
         let pool = pool.clone();
@@ -414,6 +481,7 @@ async fn list_definitions_filters_and_sorting() -> Result<()> {
                         secrets_object_key: "k1".into(),
                         digest: "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".into(),
                         source_url: None,
+                        is_pinned: true,
                     },
                     NewDefinition {
                         id: "b2".into(),
@@ -425,6 +493,7 @@ async fn list_definitions_filters_and_sorting() -> Result<()> {
                         secrets_object_key: "k2".into(),
                         digest: "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".into(),
                         source_url: None,
+                        is_pinned: true,
                     },
                     NewDefinition {
                         id: "c3".into(),
@@ -436,6 +505,7 @@ async fn list_definitions_filters_and_sorting() -> Result<()> {
                         secrets_object_key: "k3".into(),
                         digest: "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".into(),
                         source_url: None,
+                        is_pinned: true,
                     },
                 ])
                 .execute(&mut conn)?;
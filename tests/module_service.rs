@@ -1,7 +1,7 @@
 use anyhow::Result;
 use diesel::prelude::*;
 use mci::{
-    models::{Module, ModuleType, NewModule},
+    models::{Capabilities, Module, ModuleType, NewModule},
     schema::modules::dsl::*,
     services::modules_services::{list_modules, ModuleFilter, SortBy, SortOrder},
 };
@@ -29,6 +29,8 @@ async fn list_modules_filters_and_sorting() -> Result<()> {
                         digest: "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
                             .into(),
                         source_url: None,
+                        capabilities: Capabilities::default(),
+                        is_pinned: true,
                     },
                     NewModule {
                         id: "m2".into(),
@@ -41,6 +43,8 @@ async fn list_modules_filters_and_sorting() -> Result<()> {
                         digest: "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
                             .into(),
                         source_url: None,
+                        capabilities: Capabilities::default(),
+                        is_pinned: true,
                     },
                     NewModule {
                         id: "m3".into(),
@@ -53,6 +57,8 @@ async fn list_modules_filters_and_sorting() -> Result<()> {
                         digest: "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
                             .into(),
                         source_url: None,
+                        capabilities: Capabilities::default(),
+                        is_pinned: true,
                     },
                 ])
                 .execute(&mut conn)?;
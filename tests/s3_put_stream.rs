@@ -1,7 +1,13 @@
 use anyhow::{Context, Result};
 use aws_smithy_types::byte_stream::ByteStream;
-use mci::s3;
+use bytes::Bytes;
+use futures::{stream, StreamExt};
+use mci::{
+    s3::{self, UploadOptions},
+    utils::digest_utils,
+};
 use sha2::Digest;
+use std::{collections::HashMap, time::Duration};
 use uuid::Uuid;
 
 mod common;
@@ -15,7 +21,7 @@ async fn put_stream_uploads_object() -> Result<()> {
     let key = "hello.txt";
     let body = ByteStream::from_static(b"hello from put_stream");
 
-    s3::put_stream(&client, &bucket, key, body, None).await?;
+    s3::put_stream(&client, &bucket, key, body, None, &UploadOptions::default()).await?;
 
     let got = client
         .get_object()
@@ -47,6 +53,7 @@ async fn put_stream_validates_digest() -> Result<()> {
         key,
         ByteStream::from_static(&content[..]),
         Some(&expected),
+        &UploadOptions::default(),
     )
     .await?;
 
@@ -73,6 +80,7 @@ async fn put_stream_rejects_bad_digest() -> Result<()> {
         key,
         body,
         Some("sha256:0000000000000000000000000000000000000000000000000000000000000000"),
+        &UploadOptions::default(),
     )
     .await;
 
@@ -91,7 +99,15 @@ async fn put_stream_errors_on_invalid_digest_format() -> Result<()> {
     let key = "bad-format.txt";
     let body = ByteStream::from_static(b"content");
 
-    let result = s3::put_stream(&client, &bucket, key, body, Some("badformat")).await;
+    let result = s3::put_stream(
+        &client,
+        &bucket,
+        key,
+        body,
+        Some("badformat"),
+        &UploadOptions::default(),
+    )
+    .await;
 
     assert!(result.is_err(), "expected invalid digest format to error");
 
@@ -108,7 +124,15 @@ async fn put_stream_errors_on_unsupported_algorithm() -> Result<()> {
     let key = "unsupported-algo.txt";
     let body = ByteStream::from_static(b"content");
 
-    let result = s3::put_stream(&client, &bucket, key, body, Some("md5:abcd")).await;
+    let result = s3::put_stream(
+        &client,
+        &bucket,
+        key,
+        body,
+        Some("md5:abcd"),
+        &UploadOptions::default(),
+    )
+    .await;
 
     assert!(result.is_err(), "expected unsupported algorithm to error");
 
@@ -125,7 +149,7 @@ async fn put_stream_handles_empty_body() -> Result<()> {
     let key = "empty.txt";
     let body = ByteStream::from_static(b"");
 
-    s3::put_stream(&client, &bucket, key, body, None).await?;
+    s3::put_stream(&client, &bucket, key, body, None, &UploadOptions::default()).await?;
 
     let got = client
         .get_object()
@@ -140,3 +164,235 @@ async fn put_stream_handles_empty_body() -> Result<()> {
     container.stop().await.ok();
     Ok(())
 }
+
+#[tokio::test]
+async fn put_stream_keeps_existing_object_when_digest_mismatches() -> Result<()> {
+    let (container, client) = common::initialize_s3().await?;
+    let bucket = format!("test-bucket-{}", Uuid::new_v4());
+    client.create_bucket().bucket(&bucket).send().await?;
+
+    let key = "kept.txt";
+    s3::put_stream(
+        &client,
+        &bucket,
+        key,
+        ByteStream::from_static(b"v1"),
+        None,
+        &UploadOptions::default(),
+    )
+    .await?;
+
+    let result = s3::put_stream(
+        &client,
+        &bucket,
+        key,
+        ByteStream::from_static(b"v2"),
+        Some("sha256:0000000000000000000000000000000000000000000000000000000000000000"),
+        &UploadOptions::default(),
+    )
+    .await;
+    assert!(result.is_err(), "expected digest mismatch error");
+
+    let got = client.get_object().bucket(&bucket).key(key).send().await?;
+    let bytes = got.body.collect().await?.into_bytes();
+    assert_eq!(
+        bytes.as_ref(),
+        b"v1",
+        "rejected upload must not replace the object"
+    );
+
+    let listed = client.list_objects_v2().bucket(&bucket).send().await?;
+    let keys: Vec<_> = listed
+        .contents()
+        .iter()
+        .filter_map(|object| object.key())
+        .collect();
+    assert_eq!(
+        keys,
+        vec![key],
+        "rejected upload must not leave staged objects"
+    );
+
+    container.stop().await.ok();
+    Ok(())
+}
+
+/// A body spanning several parts of the smallest size S3 accepts.
+fn multipart_content() -> Vec<u8> {
+    (0..s3::MIN_PART_SIZE * 2 + 1024)
+        .map(|i| (i % 251) as u8)
+        .collect()
+}
+
+#[tokio::test]
+async fn put_checked_uploads_large_bodies_in_parts() -> Result<()> {
+    let (container, client) = common::initialize_s3().await?;
+    let bucket = format!("test-bucket-{}", Uuid::new_v4());
+    client.create_bucket().bucket(&bucket).send().await?;
+
+    let key = "large.bin";
+    let content = multipart_content();
+    let expected = format!("sha256:{:x}", sha2::Sha256::digest(&content));
+    let verifier = digest_utils::DigestVerifier::new(&expected)?;
+
+    s3::put_checked(
+        &client,
+        &bucket,
+        key,
+        ByteStream::from(content.clone()),
+        vec![Box::new(verifier)],
+        HashMap::new(),
+        &UploadOptions::new(s3::MIN_PART_SIZE, 2)?,
+    )
+    .await?;
+
+    let got = client.get_object().bucket(&bucket).key(key).send().await?;
+    assert!(
+        got.e_tag().is_some_and(|e_tag| e_tag.ends_with("-3\"")),
+        "expected a three-part upload"
+    );
+    let bytes = got.body.collect().await?.into_bytes();
+    assert_eq!(bytes.as_ref(), &content[..]);
+
+    container.stop().await.ok();
+    Ok(())
+}
+
+#[tokio::test]
+async fn put_checked_aborts_rejected_multipart_upload() -> Result<()> {
+    let (container, client) = common::initialize_s3().await?;
+    let bucket = format!("test-bucket-{}", Uuid::new_v4());
+    client.create_bucket().bucket(&bucket).send().await?;
+
+    let key = "rejected.bin";
+    let verifier = digest_utils::DigestVerifier::new(
+        "sha256:0000000000000000000000000000000000000000000000000000000000000000",
+    )?;
+
+    let result = s3::put_checked(
+        &client,
+        &bucket,
+        key,
+        ByteStream::from(multipart_content()),
+        vec![Box::new(verifier)],
+        HashMap::new(),
+        &UploadOptions::new(s3::MIN_PART_SIZE, 2)?,
+    )
+    .await;
+    assert!(result.is_err(), "expected digest mismatch error");
+
+    let listed = client.list_objects_v2().bucket(&bucket).send().await?;
+    assert!(
+        listed.contents().is_empty(),
+        "rejected upload must not be stored"
+    );
+    let uploads = client
+        .list_multipart_uploads()
+        .bucket(&bucket)
+        .send()
+        .await?;
+    assert!(
+        uploads.uploads().is_empty(),
+        "rejected upload must be aborted"
+    );
+
+    container.stop().await.ok();
+    Ok(())
+}
+
+async fn multipart_upload_count(client: &aws_sdk_s3::Client, bucket: &str) -> Result<usize> {
+    let uploads = client
+        .list_multipart_uploads()
+        .bucket(bucket)
+        .send()
+        .await?;
+    Ok(uploads.uploads().len())
+}
+
+#[tokio::test]
+async fn put_checked_aborts_cancelled_multipart_upload() -> Result<()> {
+    let (container, client) = common::initialize_s3().await?;
+    let bucket = format!("test-bucket-{}", Uuid::new_v4());
+    client.create_bucket().bucket(&bucket).send().await?;
+
+    // One part's worth of data and then a body that never ends.
+    let first = Bytes::from(vec![7u8; s3::MIN_PART_SIZE * 2]);
+    let parts = stream::iter([Ok::<_, std::io::Error>(first)]).chain(stream::pending());
+    let body = ByteStream::from_body_1_x(reqwest::Body::wrap_stream(parts));
+
+    let upload = tokio::spawn({
+        let client = client.clone();
+        let bucket = bucket.clone();
+        async move {
+            s3::put_checked(
+                &client,
+                &bucket,
+                "cancelled.bin",
+                body,
+                Vec::new(),
+                HashMap::new(),
+                &UploadOptions::new(s3::MIN_PART_SIZE, 2).unwrap(),
+            )
+            .await
+        }
+    });
+
+    let mut started = false;
+    for _ in 0..100 {
+        if multipart_upload_count(&client, &bucket).await? > 0 {
+            started = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(started, "expected a multipart upload to start");
+
+    upload.abort();
+    assert!(upload.await.unwrap_err().is_cancelled());
+
+    let mut aborted = false;
+    for _ in 0..100 {
+        if multipart_upload_count(&client, &bucket).await? == 0 {
+            aborted = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(aborted, "cancelled upload must be aborted");
+
+    container.stop().await.ok();
+    Ok(())
+}
+
+#[tokio::test]
+async fn put_stream_validates_sha512_and_blake3_digests() -> Result<()> {
+    let (container, client) = common::initialize_s3().await?;
+    let bucket = format!("test-bucket-{}", Uuid::new_v4());
+    client.create_bucket().bucket(&bucket).send().await?;
+
+    let content = b"published with a stronger digest";
+    let digests = [
+        format!("sha512:{:x}", sha2::Sha512::digest(content)),
+        format!("blake3:{}", blake3::hash(content).to_hex()),
+    ];
+
+    for (index, digest) in digests.iter().enumerate() {
+        let key = format!("digest-{}.txt", index);
+        s3::put_stream(
+            &client,
+            &bucket,
+            &key,
+            ByteStream::from_static(&content[..]),
+            Some(digest),
+            &UploadOptions::default(),
+        )
+        .await?;
+
+        let got = client.get_object().bucket(&bucket).key(&key).send().await?;
+        let bytes = got.body.collect().await?.into_bytes();
+        assert_eq!(bytes.as_ref(), content);
+    }
+
+    container.stop().await.ok();
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use anyhow::Result;
+use mci::{secrets::Keyring, services::secrets_services};
+use std::collections::BTreeMap;
+
+mod common;
+
+#[tokio::test]
+async fn rotate_secrets_rewraps_under_new_key_version() -> Result<()> {
+    let (container, client) = common::initialize_s3().await?;
+    client.create_bucket().bucket("definitions").send().await?;
+
+    let old_key = common::master_key();
+    let new_key = common::master_key();
+    let old_keyring = Keyring::new(Some(&old_key))?;
+
+    secrets_services::set_secrets(
+        &client,
+        &old_keyring,
+        "definitions",
+        "def-1",
+        BTreeMap::from([("API_KEY".to_string(), "value".to_string())]),
+    )
+    .await?;
+
+    let rotating_keyring = Keyring::with_versions(2, Some(&new_key), &[(1, old_key)])?;
+    let report =
+        secrets_services::rotate_secrets(&client, &rotating_keyring, &["definitions"]).await?;
+
+    assert_eq!(report.key_version, 2);
+    assert_eq!(report.rotated, 1);
+    assert!(report.failed.is_empty());
+
+    let second_run =
+        secrets_services::rotate_secrets(&client, &rotating_keyring, &["definitions"]).await?;
+    assert_eq!(second_run.rotated, 0);
+    assert_eq!(second_run.unchanged, 1);
+
+    let new_keyring = Keyring::with_versions(2, Some(&new_key), &[])?;
+    let secrets =
+        secrets_services::read_secrets(&client, &new_keyring, "definitions", "def-1").await?;
+    assert_eq!(secrets.get("API_KEY").map(String::as_str), Some("value"));
+
+    let metadata = mci::s3::get_metadata(&client, "definitions", "secrets/def-1").await?;
+    assert_eq!(metadata.get("key-version").map(String::as_str), Some("2"));
+
+    container.stop().await.ok();
+    Ok(())
+}
+
+#[tokio::test]
+async fn conditional_put_refuses_a_changed_object() -> Result<()> {
+    let (container, client) = common::initialize_s3().await?;
+    client.create_bucket().bucket("definitions").send().await?;
+
+    mci::s3::put_object(
+        &client,
+        "definitions",
+        "secrets/def-1",
+        b"first".to_vec().into(),
+        Default::default(),
+    )
+    .await?;
+    let read = mci::s3::get_object(&client, "definitions", "secrets/def-1")
+        .await?
+        .expect("object exists");
+    let e_tag = read.e_tag.expect("etag");
+
+    mci::s3::put_object(
+        &client,
+        "definitions",
+        "secrets/def-1",
+        b"second".to_vec().into(),
+        Default::default(),
+    )
+    .await?;
+    let written = mci::s3::put_if_match(
+        &client,
+        "definitions",
+        "secrets/def-1",
+        "stale".into(),
+        Default::default(),
+        &e_tag,
+    )
+    .await?;
+    assert!(!written);
+
+    let current = mci::s3::get_bytes(&client, "definitions", "secrets/def-1").await?;
+    assert_eq!(current.as_deref(), Some(&b"second"[..]));
+
+    container.stop().await.ok();
+    Ok(())
+}